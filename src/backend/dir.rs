@@ -0,0 +1,241 @@
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::{Record, VaultBackend, check_blob_id};
+use crate::key::EntryKey;
+use crate::storage::write_atomic;
+use crate::types::VaultConfig;
+
+/// The original layout: a directory holding `.vault`, `.index`, `store/<key>.json`,
+/// `store/<key>.link`, `trash/<key>.json` and `attachments/<id>` files
+pub struct DirBackend {
+    path: PathBuf,
+}
+
+impl DirBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Relative file name of an entry, e.g. `work/db.json`; the key is validated
+    /// so the result can never point outside the directory it is joined to
+    pub fn key_file(key: &str) -> Result<String> {
+        let key = EntryKey::parse(key)?;
+        Ok(format!(
+            "{}.json",
+            key.as_str().replace('/', std::path::MAIN_SEPARATOR_STR)
+        ))
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.path.join(".vault")
+    }
+
+    fn record_path(&self, record: Record, key: &str) -> Result<PathBuf> {
+        let file = Self::key_file(key)?;
+        Ok(match record {
+            Record::Entry => self.path.join("store").join(file),
+            Record::Alias => self.path.join("store").join(file).with_extension("link"),
+            Record::Trashed => self.path.join("trash").join(file),
+        })
+    }
+
+    /// Path of an attachment blob; the id is checked first, as it comes from
+    /// entry metadata or an import and is joined onto the vault directory
+    fn blob_path(&self, id: &str) -> Result<PathBuf> {
+        check_blob_id(id)?;
+        Ok(self.path.join("attachments").join(&id[..2]).join(id))
+    }
+
+    /// Clean up empty directories left above a removed file
+    fn remove_empty_dirs(&self, path: &Path) -> Result<()> {
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if dir == self.path.join("store")
+                || dir == self.path.join("trash")
+                || dir == self.path.join("attachments")
+            {
+                break;
+            }
+            if fs::read_dir(dir)?.next().is_none() {
+                fs::remove_dir(dir)?;
+            }
+            parent = dir.parent();
+        }
+        Ok(())
+    }
+
+    /// Walk directory tree for files with the given extension
+    fn walk(base: &Path, dir: &Path, extension: &str, keys: &mut Vec<String>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path.is_dir() {
+                Self::walk(base, &path, extension, keys)?;
+            } else if path.extension().and_then(|s| s.to_str()) == Some(extension) {
+                let relative = path
+                    .strip_prefix(base)?
+                    .with_extension("")
+                    .to_string_lossy()
+                    .replace(std::path::MAIN_SEPARATOR, "/");
+                keys.push(relative);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VaultBackend for DirBackend {
+    fn exists(&self) -> bool {
+        self.path.is_dir() && self.config_path().exists()
+    }
+
+    fn create(&self, config: &VaultConfig) -> Result<()> {
+        fs::create_dir_all(self.path.join("store"))?;
+        self.save_config(config)
+    }
+
+    fn destroy(&self) -> Result<()> {
+        // The lock file stays, so a lock held while the vault is replaced keeps
+        // other processes out until the new contents are in place
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.file_name() == Some(".lock".as_ref()) {
+                continue;
+            }
+            if path.is_dir() {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<VaultConfig> {
+        let config_data = fs::read_to_string(self.config_path())?;
+        Ok(serde_json::from_str(&config_data)?)
+    }
+
+    fn save_config(&self, config: &VaultConfig) -> Result<()> {
+        write_atomic(self.config_path(), serde_json::to_string_pretty(config)?)
+    }
+
+    fn read(&self, record: Record, key: &str) -> Result<Option<String>> {
+        match fs::read_to_string(self.record_path(record, key)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, record: Record, key: &str, data: &str) -> Result<()> {
+        let path = self.record_path(record, key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, data)
+    }
+
+    fn remove(&self, record: Record, key: &str) -> Result<bool> {
+        let path = self.record_path(record, key)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path)?;
+        self.remove_empty_dirs(&path)?;
+        Ok(true)
+    }
+
+    fn list(&self, record: Record) -> Result<Vec<String>> {
+        let (dir, extension) = match record {
+            Record::Entry => ("store", "json"),
+            Record::Alias => ("store", "link"),
+            Record::Trashed => ("trash", "json"),
+        };
+        let base = self.path.join(dir);
+        if !base.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut keys = Vec::new();
+        Self::walk(&base, &base, extension, &mut keys)?;
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn contains(&self, record: Record, key: &str) -> Result<bool> {
+        Ok(self.record_path(record, key)?.exists())
+    }
+
+    fn read_index(&self) -> Result<Option<String>> {
+        match fs::read_to_string(self.path.join(".index")) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_index(&self, data: &str) -> Result<()> {
+        write_atomic(self.path.join(".index"), data)
+    }
+
+    fn remove_index(&self) -> Result<()> {
+        match fs::remove_file(self.path.join(".index")) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn has_blob(&self, id: &str) -> Result<bool> {
+        Ok(self.blob_path(id)?.exists())
+    }
+
+    fn write_blob(
+        &self,
+        id: &str,
+        fill: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        let blob_path = self.blob_path(id)?;
+        let blob_dir = blob_path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid attachment path"))?;
+        fs::create_dir_all(blob_dir)?;
+
+        // Write to a temporary file first so a failed write never leaves a partial blob
+        let mut temp = tempfile::NamedTempFile::new_in(blob_dir)?;
+        {
+            let mut writer = io::BufWriter::new(temp.as_file_mut());
+            fill(&mut writer)?;
+            writer.flush()?;
+        }
+        temp.as_file().sync_all()?;
+        temp.persist(&blob_path)
+            .map_err(|e| anyhow!("Failed to store attachment: {}", e))?;
+        Ok(())
+    }
+
+    fn open_blob(&self, id: &str) -> Result<Option<Box<dyn Read + '_>>> {
+        match fs::File::open(self.blob_path(id)?) {
+            Ok(file) => Ok(Some(Box::new(io::BufReader::new(file)))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn remove_blob(&self, id: &str) -> Result<()> {
+        let blob_path = self.blob_path(id)?;
+        if blob_path.exists() {
+            fs::remove_file(&blob_path)?;
+            self.remove_empty_dirs(&blob_path)?;
+        }
+        Ok(())
+    }
+
+    fn lock_path(&self) -> Option<PathBuf> {
+        Some(self.path.join(".lock"))
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::{Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use super::memory::{Contents, MemoryBackend};
+use super::{Record, VaultBackend};
+use crate::crypto::Crypto;
+use crate::storage::write_atomic;
+use crate::types::{EncryptedValue, MasterKey, VaultConfig};
+
+/// Extension of single-file vaults
+pub const CONTAINER_EXTENSION: &str = "bvault";
+
+/// Version of the container format; version 1 kept its records unencrypted
+const CONTAINER_VERSION: u32 = 2;
+
+/// Looks up a vault's master key without asking for the password, e.g. in its
+/// session; None while the vault is locked
+pub type KeySource = Box<dyn Fn(&VaultConfig) -> Option<MasterKey> + Send + Sync>;
+
+#[derive(Deserialize)]
+struct Header {
+    bunker_vault: u32,
+}
+
+/// The file itself. The config stays readable, as the master key is derived
+/// from the vault id in it; every record and blob is sealed under that key.
+#[derive(Serialize, Deserialize)]
+struct Container {
+    bunker_vault: u32,
+    config: VaultConfig,
+    /// Base64 nonce and ciphertext of the JSON-encoded records
+    nonce: String,
+    records: String,
+}
+
+/// A version 1 container
+#[derive(Deserialize)]
+struct PlainContainer {
+    #[serde(flatten)]
+    contents: Contents,
+}
+
+/// What a container file was found to hold
+enum Stored {
+    Sealed(Container),
+    Plain(Contents),
+}
+
+/// A whole vault in one file, easy to copy onto a USB stick or attach to a ticket.
+///
+/// Only the vault config is readable without the master key: entries, aliases,
+/// the trash, attachments and the search index are encrypted together. The
+/// file is read on first use and rewritten atomically after every change, or
+/// once at the end of a batch.
+pub struct FileBackend {
+    path: PathBuf,
+    key: Mutex<Option<MasterKey>>,
+    key_source: KeySource,
+    loaded: Mutex<Option<MemoryBackend>>,
+    batch: Mutex<Batch>,
+}
+
+/// Changes held back by the batches in progress
+#[derive(Default)]
+struct Batch {
+    /// Batches can nest; the file is written when the outermost one ends
+    depth: usize,
+    unsaved: bool,
+}
+
+impl FileBackend {
+    pub fn new(path: PathBuf, key_source: KeySource) -> Self {
+        Self {
+            path,
+            key: Mutex::new(None),
+            key_source,
+            loaded: Mutex::new(None),
+            batch: Mutex::new(Batch::default()),
+        }
+    }
+
+    /// Run `f` on the vault's contents, reading the file the first time
+    fn with<T>(&self, f: impl FnOnce(&MemoryBackend) -> Result<T>) -> Result<T> {
+        let mut loaded = self
+            .loaded
+            .lock()
+            .map_err(|_| anyhow!("Vault contents poisoned"))?;
+        let memory = match loaded.take() {
+            Some(memory) => memory,
+            None => MemoryBackend::with_contents(self.read_contents()?),
+        };
+        let result = f(&memory);
+        *loaded = Some(memory);
+        result
+    }
+
+    /// Read the file as either a sealed container or a version 1 one
+    fn read_file(&self) -> Result<Stored> {
+        let data = fs::read(&self.path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", self.path.display(), e))?;
+        let invalid = || anyhow!("{} is not a bunker vault", self.path.display());
+        let header: Header = serde_json::from_slice(&data).map_err(|_| invalid())?;
+        match header.bunker_vault {
+            1 => {
+                let container: PlainContainer =
+                    serde_json::from_slice(&data).map_err(|_| invalid())?;
+                Ok(Stored::Plain(container.contents))
+            }
+            CONTAINER_VERSION => Ok(Stored::Sealed(
+                serde_json::from_slice(&data).map_err(|_| invalid())?,
+            )),
+            _ => Err(anyhow!(
+                "{} was written by a newer version of bunker",
+                self.path.display()
+            )),
+        }
+    }
+
+    /// Read and decrypt every record in the file
+    fn read_contents(&self) -> Result<Contents> {
+        let container = match self.read_file()? {
+            Stored::Sealed(container) => container,
+            Stored::Plain(contents) => return Ok(contents),
+        };
+        let key = self.master_key(&container.config)?;
+        let sealed = EncryptedValue {
+            nonce: BASE64.decode(&container.nonce)?,
+            ciphertext: BASE64.decode(&container.records)?,
+            salt: Vec::new(),
+        };
+        let data = Crypto::decrypt(&sealed, &key)
+            .map_err(|_| anyhow!("Cannot open {}: wrong master password", self.path.display()))?;
+        let mut contents: Contents = serde_json::from_slice(&data)?;
+        contents.config = Some(container.config);
+        Ok(contents)
+    }
+
+    /// The master key given to `unlock`, or else the one from the key source
+    fn master_key(&self, config: &VaultConfig) -> Result<MasterKey> {
+        let mut key = self.key.lock().map_err(|_| anyhow!("Vault key poisoned"))?;
+        if key.is_none() {
+            *key = (self.key_source)(config);
+        }
+        key.clone().ok_or_else(|| {
+            anyhow!(
+                "{} is locked. Run 'bunker --vault {} unlock' first",
+                self.path.display(),
+                self.path.display()
+            )
+        })
+    }
+
+    /// Run `f` on the vault's contents, then write them back to the file
+    /// unless a batch is in progress
+    fn update<T>(&self, f: impl FnOnce(&MemoryBackend) -> Result<T>) -> Result<T> {
+        self.with(|memory| {
+            let result = f(memory)?;
+            let mut batch = self
+                .batch
+                .lock()
+                .map_err(|_| anyhow!("Vault batch poisoned"))?;
+            if batch.depth > 0 {
+                batch.unsaved = true;
+            } else {
+                self.save(memory)?;
+            }
+            Ok(result)
+        })
+    }
+
+    fn save(&self, memory: &MemoryBackend) -> Result<()> {
+        let mut records = memory.contents()?.clone();
+        let config = records
+            .config
+            .take()
+            .ok_or_else(|| anyhow!("{} has no vault config", self.path.display()))?;
+        let sealed = Crypto::encrypt(&serde_json::to_vec(&records)?, &self.master_key(&config)?)?;
+        let container = Container {
+            bunker_vault: CONTAINER_VERSION,
+            config,
+            nonce: BASE64.encode(&sealed.nonce),
+            records: BASE64.encode(&sealed.ciphertext),
+        };
+        write_atomic(&self.path, serde_json::to_vec(&container)?)
+    }
+}
+
+impl VaultBackend for FileBackend {
+    fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    fn create(&self, config: &VaultConfig) -> Result<()> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let memory = MemoryBackend::default();
+        memory.create(config)?;
+        self.save(&memory)?;
+        *self
+            .loaded
+            .lock()
+            .map_err(|_| anyhow!("Vault contents poisoned"))? = Some(memory);
+        Ok(())
+    }
+
+    fn destroy(&self) -> Result<()> {
+        fs::remove_file(&self.path)?;
+        *self
+            .loaded
+            .lock()
+            .map_err(|_| anyhow!("Vault contents poisoned"))? = None;
+        *self.key.lock().map_err(|_| anyhow!("Vault key poisoned"))? = None;
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<VaultConfig> {
+        // Needed to unlock the vault, so it must not need the key itself
+        let loaded = self
+            .loaded
+            .lock()
+            .map_err(|_| anyhow!("Vault contents poisoned"))?;
+        match loaded.as_ref() {
+            Some(memory) => memory.load_config(),
+            None => match self.read_file()? {
+                Stored::Sealed(container) => Ok(container.config),
+                Stored::Plain(contents) => contents
+                    .config
+                    .ok_or_else(|| anyhow!("{} has no vault config", self.path.display())),
+            },
+        }
+    }
+
+    fn save_config(&self, config: &VaultConfig) -> Result<()> {
+        self.update(|m| m.save_config(config))
+    }
+
+    fn read(&self, record: Record, key: &str) -> Result<Option<String>> {
+        self.with(|m| m.read(record, key))
+    }
+
+    fn write(&self, record: Record, key: &str, data: &str) -> Result<()> {
+        self.update(|m| m.write(record, key, data))
+    }
+
+    fn remove(&self, record: Record, key: &str) -> Result<bool> {
+        self.update(|m| m.remove(record, key))
+    }
+
+    fn list(&self, record: Record) -> Result<Vec<String>> {
+        self.with(|m| m.list(record))
+    }
+
+    fn read_index(&self) -> Result<Option<String>> {
+        self.with(|m| m.read_index())
+    }
+
+    fn write_index(&self, data: &str) -> Result<()> {
+        self.update(|m| m.write_index(data))
+    }
+
+    fn remove_index(&self) -> Result<()> {
+        self.update(|m| m.remove_index())
+    }
+
+    fn has_blob(&self, id: &str) -> Result<bool> {
+        self.with(|m| m.has_blob(id))
+    }
+
+    fn write_blob(
+        &self,
+        id: &str,
+        fill: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        self.update(|m| m.write_blob(id, fill))
+    }
+
+    fn open_blob(&self, id: &str) -> Result<Option<Box<dyn Read + '_>>> {
+        let data = self.with(|m| match m.open_blob(id)? {
+            Some(mut reader) => {
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                Ok(Some(data))
+            }
+            None => Ok(None),
+        })?;
+        Ok(data.map(|data| Box::new(Cursor::new(data)) as Box<dyn Read>))
+    }
+
+    fn remove_blob(&self, id: &str) -> Result<()> {
+        self.update(|m| m.remove_blob(id))
+    }
+
+    fn unlock(&self, key: &MasterKey) -> Result<()> {
+        *self.key.lock().map_err(|_| anyhow!("Vault key poisoned"))? = Some(key.clone());
+        if !self.exists() {
+            return Ok(());
+        }
+        // Open the records now, so a wrong password is caught here
+        let opened = self.with(|_| Ok(()));
+        if opened.is_err() {
+            *self.key.lock().map_err(|_| anyhow!("Vault key poisoned"))? = None;
+        }
+        opened
+    }
+
+    fn batch(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        self.batch
+            .lock()
+            .map_err(|_| anyhow!("Vault batch poisoned"))?
+            .depth += 1;
+        let result = f();
+
+        let mut batch = self
+            .batch
+            .lock()
+            .map_err(|_| anyhow!("Vault batch poisoned"))?;
+        batch.depth -= 1;
+        if batch.depth > 0 || !batch.unsaved {
+            return result;
+        }
+        batch.unsaved = false;
+        drop(batch);
+
+        let saved = result.and_then(|()| self.with(|memory| self.save(memory)));
+        if saved.is_err() {
+            // Drop the half-made changes; the file still holds the vault as it was
+            self.reload()?;
+        }
+        saved
+    }
+
+    fn reload(&self) -> Result<()> {
+        *self
+            .loaded
+            .lock()
+            .map_err(|_| anyhow!("Vault contents poisoned"))? = None;
+        Ok(())
+    }
+
+    fn lock_path(&self) -> Option<PathBuf> {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".lock");
+        Some(PathBuf::from(path))
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::{Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use super::{Record, VaultBackend};
+use crate::types::VaultConfig;
+
+/// Everything in a vault, held in memory; also the payload of a single-file vault
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Contents {
+    pub config: Option<VaultConfig>,
+    #[serde(default)]
+    pub entries: BTreeMap<String, String>,
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    pub trash: BTreeMap<String, String>,
+    /// Encrypted attachment blobs, base64-encoded
+    #[serde(default)]
+    pub attachments: BTreeMap<String, String>,
+    #[serde(default)]
+    pub index: Option<String>,
+}
+
+impl Contents {
+    pub fn records(&self, record: Record) -> &BTreeMap<String, String> {
+        match record {
+            Record::Entry => &self.entries,
+            Record::Alias => &self.aliases,
+            Record::Trashed => &self.trash,
+        }
+    }
+
+    pub fn records_mut(&mut self, record: Record) -> &mut BTreeMap<String, String> {
+        match record {
+            Record::Entry => &mut self.entries,
+            Record::Alias => &mut self.aliases,
+            Record::Trashed => &mut self.trash,
+        }
+    }
+}
+
+/// A vault that lives only as long as the process, for tests and for reading exports
+#[derive(Default)]
+pub struct MemoryBackend {
+    contents: Mutex<Contents>,
+}
+
+impl MemoryBackend {
+    pub fn with_contents(contents: Contents) -> Self {
+        Self {
+            contents: Mutex::new(contents),
+        }
+    }
+
+    pub fn contents(&self) -> Result<MutexGuard<'_, Contents>> {
+        self.contents
+            .lock()
+            .map_err(|_| anyhow!("Vault contents poisoned"))
+    }
+}
+
+impl VaultBackend for MemoryBackend {
+    fn exists(&self) -> bool {
+        self.contents().is_ok_and(|c| c.config.is_some())
+    }
+
+    fn create(&self, config: &VaultConfig) -> Result<()> {
+        *self.contents()? = Contents {
+            config: Some(config.clone()),
+            ..Contents::default()
+        };
+        Ok(())
+    }
+
+    fn destroy(&self) -> Result<()> {
+        *self.contents()? = Contents::default();
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<VaultConfig> {
+        self.contents()?
+            .config
+            .clone()
+            .ok_or_else(|| anyhow!("Vault not initialized"))
+    }
+
+    fn save_config(&self, config: &VaultConfig) -> Result<()> {
+        self.contents()?.config = Some(config.clone());
+        Ok(())
+    }
+
+    fn read(&self, record: Record, key: &str) -> Result<Option<String>> {
+        Ok(self.contents()?.records(record).get(key).cloned())
+    }
+
+    fn write(&self, record: Record, key: &str, data: &str) -> Result<()> {
+        self.contents()?
+            .records_mut(record)
+            .insert(key.to_string(), data.to_string());
+        Ok(())
+    }
+
+    fn remove(&self, record: Record, key: &str) -> Result<bool> {
+        Ok(self.contents()?.records_mut(record).remove(key).is_some())
+    }
+
+    fn list(&self, record: Record) -> Result<Vec<String>> {
+        Ok(self.contents()?.records(record).keys().cloned().collect())
+    }
+
+    fn read_index(&self) -> Result<Option<String>> {
+        Ok(self.contents()?.index.clone())
+    }
+
+    fn write_index(&self, data: &str) -> Result<()> {
+        self.contents()?.index = Some(data.to_string());
+        Ok(())
+    }
+
+    fn remove_index(&self) -> Result<()> {
+        self.contents()?.index = None;
+        Ok(())
+    }
+
+    fn has_blob(&self, id: &str) -> Result<bool> {
+        Ok(self.contents()?.attachments.contains_key(id))
+    }
+
+    fn write_blob(
+        &self,
+        id: &str,
+        fill: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        let mut data = Vec::new();
+        fill(&mut data)?;
+        self.contents()?
+            .attachments
+            .insert(id.to_string(), BASE64.encode(&data));
+        Ok(())
+    }
+
+    fn open_blob(&self, id: &str) -> Result<Option<Box<dyn Read + '_>>> {
+        match self.contents()?.attachments.get(id) {
+            Some(encoded) => Ok(Some(Box::new(Cursor::new(BASE64.decode(encoded)?)))),
+            None => Ok(None),
+        }
+    }
+
+    fn remove_blob(&self, id: &str) -> Result<()> {
+        self.contents()?.attachments.remove(id);
+        Ok(())
+    }
+
+    fn lock_path(&self) -> Option<PathBuf> {
+        None
+    }
+}
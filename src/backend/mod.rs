@@ -0,0 +1,115 @@
+mod dir;
+mod file;
+mod memory;
+
+pub use dir::DirBackend;
+pub use file::{CONTAINER_EXTENSION, FileBackend, KeySource};
+pub use memory::MemoryBackend;
+
+use anyhow::{Result, anyhow};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use crate::types::{MasterKey, VaultConfig};
+
+/// The kinds of record a vault holds, each with its own namespace of keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Record {
+    /// A stored entry
+    Entry,
+    /// An alias pointing at another key
+    Alias,
+    /// An entry in the trash
+    Trashed,
+}
+
+/// Check that an attachment blob id is what `Crypto::content_id` produces, a
+/// lowercase hex SHA-256, before it is used to name a file or a record
+pub fn check_blob_id(id: &str) -> Result<()> {
+    let is_digest = id.len() == 64 && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !is_digest {
+        return Err(anyhow!("Invalid attachment blob id '{}'", id));
+    }
+    Ok(())
+}
+
+/// Where one vault's config, records and attachment blobs live.
+///
+/// `Storage` handles key normalization, aliases and encryption on top of it, so
+/// keys arrive as normalized `EntryKey`s and records as serialized JSON whose
+/// secrets are already encrypted.
+pub trait VaultBackend: Send + Sync {
+    /// Whether the vault has been created
+    fn exists(&self) -> bool;
+
+    /// Create the vault with its initial config
+    fn create(&self, config: &VaultConfig) -> Result<()>;
+
+    /// Delete the vault and everything in it except its lock file
+    fn destroy(&self) -> Result<()>;
+
+    fn load_config(&self) -> Result<VaultConfig>;
+
+    fn save_config(&self, config: &VaultConfig) -> Result<()>;
+
+    /// Read a record, or None if there is none under the key
+    fn read(&self, record: Record, key: &str) -> Result<Option<String>>;
+
+    /// Create or replace a record
+    fn write(&self, record: Record, key: &str, data: &str) -> Result<()>;
+
+    /// Remove a record, returning whether it existed
+    fn remove(&self, record: Record, key: &str) -> Result<bool>;
+
+    /// Keys of every record of a kind, sorted
+    fn list(&self, record: Record) -> Result<Vec<String>>;
+
+    fn contains(&self, record: Record, key: &str) -> Result<bool> {
+        Ok(self.read(record, key)?.is_some())
+    }
+
+    /// Read the search index, or None if the vault has none yet
+    fn read_index(&self) -> Result<Option<String>>;
+
+    fn write_index(&self, data: &str) -> Result<()>;
+
+    fn remove_index(&self) -> Result<()>;
+
+    fn has_blob(&self, id: &str) -> Result<bool>;
+
+    /// Store a blob, whose content is written by `fill`; a failed write leaves
+    /// no partial blob behind
+    fn write_blob(
+        &self,
+        id: &str,
+        fill: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()>;
+
+    /// Open a blob for reading, or None if it is missing
+    fn open_blob(&self, id: &str) -> Result<Option<Box<dyn Read + '_>>>;
+
+    fn remove_blob(&self, id: &str) -> Result<()>;
+
+    /// Hand over the vault's master key, for backends that encrypt everything
+    /// they store; fails when the key does not open the vault
+    fn unlock(&self, _key: &MasterKey) -> Result<()> {
+        Ok(())
+    }
+
+    /// Run `f`, which may make several changes, and save them together. For
+    /// backends that rewrite the whole vault on every change; if `f` fails,
+    /// none of its changes are saved.
+    fn batch(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        f()
+    }
+
+    /// Forget anything read earlier, so the next read sees what other
+    /// processes wrote; called after taking the vault's lock
+    fn reload(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// File other bunker processes lock while changing the vault, if it can be
+    /// shared between processes at all
+    fn lock_path(&self) -> Option<PathBuf>;
+}
@@ -1,7 +1,9 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::*;
 use std::path::PathBuf;
 
+use crate::types::{CharClass, EntryType};
+
 #[derive(Parser)]
 #[command(name = "bunker")]
 #[command(about = "Dead simple, secure password management")]
@@ -13,17 +15,29 @@ pub struct Cli {
     /// Vault to operate on
     #[arg(long, global = true)]
     pub vault: Option<String>,
+
+    /// Directory for vaults and config instead of ~/.bunker (or BUNKER_HOME)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub home: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Initialize a new vault
     Init {
-        /// Name of the vault
-        name: String,
+        /// Name of the vault, or a path ending in .bvault for a single-file vault
+        #[arg(required_unless_present = "here")]
+        name: Option<String>,
         /// Run in non-interactive mode
         #[arg(long)]
         non_interactive: bool,
+        /// Keep the whole vault in one .bvault file instead of a directory
+        #[arg(long)]
+        single_file: bool,
+        /// Create a project vault in .bunker/ in the current directory, named after it
+        #[arg(long, conflicts_with_all = ["name", "single_file"])]
+        here: bool,
     },
 
     /// Add a new password
@@ -39,12 +53,20 @@ pub enum Commands {
         /// Read content from file
         #[arg(long)]
         file: Option<PathBuf>,
+        /// Overwrite an existing entry without confirmation
+        #[arg(short, long)]
+        force: bool,
+        #[command(flatten)]
+        flags: EntryFlags,
     },
 
     /// Get a password
     Get {
         /// Entry key/name
         key: String,
+        /// Use this field instead of the main value (username, url, notes or a custom field)
+        #[arg(long)]
+        field: Option<String>,
         /// Copy to clipboard instead of printing
         #[arg(short, long)]
         copy: bool,
@@ -55,44 +77,73 @@ pub enum Commands {
 
     /// Edit an existing password
     Edit {
-        /// Entry key/name  
+        /// Entry key/name
         key: String,
-        /// New password value (will prompt if not provided)
+        /// New password value (skips the interactive menu)
         #[arg(long)]
         value: Option<String>,
+        /// Edit the whole entry as a TOML document in $EDITOR
+        #[arg(long, conflicts_with = "value")]
+        editor: bool,
+        #[command(flatten)]
+        flags: EntryFlags,
     },
 
-    /// Remove a password
+    /// Remove passwords (moved to the trash)
+    #[command(alias = "rm")]
     Remove {
-        /// Entry key/name
+        /// Entry key/name, or a glob such as 'legacy/**'
         key: String,
         /// Force removal without confirmation
         #[arg(short, long)]
         force: bool,
+        /// Delete permanently instead of moving to the trash
+        #[arg(long)]
+        permanent: bool,
+        /// Remove everything under the key's path
+        #[arg(short, long)]
+        recursive: bool,
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// List all passwords
     List {
+        /// Only entries under this path or matching this glob
+        path: Option<String>,
         /// Show as tree structure
         #[arg(short, long)]
         tree: bool,
+        /// Also show entries in the trash
+        #[arg(long)]
+        include_trash: bool,
+        /// Only entries matching this tag filter (repeat to AND; a|b for OR, !a for NOT)
+        #[arg(long = "tag", value_name = "FILTER")]
+        tags: Vec<String>,
     },
 
     /// Search passwords
     Search {
         /// Search query (optional for interactive search)
         query: Option<String>,
+        /// Also show entries in the trash
+        #[arg(long)]
+        include_trash: bool,
+        /// Only entries matching this tag filter (repeat to AND; a|b for OR, !a for NOT)
+        #[arg(long = "tag", value_name = "FILTER")]
+        tags: Vec<String>,
     },
 
     /// Generate a secure password
     Generate {
-        /// Password length
-        #[arg(short, long, default_value = "20")]
-        length: usize,
+        /// Password length [default: 20]
+        #[arg(short, long)]
+        length: Option<usize>,
         /// Include uppercase letters
         #[arg(long)]
         uppercase: bool,
-        /// Include lowercase letters  
+        /// Include lowercase letters
         #[arg(long)]
         lowercase: bool,
         /// Include numbers
@@ -107,15 +158,117 @@ pub enum Commands {
         /// Custom character set
         #[arg(long)]
         charset: Option<String>,
+        /// Minimum number of uppercase letters
+        #[arg(long)]
+        min_upper: Option<usize>,
+        /// Minimum number of lowercase letters
+        #[arg(long)]
+        min_lower: Option<usize>,
+        /// Minimum number of digits
+        #[arg(long)]
+        min_digits: Option<usize>,
+        /// Minimum number of symbols
+        #[arg(long)]
+        min_symbols: Option<usize>,
+        /// Maximum run of the same character
+        #[arg(long)]
+        max_repeat: Option<usize>,
+        /// Symbols allowed in the password
+        #[arg(long)]
+        allowed_symbols: Option<String>,
+        /// Characters that must never appear
+        #[arg(long)]
+        forbid: Option<String>,
+        /// Required password prefix
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Required class of the first character (lower, upper, digit, symbol, letter)
+        #[arg(long)]
+        first_char: Option<CharClass>,
+        /// Named generation profile from config.toml
+        #[arg(long)]
+        profile: Option<String>,
         /// Copy to clipboard
         #[arg(short, long)]
         copy: bool,
+        /// Generate a passphrase with this many words instead of a password
+        #[arg(long)]
+        words: Option<usize>,
+        /// Separator between passphrase words
+        #[arg(long, default_value = "-", requires = "words")]
+        separator: String,
+        /// Capitalize each passphrase word
+        #[arg(long, requires = "words")]
+        capitalize: bool,
+        /// Append a random digit to one passphrase word
+        #[arg(long, requires = "words")]
+        add_digit: bool,
+        /// Custom wordlist file (one word per line, diceware format accepted)
+        #[arg(long, requires = "words")]
+        wordlist: Option<PathBuf>,
+    },
+
+    /// Derive a site password from the vault key (nothing secret is stored)
+    Derive {
+        /// Site name or domain
+        site: String,
+        /// Login/username for the site
+        #[arg(long)]
+        login: Option<String>,
+        /// Counter, bump it to rotate the password [default: 1]
+        #[arg(long)]
+        counter: Option<u32>,
+        /// Entry key to save the parameters under [default: site]
+        #[arg(long)]
+        key: Option<String>,
+        /// Password length [default: 20]
+        #[arg(short, long)]
+        length: Option<usize>,
+        /// Exclude symbols
+        #[arg(long)]
+        no_symbols: bool,
+        /// Named generation profile from config.toml
+        #[arg(long)]
+        profile: Option<String>,
+        /// Copy to clipboard instead of printing
+        #[arg(short, long)]
+        copy: bool,
+        /// Don't save the site, login and counter as an entry
+        #[arg(long)]
+        no_save: bool,
+    },
+
+    /// Rotate secrets, keeping the old values in entry history
+    Rotate {
+        /// Entry key/name (or use --tag/--path for bulk rotation)
+        key: Option<String>,
+        /// Rotate entries matching this tag filter (repeat to AND; a|b for OR, !a for NOT)
+        #[arg(long = "tag", value_name = "FILTER")]
+        tags: Vec<String>,
+        /// Rotate entries under this path, or matching a glob such as 'aws/**/iam'
+        #[arg(long)]
+        path: Option<String>,
+        /// Named generation profile from config.toml (saved as the entry's policy)
+        #[arg(long)]
+        profile: Option<String>,
+        /// Command run before saving; gets BUNKER_OLD_SECRET and BUNKER_NEW_SECRET
+        #[arg(long)]
+        hook: Option<String>,
+        /// Pass the old and new secrets to the hook on stdin instead of env vars
+        #[arg(long, requires = "hook")]
+        hook_stdin: bool,
+        /// Show which entries would be rotated without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Copy password to clipboard
     Copy {
         /// Entry key/name
         key: String,
+        /// Use this field instead of the main value (username, url, notes or a custom field)
+        #[arg(long)]
+        field: Option<String>,
         /// Clipboard timeout in seconds
         #[arg(long, default_value = "45")]
         timeout: u64,
@@ -125,18 +278,44 @@ pub enum Commands {
     Peek {
         /// Entry key/name
         key: String,
+        /// Use this field instead of the main value (username, url, notes or a custom field)
+        #[arg(long)]
+        field: Option<String>,
         /// Number of characters to show
         #[arg(long, default_value = "3")]
         chars: usize,
     },
 
-    /// Move/rename a password
+    /// Move/rename passwords
     #[command(name = "mv")]
     Move {
-        /// Source key
+        /// Source key, or a glob such as 'old-team/**'
+        from: String,
+        /// Target key, or a directory ending in '/'
+        to: String,
+        /// Move everything under the source path
+        #[arg(short, long)]
+        recursive: bool,
+        /// Show what would be moved without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Point references to the moved keys at their new keys without asking
+        #[arg(long)]
+        update_refs: bool,
+    },
+
+    /// Copy passwords to new keys
+    Cp {
+        /// Source key, or a glob such as 'templates/**'
         from: String,
-        /// Target key
+        /// Target key, or a directory ending in '/'
         to: String,
+        /// Copy everything under the source path
+        #[arg(short, long)]
+        recursive: bool,
+        /// Show what would be copied without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Execute command with password as argument or environment variable
@@ -147,6 +326,9 @@ pub enum Commands {
         /// Entry key/name
         #[arg(short, long)]
         key: String,
+        /// Use this field instead of the main value (username, url, notes or a custom field)
+        #[arg(long)]
+        field: Option<String>,
         /// Use as environment variable
         #[arg(short, long)]
         env: Option<String>,
@@ -163,6 +345,15 @@ pub enum Commands {
         /// Include metadata
         #[arg(long)]
         metadata: bool,
+        /// Only entries matching this tag filter (repeat to AND; a|b for OR, !a for NOT)
+        #[arg(long = "tag", value_name = "FILTER")]
+        tags: Vec<String>,
+        /// Only entries under this path or matching this glob
+        #[arg(long)]
+        path: Option<String>,
+        /// List the entries that would be exported without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Import passwords from file
@@ -184,6 +375,36 @@ pub enum Commands {
         /// Case insensitive search
         #[arg(short, long)]
         ignore_case: bool,
+        /// Only entries matching this tag filter (repeat to AND; a|b for OR, !a for NOT)
+        #[arg(long = "tag", value_name = "FILTER")]
+        tags: Vec<String>,
+        /// Only search the indexed metadata, not secret values (skips decrypting every entry)
+        #[arg(long)]
+        metadata_only: bool,
+    },
+
+    /// Manage the vault's encrypted search index
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+
+    /// List, restore or purge removed entries
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    /// Manage entry tags
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// Manage encrypted file attachments
+    Attach {
+        #[command(subcommand)]
+        action: AttachAction,
     },
 
     /// Version control operations
@@ -233,18 +454,86 @@ pub enum Commands {
         /// Limit number of entries
         #[arg(short, long, default_value = "20")]
         limit: usize,
+        /// Show the entry's previous values instead of git history
+        #[arg(long, requires = "key")]
+        values: bool,
+    },
+
+    /// Create an alias that resolves to another entry
+    Ln {
+        /// Entry the alias points at
+        target: String,
+        /// Alias key
+        alias: String,
+        /// Replace an existing alias
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Show what an entry references and which entries reference it
+    Refs {
+        /// Entry key/name
+        key: String,
+    },
+
+    /// Restore a previous value of an entry
+    Revert {
+        /// Entry key/name
+        key: String,
+        /// Version to restore, as numbered by 'history <key> --values'
+        #[arg(long)]
+        to: usize,
     },
 
     /// Export environment variable
     Env {
         /// Entry key/name
         key: String,
+        /// Use this field instead of the main value (username, url, notes or a custom field)
+        #[arg(long)]
+        field: Option<String>,
         /// Environment variable name
         #[arg(long)]
         var: Option<String>,
     },
 }
 
+impl Commands {
+    /// Whether the command writes to the current vault, and so runs under its lock
+    pub fn mutates_vault(&self) -> bool {
+        match self {
+            Commands::Add { .. }
+            | Commands::Edit { .. }
+            | Commands::Remove { .. }
+            | Commands::Derive { .. }
+            | Commands::Rotate { .. }
+            | Commands::Move { .. }
+            | Commands::Cp { .. }
+            | Commands::Import { .. }
+            | Commands::Index { .. }
+            | Commands::Trash { .. }
+            | Commands::Ln { .. }
+            | Commands::Revert { .. } => true,
+            Commands::Tag { action } => {
+                matches!(action, TagAction::Add { .. } | TagAction::Remove { .. })
+            }
+            Commands::Attach { action } => {
+                matches!(
+                    action,
+                    AttachAction::Add { .. } | AttachAction::Remove { .. }
+                )
+            }
+            Commands::Git { action } => !matches!(action, GitAction::Status),
+            Commands::Vault { action } => match action {
+                VaultAction::TrashRetention { days } => days.is_some(),
+                VaultAction::HistoryLimit { limit } => limit.is_some(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum GitAction {
     /// Sync with remote
@@ -262,12 +551,55 @@ pub enum GitAction {
     },
 }
 
+#[derive(Subcommand)]
+pub enum AttachAction {
+    /// Attach files to an entry
+    Add {
+        /// Entry key/name
+        key: String,
+        /// Files to attach
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Attachment name (defaults to the file name; single file only)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// List the attachments of an entry
+    List {
+        /// Entry key/name
+        key: String,
+    },
+    /// Decrypt an attachment to a file
+    Extract {
+        /// Entry key/name
+        key: String,
+        /// Attachment name
+        name: String,
+        /// Output path (defaults to the attachment name, `-` for stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove an attachment from an entry
+    Remove {
+        /// Entry key/name
+        key: String,
+        /// Attachment name
+        name: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum VaultAction {
     /// Create a new vault
     Create {
         /// Vault name
         name: String,
+        /// Keep the whole vault in one .bvault file instead of a directory
+        #[arg(long)]
+        single_file: bool,
     },
     /// Switch to a vault
     Use {
@@ -301,6 +633,136 @@ pub enum VaultAction {
         /// Target vault name
         name: String,
     },
+    /// Merge another vault's entries into this one
+    Merge {
+        /// Vault to merge from (left unchanged)
+        source: String,
+        /// Vault to merge into (defaults to the current vault)
+        #[arg(long)]
+        into: Option<String>,
+        /// How to resolve conflicts (skip, overwrite, newest-wins, keep-both, interactive)
+        #[arg(short, long, default_value = "skip")]
+        strategy: String,
+        /// Suffix for the second copy with keep-both (defaults to -<source>)
+        #[arg(long)]
+        suffix: Option<String>,
+        /// Report new, identical and conflicting entries without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Compare two vaults, or a vault with an export file
+    Diff {
+        /// Vault to compare
+        a: String,
+        /// Vault to compare it with
+        b: Option<String>,
+        /// Compare with an export file instead of a vault
+        #[arg(long, conflicts_with = "b")]
+        export: Option<PathBuf>,
+        /// Export password (prompted for if not given)
+        #[arg(long, requires = "export")]
+        password: Option<String>,
+    },
+    /// Show or set how many days removed entries stay in the trash
+    TrashRetention {
+        /// Days to keep trashed entries (0 keeps them forever); omit to show
+        days: Option<u32>,
+    },
+    /// Show or set how many previous values are kept per entry
+    HistoryLimit {
+        /// New limit (0 disables history); omit to show the current one
+        limit: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IndexAction {
+    /// Rebuild the search index from the stored entries
+    Rebuild,
+}
+
+#[derive(Subcommand)]
+pub enum TrashAction {
+    /// List trashed entries
+    List,
+    /// Move a trashed entry back into the vault
+    Restore {
+        /// Entry key/name
+        key: String,
+    },
+    /// Permanently delete a trashed entry, or empty the whole trash
+    Purge {
+        /// Entry key/name (omit to empty the trash)
+        key: Option<String>,
+        /// Skip confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagAction {
+    /// Add tags to an entry
+    Add {
+        /// Entry key/name
+        key: String,
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from an entry
+    Remove {
+        /// Entry key/name
+        key: String,
+        /// Tags to remove
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Show every tag with the number of entries using it
+    List,
+}
+
+/// Metadata flags shared by `add` and `edit`
+#[derive(Args, Debug, Default)]
+pub struct EntryFlags {
+    /// Entry type (card, identity, database and sshkey prompt for their fields unless --field is given)
+    #[arg(long = "type", value_name = "TYPE")]
+    pub entry_type: Option<EntryType>,
+    /// Username (empty to remove)
+    #[arg(long)]
+    pub username: Option<String>,
+    /// URL (empty to remove)
+    #[arg(long)]
+    pub url: Option<String>,
+    /// Add a tag (repeatable)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Notes (empty to remove)
+    #[arg(long)]
+    pub notes: Option<String>,
+    /// Set a typed or custom field (repeatable; an empty value removes a custom field)
+    #[arg(long = "field", value_name = "KEY=VALUE")]
+    pub fields: Vec<String>,
+    /// Expiry: YYYY-MM-DD, an RFC 3339 timestamp, a number of days such as 90d, or 'never'
+    #[arg(long)]
+    pub expires: Option<String>,
+    /// Read the value from stdin
+    #[arg(long, conflicts_with = "value")]
+    pub stdin: bool,
+}
+
+impl EntryFlags {
+    /// Whether any metadata flag was given
+    pub fn is_empty(&self) -> bool {
+        self.entry_type.is_none()
+            && self.username.is_none()
+            && self.url.is_none()
+            && self.tags.is_empty()
+            && self.notes.is_none()
+            && self.fields.is_empty()
+            && self.expires.is_none()
+            && !self.stdin
+    }
 }
 
 pub struct CliDisplay;
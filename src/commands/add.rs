@@ -1,6 +1,8 @@
-use crate::cli::Cli;
+use crate::cli::{Cli, EntryFlags};
+use crate::commands::edit;
 use crate::crypto::Crypto;
 use crate::git::Git;
+use crate::schema;
 use crate::storage::Storage;
 use crate::types::{Entry, EntryMetadata, EntryType};
 use crate::utils;
@@ -8,6 +10,7 @@ use anyhow::{Result, anyhow};
 use chrono::Utc;
 use colored::*;
 use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
 use uuid::Uuid;
 
@@ -16,19 +19,43 @@ pub async fn execute(
     value: Option<String>,
     note: bool,
     file: Option<PathBuf>,
+    force: bool,
+    flags: EntryFlags,
     vault: Option<String>,
 ) -> Result<()> {
+    let value = if flags.stdin {
+        Some(utils::read_stdin_value()?)
+    } else {
+        value
+    };
+    let entry_type = flags.entry_type.clone();
+
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
 
+    // Writing to an alias writes to the entry it points at
+    let key = storage.resolve_key(&key)?;
+
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Check if entry already exists and warn about overwriting
-    if storage.entry_exists(&key)? {
+    let existing = if !storage.entry_exists(&key)? {
+        None
+    } else if force {
+        Some(storage.load_entry(&key, &master_key)?)
+    } else {
+        // Nobody can answer the prompt when stdin is piped
+        if flags.stdin || !io::stdin().is_terminal() {
+            return Err(anyhow!(
+                "Entry '{}' already exists. Use --force to overwrite it",
+                key
+            ));
+        }
+
         let existing_entry = storage.load_entry(&key, &master_key)?;
         println!("⚠️  Warning: Entry '{}' already exists!", key.yellow());
         println!(
@@ -58,13 +85,50 @@ pub async fn execute(
             return Ok(());
         }
         println!("{}", "Proceeding with overwrite...".yellow());
-    }
+        Some(existing_entry)
+    };
+
+    // Typed entries prompt for their structured fields instead of a single value,
+    // unless the fields were given as flags
+    let schema = entry_type.as_ref().and_then(schema::fields);
+    let field_values = match schema {
+        Some(fields) => {
+            let primary = match &file {
+                Some(path) => Some(
+                    fs::read_to_string(path).map_err(|e| anyhow!("Failed to read file: {}", e))?,
+                ),
+                None => value.clone(),
+            };
+            if flags.fields.is_empty() && !flags.stdin {
+                Some(schema::prompt_fields(fields, None, primary)?)
+            } else {
+                Some(
+                    primary
+                        .map(|v| {
+                            std::collections::BTreeMap::from([(fields[0].name.to_string(), v)])
+                        })
+                        .unwrap_or_default(),
+                )
+            }
+        }
+        None => None,
+    };
 
     // Determine entry type and value
-    let (entry_type, entry_value) = if let Some(file_path) = file {
+    let (entry_type, entry_value) = if let Some(values) = &field_values {
+        let fields = schema.unwrap_or_default();
+        (
+            entry_type.clone().unwrap_or_default(),
+            values.get(fields[0].name).cloned().unwrap_or_default(),
+        )
+    } else if let Some(file_path) = file {
         // Read file content
-        let content =
-            fs::read_to_string(&file_path).map_err(|e| anyhow!("Failed to read file: {}", e))?;
+        let content = fs::read_to_string(&file_path).map_err(|e| {
+            anyhow!(
+                "Failed to read file: {}. Use 'bunker attach add' for binary files",
+                e
+            )
+        })?;
         (EntryType::SecureFile, content)
     } else if note {
         // Get note content
@@ -82,7 +146,7 @@ pub async fn execute(
         } else {
             utils::prompt_password(&format!("Enter password for '{}'", key))?
         };
-        (EntryType::Password, password)
+        (entry_type.clone().unwrap_or(EntryType::Password), password)
     };
 
     // Create metadata
@@ -93,15 +157,21 @@ pub async fn execute(
         url: None,
         username: None,
         custom_fields: std::collections::HashMap::new(),
+        secret_fields: std::collections::HashMap::new(),
         expires_at: None,
         auto_type: None,
+        derivation: None,
+        policy: None,
+        history: Vec::new(),
+        fields: std::collections::BTreeMap::new(),
+        attachments: Vec::new(),
     };
 
     // Encrypt the value
     let encrypted_value = Crypto::encrypt(entry_value.as_bytes(), &master_key)?;
 
     // Create entry
-    let entry = Entry {
+    let mut entry = Entry {
         id: Uuid::new_v4(),
         key: key.clone(),
         value: encrypted_value,
@@ -111,6 +181,19 @@ pub async fn execute(
         accessed_at: None,
     };
 
+    if let (Some(fields), Some(values)) = (schema, &field_values) {
+        schema::apply_fields(&mut entry, fields, values, &master_key)?;
+    }
+    edit::apply_flags(&mut entry, &flags, &master_key)?;
+    if let Some(fields) = schema {
+        schema::validate(fields, &schema::read_fields(&entry, fields, &master_key)?)?;
+    }
+
+    // An overwritten entry keeps its history and attachments
+    if let Some(existing) = existing {
+        entry.inherit_from(existing, storage.load_config()?.history_limit);
+    }
+
     // Store entry
     storage.store_entry(&entry, &master_key)?;
 
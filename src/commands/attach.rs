@@ -0,0 +1,262 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli::AttachAction;
+use crate::git::Git;
+use crate::storage::Storage;
+use crate::types::{Attachment, MasterKey};
+use crate::utils;
+
+pub async fn execute(action: AttachAction, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
+
+    match action {
+        AttachAction::Add { key, files, name } => add(&storage, &master_key, key, files, name),
+        AttachAction::List { key } => list(&storage, &master_key, key),
+        AttachAction::Extract {
+            key,
+            name,
+            output,
+            force,
+        } => extract(&storage, &master_key, key, name, output, force),
+        AttachAction::Remove { key, name } => remove(&storage, &master_key, key, name),
+    }
+}
+
+fn add(
+    storage: &Storage,
+    master_key: &MasterKey,
+    key: String,
+    files: Vec<PathBuf>,
+    name: Option<String>,
+) -> Result<()> {
+    if name.is_some() && files.len() > 1 {
+        return Err(anyhow!(
+            "--name can only be used when attaching a single file"
+        ));
+    }
+
+    let mut entry = storage.load_entry(&key, master_key)?;
+
+    // The blobs and the entry listing them are saved together
+    storage.batch(|| {
+        for file in &files {
+            let attachment_name = match &name {
+                Some(n) => check_name(n)?,
+                None => file
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .ok_or_else(|| anyhow!("Invalid file name: {}", file.display()))?,
+            };
+            if entry
+                .metadata
+                .attachments
+                .iter()
+                .any(|a| a.name == attachment_name)
+            {
+                return Err(anyhow!(
+                    "Entry '{}' already has an attachment named '{}'",
+                    key,
+                    attachment_name
+                ));
+            }
+
+            let (blob, size) = storage.store_attachment(file, master_key)?;
+            entry.metadata.attachments.push(Attachment {
+                name: attachment_name.clone(),
+                blob,
+                size,
+                added_at: Utc::now(),
+            });
+            println!(
+                "{} Attached '{}' ({})",
+                "📎".green(),
+                attachment_name.cyan(),
+                format_size(size)
+            );
+        }
+
+        entry.updated_at = Utc::now();
+        storage.store_entry(&entry, master_key)
+    })?;
+    commit(storage, &format!("Attach files to {}", key))
+}
+
+/// An attachment name must be a plain file name, as `extract` writes to it
+fn check_name(name: &str) -> Result<String> {
+    if name == "-" || Path::new(name).file_name() != Some(name.as_ref()) {
+        return Err(anyhow!(
+            "Invalid attachment name '{}': use a file name without directories",
+            name
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn list(storage: &Storage, master_key: &MasterKey, key: String) -> Result<()> {
+    let entry = storage.load_entry(&key, master_key)?;
+
+    if entry.metadata.attachments.is_empty() {
+        println!("{}", "No attachments".yellow());
+        return Ok(());
+    }
+
+    println!(
+        "{} {} attachments for '{}':\n",
+        "📎".green(),
+        entry.metadata.attachments.len().to_string().bold(),
+        key.cyan()
+    );
+    for attachment in &entry.metadata.attachments {
+        println!(
+            "  {}  {}  {}",
+            attachment.name.cyan(),
+            format_size(attachment.size).yellow(),
+            attachment
+                .added_at
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+                .dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn extract(
+    storage: &Storage,
+    master_key: &MasterKey,
+    key: String,
+    name: String,
+    output: Option<PathBuf>,
+    force: bool,
+) -> Result<()> {
+    let entry = storage.load_entry(&key, master_key)?;
+    let attachment = find(&entry.metadata.attachments, &key, &name)?;
+
+    if output.as_deref() == Some(Path::new("-")) {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        storage.read_attachment(&attachment.blob, &mut handle, master_key)?;
+        return Ok(());
+    }
+
+    // Names from merged or imported vaults were never checked, so only their
+    // last component is used
+    let output = match output {
+        Some(output) => output,
+        None => Path::new(&attachment.name)
+            .file_name()
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Attachment '{}' has no usable file name. Use --output",
+                    attachment.name
+                )
+            })?,
+    };
+
+    if output.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists. Use --force to overwrite",
+            output.display()
+        ));
+    }
+
+    // Decrypt next to the destination, then move it into place
+    let dir = match output.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut temp = tempfile::NamedTempFile::new_in(&dir)?;
+    let written = {
+        let mut writer = io::BufWriter::new(temp.as_file_mut());
+        let written = storage.read_attachment(&attachment.blob, &mut writer, master_key)?;
+        writer.flush()?;
+        written
+    };
+    temp.persist(&output)
+        .map_err(|e| anyhow!("Failed to write {}: {}", output.display(), e))?;
+
+    // Attachments are usually key material; keep them private
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&output, fs::Permissions::from_mode(0o600))?;
+    }
+
+    println!(
+        "{} Extracted '{}' to {} ({})",
+        "✓".green().bold(),
+        attachment.name.cyan(),
+        output.display(),
+        format_size(written)
+    );
+
+    Ok(())
+}
+
+fn remove(storage: &Storage, master_key: &MasterKey, key: String, name: String) -> Result<()> {
+    let mut entry = storage.load_entry(&key, master_key)?;
+    let blob = find(&entry.metadata.attachments, &key, &name)?.blob.clone();
+
+    entry.metadata.attachments.retain(|a| a.name != name);
+    entry.updated_at = Utc::now();
+    storage.store_entry(&entry, master_key)?;
+    storage.delete_attachment_if_unused(&blob)?;
+
+    println!(
+        "{} Removed attachment '{}' from '{}'",
+        "✓".green().bold(),
+        name.cyan(),
+        key.cyan()
+    );
+
+    commit(storage, &format!("Remove attachment {} from {}", name, key))
+}
+
+fn find<'a>(attachments: &'a [Attachment], key: &str, name: &str) -> Result<&'a Attachment> {
+    attachments
+        .iter()
+        .find(|a| a.name == name)
+        .ok_or_else(|| anyhow!("Entry '{}' has no attachment named '{}'", key, name))
+}
+
+/// Commit if git enabled
+fn commit(storage: &Storage, message: &str) -> Result<()> {
+    let config = storage.load_config()?;
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(storage.get_vault_path(), message)?;
+
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -12,6 +12,13 @@ pub async fn execute(destination: Option<PathBuf>, vault: Option<String>) -> Res
     if !storage.vault_exists() {
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
+    if storage.is_single_file() {
+        return Err(anyhow!(
+            "Vault '{}' is a single file; back it up by copying {}",
+            storage.get_vault_name(),
+            storage.get_vault_path().display()
+        ));
+    }
 
     // Determine backup destination
     let backup_path = if let Some(dest) = destination {
@@ -1,12 +1,13 @@
 use anyhow::{Result, anyhow};
 
 use crate::cli::Cli;
-use crate::crypto::Crypto;
+use crate::refs;
 use crate::storage::Storage;
 use crate::utils;
 
 pub async fn execute(
     key: String,
+    field: Option<String>,
     persist: bool,
     timeout: u64,
     vault: Option<String>,
@@ -18,15 +19,13 @@ pub async fn execute(
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Load entry
     let entry = storage.load_entry(&key, &master_key)?;
 
-    // Decrypt the value
-    let decrypted = Crypto::decrypt(&entry.value, &master_key)?;
-    let value =
-        String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))?;
+    // Decrypt the value (or the requested field)
+    let value = refs::resolve_field(&storage, &entry, field.as_deref(), &master_key)?;
 
     // Copy to clipboard
     let actual_timeout = if persist { 0 } else { timeout };
@@ -0,0 +1,379 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+use uuid::Uuid;
+
+use crate::git::Git;
+use crate::key::EntryKey;
+use crate::select;
+use crate::storage::Storage;
+use crate::utils;
+
+pub async fn execute(
+    from: String,
+    to: String,
+    recursive: bool,
+    dry_run: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    if let Some((source, target)) = vault_pair(&from, &to, &vault)? {
+        return transfer(source, target, &from, &to, recursive, dry_run, false);
+    }
+    let (from, to) = (select::split_vault(&from).1, select::split_vault(&to).1);
+
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    let keys = select::select_keys(&storage, from, recursive)?;
+    let copies = select::relocate(&keys, from, recursive, to);
+    check_destinations(&storage, &copies)?;
+
+    if dry_run {
+        print_plan(&copies, "copied");
+        return Ok(());
+    }
+
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
+
+    storage.batch(|| {
+        // Copying an alias copies the entry it points to
+        for (src, dest) in &copies {
+            let mut entry = storage.load_entry(src, &master_key)?;
+            let now = Utc::now();
+            entry.id = Uuid::new_v4();
+            entry.key = dest.clone();
+            entry.created_at = now;
+            entry.updated_at = now;
+            entry.accessed_at = None;
+            storage.store_entry(&entry, &master_key)?;
+        }
+        Ok(())
+    })?;
+
+    // One commit for the whole batch
+    let message = match copies.as_slice() {
+        [(src, dest)] => format!("Copy {} to {}", src, dest),
+        _ => format!("Copy {} entries from {} to {}", copies.len(), from, to),
+    };
+    commit(&storage, &message)?;
+
+    for (src, dest) in &copies {
+        println!(
+            "{} Password copied from '{}' to '{}'",
+            "✓".green().bold(),
+            src.cyan(),
+            dest.cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Open the source and target vaults when `vault:key` addresses name two different vaults
+pub fn vault_pair(
+    from: &str,
+    to: &str,
+    vault: &Option<String>,
+) -> Result<Option<(Storage, Storage)>> {
+    let (from_vault, _) = select::split_vault(from);
+    let (to_vault, _) = select::split_vault(to);
+    if from_vault.is_none() && to_vault.is_none() {
+        return Ok(None);
+    }
+
+    let open = |name: Option<&str>| -> Result<Storage> {
+        let storage = Storage::new(name.map(str::to_string).or_else(|| vault.clone()))?;
+        if !storage.vault_exists() {
+            return Err(anyhow!("Vault '{}' not found", storage.get_vault_name()));
+        }
+        Ok(storage)
+    };
+    let source = open(from_vault)?;
+    let target = open(to_vault)?;
+
+    if source.get_vault_path() == target.get_vault_path() {
+        return Ok(None);
+    }
+    Ok(Some((source, target)))
+}
+
+/// Copy or move entries to another vault, re-encrypting them with its key.
+///
+/// Entries keep their id, metadata, timestamps and history. Both vaults get
+/// a single commit.
+pub fn transfer(
+    source: Storage,
+    target: Storage,
+    from: &str,
+    to: &str,
+    recursive: bool,
+    dry_run: bool,
+    remove_source: bool,
+) -> Result<()> {
+    let (from, to) = (select::split_vault(from).1, select::split_vault(to).1);
+    let (source_name, target_name) = (source.get_vault_name(), target.get_vault_name());
+    let _locks = (source.lock()?, target.lock()?);
+
+    let keys = select::select_keys(&source, from, recursive)?;
+    let pairs: Vec<(String, String)> = if to.is_empty() {
+        // `vault:` keeps the keys as they are
+        keys.iter().map(|k| (k.clone(), k.clone())).collect()
+    } else {
+        select::relocate(&keys, from, recursive, to)
+    };
+    check_destinations(&target, &pairs)?;
+
+    if remove_source {
+        for (src, _) in &pairs {
+            if let Some(alias_target) = source.read_alias(src)? {
+                return Err(anyhow!(
+                    "'{}' is an alias of '{}'; move '{}' instead",
+                    src,
+                    alias_target,
+                    alias_target
+                ));
+            }
+        }
+    }
+
+    let verb = if remove_source { "moved" } else { "copied" };
+    if dry_run {
+        let plan: Vec<(String, String)> = pairs
+            .iter()
+            .map(|(src, dest)| {
+                (
+                    format!("{}:{}", source_name, src),
+                    format!("{}:{}", target_name, dest),
+                )
+            })
+            .collect();
+        print_plan(&plan, verb);
+        return Ok(());
+    }
+
+    let source_key = utils::get_master_key(&source)?;
+    let target_key = utils::get_master_key(&target)?;
+
+    let mut derived = Vec::new();
+    for (src, dest) in &pairs {
+        let mut entry = source.load_entry(src, &source_key)?;
+        if entry.metadata.derivation.is_some() {
+            derived.push(dest.clone());
+        }
+        entry.key = dest.clone();
+        let entry = target.adopt_entry(entry, &source, &source_key, &target_key)?;
+        target.store_entry(&entry, &target_key)?;
+    }
+
+    // The source is only touched once every entry is safely in the target
+    let mut dangling = Vec::new();
+    if remove_source {
+        let aliases = source.list_aliases()?;
+        for (src, _) in &pairs {
+            source.delete_entry(src)?;
+            dangling.extend(
+                aliases
+                    .iter()
+                    .filter(|(_, alias_target)| alias_target == src)
+                    .map(|(alias, _)| alias.clone()),
+            );
+        }
+    }
+
+    let (action, count) = (if remove_source { "Move" } else { "Copy" }, pairs.len());
+    let (target_message, source_message) = match pairs.as_slice() {
+        [(src, dest)] => (
+            format!("{} {} from {}:{}", action, dest, source_name, src),
+            format!("{} {} to {}:{}", action, src, target_name, dest),
+        ),
+        _ => (
+            format!("{} {} entries from {}:{}", action, count, source_name, from),
+            format!("{} {} entries to {}:{}", action, count, target_name, to),
+        ),
+    };
+    commit(&target, &target_message)?;
+    if remove_source {
+        commit(&source, &source_message)?;
+    }
+
+    for (src, dest) in &pairs {
+        println!(
+            "{} Password {} from '{}' to '{}'",
+            "✓".green().bold(),
+            verb,
+            format!("{}:{}", source_name, src).cyan(),
+            format!("{}:{}", target_name, dest).cyan()
+        );
+    }
+    for key in &derived {
+        println!(
+            "  {} '{}' was a derived password; its current value is now stored in '{}'",
+            "!".yellow().bold(),
+            key,
+            target_name
+        );
+    }
+    if !dangling.is_empty() {
+        println!(
+            "  {} Aliases left pointing at moved entries in '{}': {}",
+            "!".yellow().bold(),
+            source_name,
+            dangling.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Refuse a batch when a destination exists or would be written twice
+pub fn check_destinations(storage: &Storage, pairs: &[(String, String)]) -> Result<()> {
+    let mut seen = Vec::new();
+    for (src, dest) in pairs {
+        if dest.is_empty() || dest.ends_with('/') {
+            return Err(anyhow!("Invalid destination for '{}'", src));
+        }
+        let normalized = EntryKey::parse(dest)?;
+        if seen.contains(&normalized) {
+            return Err(anyhow!(
+                "More than one entry would be written to '{}'",
+                normalized
+            ));
+        }
+        if storage.entry_exists(dest)? || storage.read_alias(dest)?.is_some() {
+            return Err(anyhow!("Entry '{}' already exists", normalized));
+        }
+        seen.push(normalized);
+    }
+    Ok(())
+}
+
+pub fn print_plan(pairs: &[(String, String)], verb: &str) {
+    for (src, dest) in pairs {
+        println!("  {} → {}", src.cyan(), dest.cyan());
+    }
+    println!("{} entries would be {} (dry run)", pairs.len(), verb);
+}
+
+fn commit(storage: &Storage, message: &str) -> Result<()> {
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(storage.get_vault_path(), message)?;
+
+        let config = storage.load_config()?;
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{disk_vault, test_entry};
+    use crate::types::{Attachment, MasterKey};
+
+    fn value(storage: &Storage, key: &str, master_key: &MasterKey) -> String {
+        let entry = storage.load_entry(key, master_key).unwrap();
+        utils::entry_value(&entry, master_key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_copy_across_vaults() {
+        let (personal, personal_key) = disk_vault();
+        let (team, team_key) = disk_vault();
+        let source = Storage::new(Some(personal.clone())).unwrap();
+        let target = Storage::new(Some(team.clone())).unwrap();
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"certificate").unwrap();
+        let (blob, size) = source.store_attachment(file.path(), &personal_key).unwrap();
+        let mut entry = test_entry("web/github", b"hunter2", &personal_key);
+        entry.metadata.attachments.push(Attachment {
+            name: "recovery.txt".to_string(),
+            blob,
+            size,
+            added_at: Utc::now(),
+        });
+        source.store_entry(&entry, &personal_key).unwrap();
+        source
+            .store_entry(
+                &test_entry("web/gitlab", b"s3cret", &personal_key),
+                &personal_key,
+            )
+            .unwrap();
+
+        execute(
+            format!("{}:web/**", personal),
+            format!("{}:shared/", team),
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Re-encrypted under the target's key, attachment included
+        assert_eq!(value(&target, "shared/github", &team_key), "hunter2");
+        assert_eq!(value(&target, "shared/gitlab", &team_key), "s3cret");
+        let copied = target.load_entry("shared/github", &team_key).unwrap();
+        assert_eq!(copied.id, entry.id);
+        let mut data = Vec::new();
+        target
+            .read_attachment(&copied.metadata.attachments[0].blob, &mut data, &team_key)
+            .unwrap();
+        assert_eq!(data, b"certificate");
+
+        // The source is left alone, and existing keys are not overwritten
+        assert_eq!(value(&source, "web/github", &personal_key), "hunter2");
+        let again = execute(
+            format!("{}:web/github", personal),
+            format!("{}:shared/github", team),
+            false,
+            false,
+            None,
+        )
+        .await;
+        assert!(again.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_move_across_vaults() {
+        let (personal, personal_key) = disk_vault();
+        let (team, team_key) = disk_vault();
+        let source = Storage::new(Some(personal.clone())).unwrap();
+        let target = Storage::new(Some(team.clone())).unwrap();
+        source
+            .store_entry(&test_entry("aws", b"AKIA", &personal_key), &personal_key)
+            .unwrap();
+        source.store_alias("aws-current", "aws").unwrap();
+
+        // An alias cannot be moved on its own
+        let moved = crate::commands::move_cmd::execute(
+            format!("{}:aws-current", personal),
+            format!("{}:", team),
+            false,
+            false,
+            false,
+            None,
+        )
+        .await;
+        assert!(moved.is_err());
+
+        crate::commands::move_cmd::execute(
+            format!("{}:aws", personal),
+            format!("{}:", team),
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(value(&target, "aws", &team_key), "AKIA");
+        assert!(!source.entry_stored("aws").unwrap());
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::crypto::Crypto;
+use crate::git::Git;
+use crate::storage::Storage;
+use crate::types::{DerivationParams, Entry, EntryMetadata, EntryType, GenerateOptions};
+use crate::utils;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn execute(
+    site: String,
+    login: Option<String>,
+    counter: Option<u32>,
+    key: Option<String>,
+    policy: Option<GenerateOptions>,
+    copy: bool,
+    no_save: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
+
+    let site = site.trim().to_lowercase();
+    if site.is_empty() {
+        return Err(anyhow!("Site cannot be empty"));
+    }
+    let entry_key = key.unwrap_or_else(|| site.clone());
+
+    // Reuse the saved parameters of an existing derived entry
+    let existing = if storage.entry_exists(&entry_key)? {
+        let entry = storage.load_entry(&entry_key, &master_key)?;
+        if entry.metadata.derivation.is_none() {
+            return Err(anyhow!(
+                "Entry '{}' already exists and is not a derived entry. Use --key to pick another name",
+                entry_key
+            ));
+        }
+        Some(entry)
+    } else {
+        None
+    };
+    let saved = existing
+        .as_ref()
+        .and_then(|entry| entry.metadata.derivation.clone());
+
+    let params = DerivationParams {
+        site: site.clone(),
+        login: login
+            .or_else(|| saved.as_ref().map(|p| p.login.clone()))
+            .unwrap_or_default(),
+        counter: counter
+            .or_else(|| saved.as_ref().map(|p| p.counter))
+            .unwrap_or(1),
+        policy: policy
+            .or_else(|| saved.as_ref().map(|p| p.policy.clone()))
+            .unwrap_or_else(Crypto::derivation_policy),
+    };
+
+    let password = Crypto::derive_site_password(&master_key, &params)?;
+
+    if !no_save {
+        let now = Utc::now();
+        let entry = match existing {
+            Some(mut entry) => {
+                entry.metadata.derivation = Some(params.clone());
+                entry.metadata.username = non_empty(&params.login);
+                entry.updated_at = now;
+                entry
+            }
+            None => Entry {
+                id: Uuid::new_v4(),
+                key: entry_key.clone(),
+                // Derived passwords are never stored
+                value: Crypto::encrypt(b"", &master_key)?,
+                metadata: EntryMetadata {
+                    entry_type: EntryType::Password,
+                    url: Some(site.clone()),
+                    username: non_empty(&params.login),
+                    derivation: Some(params.clone()),
+                    ..EntryMetadata::default()
+                },
+                created_at: now,
+                updated_at: now,
+                accessed_at: None,
+            },
+        };
+
+        storage.store_entry(&entry, &master_key)?;
+
+        // Commit if git enabled
+        let config = storage.load_config()?;
+        if Git::is_repo(storage.get_vault_path())? {
+            Git::commit(storage.get_vault_path(), &format!("Derive {}", entry_key))?;
+
+            if config.auto_sync && config.git_remote.is_some() {
+                let _ = Git::push(storage.get_vault_path());
+            }
+        }
+    }
+
+    let login_display = if params.login.is_empty() {
+        String::new()
+    } else {
+        format!(" as {}", params.login.cyan())
+    };
+    println!(
+        "{} Derived password for '{}'{} (counter {})",
+        "🔑".green().bold(),
+        site.cyan(),
+        login_display,
+        params.counter.to_string().yellow()
+    );
+
+    if copy {
+        let timeout = Config::load().map(|c| c.clipboard_timeout).unwrap_or(45);
+        utils::copy_to_clipboard(&password, timeout)?;
+        println!(
+            "{} Copied to clipboard (clears in {} seconds)",
+            "📋".green().bold(),
+            timeout.to_string().yellow()
+        );
+    } else {
+        println!("{}", password.green().bold());
+    }
+
+    if !no_save {
+        println!(
+            "Saved as '{}' (site, login and counter only)",
+            entry_key.cyan()
+        );
+    }
+
+    Ok(())
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::crypto::Crypto;
+use crate::diff;
+use crate::storage::Storage;
+use crate::types::MasterKey;
+use crate::utils;
+
+type Flattened = BTreeMap<String, BTreeMap<String, String>>;
+
+pub async fn execute(
+    a: String,
+    b: Option<String>,
+    export: Option<PathBuf>,
+    password: Option<String>,
+) -> Result<()> {
+    let left = open_vault(&a)?;
+    let left_key = utils::get_master_key(&left)?;
+
+    let (right_name, left_entries, right_entries) = match (b, export) {
+        (Some(b), None) => {
+            let right = open_vault(&b)?;
+            let right_key = utils::get_master_key(&right)?;
+            (
+                format!("vault '{}'", b),
+                flatten_vault(&left, &left_key, true)?,
+                flatten_vault(&right, &right_key, true)?,
+            )
+        }
+        (None, Some(file)) => {
+            let data =
+                fs::read(&file).map_err(|e| anyhow!("Failed to read {}: {}", file.display(), e))?;
+            let password = match password {
+                Some(password) => password,
+                None => utils::prompt_password("Export password")?,
+            };
+            let exported = Storage::open_export(&data, &password)?;
+            let config = exported.load_config()?;
+
+            // An export of the same vault shares its key; otherwise the export
+            // password is tried as the master password first
+            let export_key = if config.id == left.load_config()?.id {
+                left_key.clone()
+            } else {
+                let key = Crypto::derive_key(&password, config.id.as_bytes())?;
+                if opens(&exported, &key)? {
+                    key
+                } else {
+                    let master = utils::prompt_password("Master password of the exported vault")?;
+                    Crypto::derive_key(&master, config.id.as_bytes())?
+                }
+            };
+
+            // Exports older than 2.0 carry no attachments, so both sides
+            // compare them by size
+            let attachments = Storage::export_has_attachments(&data);
+            let flattened = flatten_vault(&exported, &export_key, attachments)
+                .map_err(|e| anyhow!("Could not decrypt the export's entries: {}", e))?;
+            (
+                format!("export {}", file.display()),
+                flatten_vault(&left, &left_key, attachments)?,
+                flattened,
+            )
+        }
+        _ => return Err(anyhow!("Give a second vault or --export <file>")),
+    };
+
+    println!(
+        "{} Comparing vault '{}' with {}\n",
+        "🔍".blue(),
+        a.cyan(),
+        right_name.cyan()
+    );
+
+    // Values are only shown as hashes salted for this run, so they can be told
+    // apart without being recoverable from the output
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill(&mut salt);
+    let hash = |value: Option<&String>| match value {
+        Some(value) => {
+            let mut hasher = Sha256::new();
+            hasher.update(salt);
+            hasher.update(value.as_bytes());
+            hex::encode(&hasher.finalize()[..4])
+        }
+        None => "(none)".to_string(),
+    };
+
+    let (mut added, mut removed, mut changed, mut identical) = (0, 0, 0, 0);
+    let mut keys: Vec<&String> = left_entries.keys().chain(right_entries.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        match (left_entries.get(key), right_entries.get(key)) {
+            (None, Some(_)) => {
+                println!("  {} {}", "+".green().bold(), key.green());
+                added += 1;
+            }
+            (Some(_), None) => {
+                println!("  {} {}", "-".red().bold(), key.red());
+                removed += 1;
+            }
+            (Some(left), Some(right)) => {
+                let fields = diff::changed_fields(left, right);
+                if fields.is_empty() {
+                    identical += 1;
+                    continue;
+                }
+                println!("  {} {}", "~".yellow().bold(), key.yellow());
+                for field in fields {
+                    println!(
+                        "      {}: {} → {}",
+                        field,
+                        hash(left.get(&field)),
+                        hash(right.get(&field))
+                    );
+                }
+                changed += 1;
+            }
+            (None, None) => {}
+        }
+    }
+
+    if added + removed + changed == 0 {
+        println!(
+            "{} No differences ({} entries)",
+            "✓".green().bold(),
+            identical
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} added, {} removed, {} changed, {} identical",
+        added, removed, changed, identical
+    );
+    // Like diff(1), report differences through the exit status
+    std::process::exit(1);
+}
+
+fn open_vault(name: &str) -> Result<Storage> {
+    let storage = Storage::new(Some(name.to_string()))?;
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault '{}' does not exist", name));
+    }
+    Ok(storage)
+}
+
+fn flatten_vault(
+    storage: &Storage,
+    master_key: &MasterKey,
+    attachments: bool,
+) -> Result<Flattened> {
+    let mut flattened = Flattened::new();
+    for (key, entry) in storage.load_entries(&storage.list_entries()?, master_key) {
+        let entry = entry?;
+        let source = attachments.then_some(storage);
+        flattened.insert(key, diff::flatten(source, &entry, master_key)?);
+    }
+    Ok(flattened)
+}
+
+/// Whether a key decrypts the first entry of a vault
+fn opens(storage: &Storage, master_key: &MasterKey) -> Result<bool> {
+    Ok(storage
+        .list_entries()?
+        .first()
+        .is_none_or(|key| storage.load_entry(key, master_key).is_ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{disk_vault, test_entry};
+
+    #[tokio::test]
+    async fn test_diff_vaults() {
+        let (personal, personal_key) = disk_vault();
+        let (team, team_key) = disk_vault();
+        let left = open_vault(&personal).unwrap();
+        let right = open_vault(&team).unwrap();
+        for (key, value) in [("aws", "AKIA"), ("db/prod", "hunter2")] {
+            left.store_entry(
+                &test_entry(key, value.as_bytes(), &personal_key),
+                &personal_key,
+            )
+            .unwrap();
+            right
+                .store_entry(&test_entry(key, value.as_bytes(), &team_key), &team_key)
+                .unwrap();
+        }
+
+        // Equal content under different keys is no difference
+        execute(personal.clone(), Some(team.clone()), None, None)
+            .await
+            .unwrap();
+
+        let mut entry = right.load_entry("db/prod", &team_key).unwrap();
+        entry.metadata.username = Some("admin".to_string());
+        right.store_entry(&entry, &team_key).unwrap();
+        right
+            .store_entry(&test_entry("web", b"s3cret", &team_key), &team_key)
+            .unwrap();
+        left.delete_entry("aws").unwrap();
+
+        let left_entries = flatten_vault(&left, &personal_key, true).unwrap();
+        let right_entries = flatten_vault(&right, &team_key, true).unwrap();
+        assert!(!left_entries.contains_key("aws"));
+        assert!(right_entries.contains_key("aws"));
+        assert!(right_entries.contains_key("web"));
+        assert_eq!(
+            diff::changed_fields(&left_entries["db/prod"], &right_entries["db/prod"]),
+            ["username"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff_against_export() {
+        let (personal, master_key) = disk_vault();
+        let storage = open_vault(&personal).unwrap();
+        storage
+            .store_entry(&test_entry("aws", b"AKIA", &master_key), &master_key)
+            .unwrap();
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(
+            file.path(),
+            storage.export_vault("export password").unwrap(),
+        )
+        .unwrap();
+
+        // An export of the same vault is read with the vault's own key
+        execute(
+            personal,
+            None,
+            Some(file.path().to_path_buf()),
+            Some("export password".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(opens(&storage, &master_key).unwrap());
+        assert!(
+            !opens(
+                &storage,
+                &Crypto::derive_key("wrong", b"some other vault id").unwrap()
+            )
+            .unwrap()
+        );
+    }
+}
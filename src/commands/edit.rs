@@ -1,14 +1,37 @@
 use anyhow::{Result, anyhow};
 use chrono::Utc;
 use colored::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
 
+use crate::cli::EntryFlags;
+use crate::config::Config;
 use crate::crypto::Crypto;
+use crate::schema;
 use crate::storage::Storage;
-use crate::types::{EntryMetadata, EntryType};
+use crate::tags;
+use crate::types::{Entry, EntryType, FieldValue, MasterKey};
 use crate::utils;
 
-pub async fn execute(key: String, value: Option<String>, vault: Option<String>) -> Result<()> {
+/// Why the value of a derived entry cannot be set, which would leave `get`
+/// computing the old password
+const DERIVED_VALUE: &str = "Derived entries have no stored value; use 'bunker rotate' or 'bunker derive --counter' to change it";
+
+pub async fn execute(
+    key: String,
+    value: Option<String>,
+    editor: bool,
+    flags: EntryFlags,
+    vault: Option<String>,
+) -> Result<()> {
+    if editor && !flags.is_empty() {
+        return Err(anyhow!("--editor cannot be combined with other edit flags"));
+    }
+
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
@@ -16,11 +39,65 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Load existing entry
     let mut entry = storage.load_entry(&key, &master_key)?;
 
+    if editor {
+        let previous = entry.value.clone();
+        let current_value = utils::entry_value(&entry, &master_key)?;
+        if !edit_document(&mut entry, &master_key)? {
+            println!("{}", "No changes".yellow());
+            return Ok(());
+        }
+
+        if utils::entry_value(&entry, &master_key)? != current_value {
+            entry.push_history(previous, storage.load_config()?.history_limit);
+        }
+        entry.updated_at = Utc::now();
+        storage.store_entry(&entry, &master_key)?;
+
+        println!(
+            "{} Entry '{}' updated successfully",
+            "✓".green().bold(),
+            key.cyan()
+        );
+        return Ok(());
+    }
+
+    // Flags describe the whole edit; skip the menu
+    if value.is_some() || !flags.is_empty() {
+        let value = if flags.stdin {
+            Some(utils::read_stdin_value()?)
+        } else {
+            value
+        };
+        let current_value = utils::entry_value(&entry, &master_key)?;
+        let previous = entry.value.clone();
+
+        if value.is_some() && entry.metadata.derivation.is_some() {
+            return Err(anyhow!(DERIVED_VALUE));
+        }
+        if let Some(v) = value {
+            entry.value = Crypto::encrypt(v.as_bytes(), &master_key)?;
+        }
+        apply_flags(&mut entry, &flags, &master_key)?;
+
+        if utils::entry_value(&entry, &master_key)? != current_value {
+            entry.push_history(previous, storage.load_config()?.history_limit);
+        }
+        entry.updated_at = Utc::now();
+        storage.store_entry(&entry, &master_key)?;
+
+        println!(
+            "{} Entry '{}' updated successfully",
+            "✓".green().bold(),
+            key.cyan()
+        );
+        return Ok(());
+    }
+
     println!("{} Editing entry '{}'", "✏️".blue(), key.cyan().bold());
     println!(
         "Current type: {}",
@@ -35,7 +112,12 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
 
     // Ask what to edit
     println!("\nWhat would you like to edit?");
-    println!("  {} Password/Value", "1.".blue());
+    let schema = schema::fields(&entry.metadata.entry_type);
+    if schema.is_some() {
+        println!("  {} Fields", "1.".blue());
+    } else {
+        println!("  {} Password/Value", "1.".blue());
+    }
     println!("  {} Entry Type", "2.".blue());
     println!("  {} Username", "3.".blue());
     println!("  {} Notes", "4.".blue());
@@ -47,16 +129,34 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
     let choice = utils::prompt_input("Choice (1-8): ")?;
 
     match choice.as_str() {
+        "1" if schema.is_some() => {
+            // Edit structured fields, keeping current values by default
+            if entry.metadata.derivation.is_some() {
+                return Err(anyhow!(DERIVED_VALUE));
+            }
+            let fields = schema.unwrap_or_default();
+            let current = schema::read_fields(&entry, fields, &master_key)?;
+            let values = schema::prompt_fields(fields, Some(&current), None)?;
+            let previous = entry.value.clone();
+            schema::apply_fields(&mut entry, fields, &values, &master_key)?;
+            if values.get(fields[0].name) != current.get(fields[0].name) {
+                entry.push_history(previous, storage.load_config()?.history_limit);
+            }
+            println!("{} Fields updated", "✓".green());
+        }
+
         "1" => {
             // Edit password/value
-            let new_value = if let Some(v) = value {
-                v
-            } else {
-                utils::prompt_password(&format!("Enter new value for '{}': ", key))?
-            };
+            if entry.metadata.derivation.is_some() {
+                return Err(anyhow!(DERIVED_VALUE));
+            }
+            let new_value = utils::prompt_password(&format!("Enter new value for '{}': ", key))?;
 
-            let encrypted_value = Crypto::encrypt(new_value.as_bytes(), &master_key)?;
-            entry.value = encrypted_value;
+            if new_value != current_value {
+                let encrypted_value = Crypto::encrypt(new_value.as_bytes(), &master_key)?;
+                let previous = std::mem::replace(&mut entry.value, encrypted_value);
+                entry.push_history(previous, storage.load_config()?.history_limit);
+            }
             println!("{} Value updated", "✓".green());
         }
 
@@ -98,6 +198,16 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
             };
 
             if new_type != entry.metadata.entry_type {
+                // Typed entries need their fields; the current value becomes the primary one
+                if let Some(fields) = schema::fields(&new_type) {
+                    if entry.metadata.derivation.is_some() {
+                        return Err(anyhow!(DERIVED_VALUE));
+                    }
+                    let values = schema::prompt_fields(fields, None, Some(current_value))?;
+                    schema::apply_fields(&mut entry, fields, &values, &master_key)?;
+                } else {
+                    entry.metadata.fields.clear();
+                }
                 entry.metadata.entry_type = new_type;
                 println!("{} Entry type updated", "✓".green());
             }
@@ -159,11 +269,11 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
                 entry.metadata.tags.clear();
                 println!("{} Tags removed", "✓".green());
             } else {
-                let new_tags: Vec<String> = new_tags_input
+                let new_tags = new_tags_input
                     .split(',')
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect();
+                    .filter(|s| !s.trim().is_empty())
+                    .map(tags::validate)
+                    .collect::<Result<Vec<_>>>()?;
                 entry.metadata.tags = new_tags;
                 println!("{} Tags updated", "✓".green());
             }
@@ -171,13 +281,16 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
 
         "7" => {
             // Edit custom fields
-            if entry.metadata.custom_fields.is_empty() {
+            if entry.metadata.custom_fields.is_empty() && entry.metadata.secret_fields.is_empty() {
                 println!("No custom fields currently set");
             } else {
                 println!("Current custom fields:");
                 for (field_name, field_value) in &entry.metadata.custom_fields {
                     println!("  {}: {}", field_name.cyan(), field_value.green());
                 }
+                for field_name in entry.metadata.secret_fields.keys() {
+                    println!("  {}: {}", field_name.cyan(), "******** (secret)".green());
+                }
             }
 
             let action = utils::prompt_input(
@@ -186,13 +299,23 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
             match action.to_lowercase().as_str() {
                 "add" => {
                     let field_name = utils::prompt_input("Enter field name: ")?;
-                    let field_value = utils::prompt_input("Enter field value: ")?;
-                    entry.metadata.custom_fields.insert(field_name, field_value);
+                    if utils::prompt_confirm("Is this a secret value?")? {
+                        let field_value = utils::prompt_password("Enter field value")?;
+                        let encrypted = Crypto::encrypt(field_value.as_bytes(), &master_key)?;
+                        entry.metadata.custom_fields.remove(&field_name);
+                        entry.metadata.secret_fields.insert(field_name, encrypted);
+                    } else {
+                        let field_value = utils::prompt_input("Enter field value: ")?;
+                        entry.metadata.secret_fields.remove(&field_name);
+                        entry.metadata.custom_fields.insert(field_name, field_value);
+                    }
                     println!("{} Custom field added", "✓".green());
                 }
                 "remove" => {
                     let field_name = utils::prompt_input("Enter field name to remove: ")?;
-                    if entry.metadata.custom_fields.remove(&field_name).is_some() {
+                    let removed = entry.metadata.custom_fields.remove(&field_name).is_some()
+                        | entry.metadata.secret_fields.remove(&field_name).is_some();
+                    if removed {
                         println!("{} Custom field removed", "✓".green());
                     } else {
                         println!("{} Field not found", "⚠️".yellow());
@@ -227,3 +350,442 @@ pub async fn execute(key: String, value: Option<String>, vault: Option<String>)
 
     Ok(())
 }
+
+/// Apply the metadata flags of `add` or `edit` to an entry
+pub fn apply_flags(entry: &mut Entry, flags: &EntryFlags, master_key: &MasterKey) -> Result<()> {
+    let optional = |v: &String| Some(v.clone()).filter(|v| !v.trim().is_empty());
+    if let Some(username) = &flags.username {
+        entry.metadata.username = optional(username);
+    }
+    if let Some(url) = &flags.url {
+        entry.metadata.url = optional(url);
+    }
+    if let Some(notes) = &flags.notes {
+        entry.metadata.notes = optional(notes);
+    }
+    for tag in &flags.tags {
+        let tag = tags::validate(tag)?;
+        if !entry.metadata.tags.contains(&tag) {
+            entry.metadata.tags.push(tag);
+        }
+    }
+    if let Some(expires) = &flags.expires {
+        entry.metadata.expires_at = utils::parse_expiry(expires)?;
+    }
+
+    let retyped = match &flags.entry_type {
+        Some(t) if *t != entry.metadata.entry_type => {
+            entry.metadata.entry_type = t.clone();
+            true
+        }
+        _ => false,
+    };
+    let schema = schema::fields(&entry.metadata.entry_type);
+
+    // Fields named in the type's schema are typed fields, the rest are custom
+    let mut typed = BTreeMap::new();
+    for field in &flags.fields {
+        let (name, value) = utils::parse_key_value(field)?;
+        if schema.is_some_and(|s| s.iter().any(|spec| spec.name == name)) {
+            typed.insert(name, value);
+            continue;
+        }
+        entry.metadata.secret_fields.remove(&name);
+        if value.is_empty() {
+            entry.metadata.custom_fields.remove(&name);
+        } else {
+            entry.metadata.custom_fields.insert(name, value);
+        }
+    }
+
+    match schema {
+        Some(fields) if retyped || !typed.is_empty() => {
+            // The primary field would store a value the entry derives
+            if entry.metadata.derivation.is_some() {
+                return Err(anyhow!(DERIVED_VALUE));
+            }
+            // A retyped entry starts from its current value as the primary field
+            let mut values = if retyped {
+                BTreeMap::from([(
+                    fields[0].name.to_string(),
+                    utils::entry_value(entry, master_key)?,
+                )])
+            } else {
+                schema::read_fields(entry, fields, master_key)?
+            };
+            values.extend(typed);
+            schema::validate(fields, &values)?;
+            schema::apply_fields(entry, fields, &values, master_key)?;
+        }
+        None if retyped => entry.metadata.fields.clear(),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// An entry in the editable document form used by `edit --editor`
+#[derive(Serialize, Deserialize, PartialEq)]
+struct EntryDocument {
+    #[serde(rename = "type")]
+    entry_type: String,
+    value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// YYYY-MM-DD or an RFC 3339 timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires: Option<String>,
+    /// Typed fields of card, identity, database and SSH key entries
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+    #[serde(default)]
+    custom: BTreeMap<String, String>,
+    /// Custom fields stored encrypted
+    #[serde(default)]
+    secret: BTreeMap<String, String>,
+}
+
+/// Open the entry in the user's editor until it parses and validates.
+/// Returns false when the document was left unchanged.
+fn edit_document(entry: &mut Entry, master_key: &MasterKey) -> Result<bool> {
+    let original = to_document(entry, master_key)?;
+    let mut text = format!(
+        "# Editing '{}'. Save and quit to apply; leave unchanged to cancel.\n\n{}",
+        entry.key,
+        toml::to_string_pretty(&original)?
+    );
+
+    let file = PlaintextFile::new()?;
+    let editor = Config::load()?.editor();
+
+    let result = loop {
+        // Go through the path: editors may replace the file instead of writing to it
+        fs::write(file.path(), &text)?;
+        let status = run_editor(&editor, file.path());
+        text = fs::read_to_string(file.path()).unwrap_or_default();
+        if let Err(e) = status {
+            break Err(e);
+        }
+
+        let parsed = toml::from_str::<EntryDocument>(&text)
+            .map_err(|e| anyhow!("Invalid document: {}", e))
+            .and_then(|document| {
+                if document == original {
+                    return Ok(None);
+                }
+                let mut updated = entry.clone();
+                apply_document(&mut updated, document, master_key)?;
+                Ok(Some(updated))
+            });
+        match parsed {
+            Ok(updated) => break Ok(updated),
+            Err(e) => {
+                println!("{} {}", "⚠️".yellow(), e);
+                if !utils::prompt_confirm("Edit again?")? {
+                    break Err(anyhow!("Edit cancelled"));
+                }
+            }
+        }
+    };
+
+    drop(file);
+
+    match result? {
+        Some(updated) => {
+            *entry = updated;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// The temporary file an entry is edited in. Its plaintext is overwritten
+/// before it is removed, however the edit ends.
+struct PlaintextFile(tempfile::NamedTempFile);
+
+impl PlaintextFile {
+    fn new() -> Result<Self> {
+        // Keep the plaintext off disk when a RAM-backed directory is available
+        let dir = Path::new("/dev/shm");
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("bunker-").suffix(".toml");
+        let file = if dir.is_dir() {
+            builder.tempfile_in(dir)?
+        } else {
+            builder.tempfile()?
+        };
+        Ok(Self(file))
+    }
+
+    fn path(&self) -> &Path {
+        self.0.path()
+    }
+}
+
+impl Drop for PlaintextFile {
+    fn drop(&mut self) {
+        // Go through the path: the editor may have replaced the file
+        if let Ok(mut plain) = fs::OpenOptions::new().write(true).open(self.path()) {
+            let len = plain.metadata().map(|m| m.len()).unwrap_or(0);
+            let _ = plain.write_all(&vec![0u8; len as usize]);
+            let _ = plain.sync_all();
+        }
+    }
+}
+
+fn run_editor(editor: &str, path: &Path) -> Result<()> {
+    // The editor setting may carry arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow!("No editor configured"))?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow!("Failed to start editor '{}': {}", editor, e))?;
+    if !status.success() {
+        return Err(anyhow!(
+            "Editor exited with status {}",
+            status.code().unwrap_or(-1)
+        ));
+    }
+    Ok(())
+}
+
+fn to_document(entry: &Entry, master_key: &MasterKey) -> Result<EntryDocument> {
+    let metadata = &entry.metadata;
+    let mut fields = BTreeMap::new();
+    for (name, value) in &metadata.fields {
+        fields.insert(name.clone(), schema::read_field(value, master_key)?);
+    }
+    let mut secret = BTreeMap::new();
+    for (name, value) in &metadata.secret_fields {
+        secret.insert(
+            name.clone(),
+            schema::read_field(&FieldValue::Secret(value.clone()), master_key)?,
+        );
+    }
+
+    Ok(EntryDocument {
+        entry_type: type_name(&metadata.entry_type),
+        value: utils::entry_value(entry, master_key)?,
+        username: metadata.username.clone(),
+        url: metadata.url.clone(),
+        notes: metadata.notes.clone(),
+        tags: metadata.tags.clone(),
+        expires: metadata.expires_at.map(|d| d.to_rfc3339()),
+        fields,
+        custom: metadata.custom_fields.clone().into_iter().collect(),
+        secret,
+    })
+}
+
+fn apply_document(
+    entry: &mut Entry,
+    document: EntryDocument,
+    master_key: &MasterKey,
+) -> Result<()> {
+    let entry_type: EntryType = document.entry_type.parse().map_err(|e| anyhow!("{}", e))?;
+
+    if entry.metadata.derivation.is_some()
+        && document.value != utils::entry_value(entry, master_key)?
+    {
+        return Err(anyhow!(DERIVED_VALUE));
+    }
+
+    match schema::fields(&entry_type) {
+        Some(_) if entry.metadata.derivation.is_some() => return Err(anyhow!(DERIVED_VALUE)),
+        Some(schema) => {
+            let mut values = document.fields;
+            values.insert(schema[0].name.to_string(), document.value);
+            schema::validate(schema, &values)?;
+            schema::apply_fields(entry, schema, &values, master_key)?;
+        }
+        None => {
+            if !document.fields.is_empty() {
+                return Err(anyhow!(
+                    "Type '{}' has no typed fields; use [custom] instead",
+                    document.entry_type
+                ));
+            }
+            entry.metadata.fields.clear();
+            if entry.metadata.derivation.is_none() {
+                entry.value = Crypto::encrypt(document.value.as_bytes(), master_key)?;
+            }
+        }
+    }
+
+    let optional = |v: Option<String>| v.filter(|v| !v.trim().is_empty());
+    entry.metadata.entry_type = entry_type;
+    entry.metadata.username = optional(document.username);
+    entry.metadata.url = optional(document.url);
+    entry.metadata.notes = optional(document.notes);
+    entry.metadata.tags = document
+        .tags
+        .iter()
+        .map(|t| tags::validate(t))
+        .collect::<Result<Vec<_>>>()?;
+    entry.metadata.expires_at = match document.expires {
+        Some(expires) => utils::parse_expiry(&expires)?,
+        None => None,
+    };
+    entry.metadata.custom_fields = document.custom.into_iter().collect();
+    entry.metadata.secret_fields.clear();
+    for (name, value) in document.secret {
+        entry.metadata.custom_fields.remove(&name);
+        entry
+            .metadata
+            .secret_fields
+            .insert(name, Crypto::encrypt(value.as_bytes(), master_key)?);
+    }
+
+    Ok(())
+}
+
+/// Type name as accepted by `--type`
+fn type_name(entry_type: &EntryType) -> String {
+    match entry_type {
+        EntryType::Password => "password",
+        EntryType::Note => "note",
+        EntryType::Card => "card",
+        EntryType::Identity => "identity",
+        EntryType::SecureFile => "file",
+        EntryType::ApiKey => "apikey",
+        EntryType::SshKey => "sshkey",
+        EntryType::Database => "database",
+        EntryType::Custom(name) => name,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::crypto::Crypto;
+    use crate::storage::testing::{disk_vault, test_entry};
+    use crate::types::{DerivationParams, GenerateOptions};
+    use clap::Parser;
+
+    fn derived_entry(key: &str, master_key: &MasterKey) -> Entry {
+        let mut entry = test_entry(key, b"", master_key);
+        entry.metadata.derivation = Some(DerivationParams {
+            site: "example.com".to_string(),
+            login: "alice".to_string(),
+            counter: 1,
+            policy: GenerateOptions::default(),
+        });
+        entry
+    }
+
+    #[tokio::test]
+    async fn test_derived_entry_value_is_refused() {
+        let (vault, master_key) = disk_vault();
+        let storage = Storage::new(Some(vault.clone())).unwrap();
+        storage
+            .store_entry(&derived_entry("site", &master_key), &master_key)
+            .unwrap();
+
+        // --value
+        let result = execute(
+            "site".to_string(),
+            Some("typed".to_string()),
+            false,
+            EntryFlags::default(),
+            Some(vault.clone()),
+        )
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), DERIVED_VALUE);
+
+        // --type with typed fields, which would store the derived value
+        let flags = EntryFlags {
+            entry_type: Some(EntryType::Database),
+            ..EntryFlags::default()
+        };
+        let result = execute("site".to_string(), None, false, flags, Some(vault.clone())).await;
+        assert_eq!(result.unwrap_err().to_string(), DERIVED_VALUE);
+
+        let stored = storage.load_entry("site", &master_key).unwrap();
+        assert_eq!(stored.metadata.entry_type, EntryType::Password);
+        assert!(stored.metadata.fields.is_empty());
+
+        // Metadata can still be changed
+        let flags = EntryFlags {
+            notes: Some("work account".to_string()),
+            ..EntryFlags::default()
+        };
+        execute("site".to_string(), None, false, flags, Some(vault))
+            .await
+            .unwrap();
+        let stored = storage.load_entry("site", &master_key).unwrap();
+        assert_eq!(stored.metadata.notes.as_deref(), Some("work account"));
+        assert!(stored.metadata.derivation.is_some());
+    }
+
+    #[test]
+    fn test_derived_typed_fields_are_refused() {
+        let master_key = MasterKey::new(vec![7; 32]);
+
+        let mut entry = derived_entry("site", &master_key);
+        entry.metadata.entry_type = EntryType::Card;
+        let flags = EntryFlags {
+            fields: vec!["number=4111111111111111".to_string()],
+            ..EntryFlags::default()
+        };
+        let error = apply_flags(&mut entry, &flags, &master_key).unwrap_err();
+        assert_eq!(error.to_string(), DERIVED_VALUE);
+
+        // Retyping in the editor keeps the value but would store it as a field
+        let mut entry = derived_entry("site", &master_key);
+        let mut document = to_document(&entry, &master_key).unwrap();
+        document.entry_type = "database".to_string();
+        let error = apply_document(&mut entry, document, &master_key).unwrap_err();
+        assert_eq!(error.to_string(), DERIVED_VALUE);
+
+        let mut entry = derived_entry("site", &master_key);
+        let mut document = to_document(&entry, &master_key).unwrap();
+        document.value = "typed".to_string();
+        let error = apply_document(&mut entry, document, &master_key).unwrap_err();
+        assert_eq!(error.to_string(), DERIVED_VALUE);
+        assert!(
+            Crypto::decrypt(&entry.value, &master_key)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_plaintext_file_is_wiped() {
+        let file = PlaintextFile::new().unwrap();
+        let path = file.path().to_path_buf();
+        let plaintext = "value = \"hunter2\"";
+        fs::write(&path, plaintext).unwrap();
+        let mut still_open = fs::File::open(&path).unwrap();
+
+        // Dropped the way an error returned mid-edit drops it
+        fn edit(file: PlaintextFile) -> Result<()> {
+            let _file = file;
+            Err(anyhow!("Editor exited with status 1"))
+        }
+        let failed = edit(file);
+        assert!(failed.is_err());
+
+        assert!(!path.exists());
+        let mut left = Vec::new();
+        std::io::Read::read_to_end(&mut still_open, &mut left).unwrap();
+        assert_eq!(left, vec![0u8; plaintext.len()]);
+    }
+
+    #[test]
+    fn test_force_is_add_only() {
+        assert!(Cli::try_parse_from(["bunker", "add", "site", "--force"]).is_ok());
+        assert!(Cli::try_parse_from(["bunker", "edit", "site", "--force"]).is_err());
+    }
+}
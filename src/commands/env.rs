@@ -1,11 +1,16 @@
 use anyhow::{Result, anyhow};
 use colored::*;
 
-use crate::crypto::Crypto;
+use crate::refs;
 use crate::storage::Storage;
 use crate::utils;
 
-pub async fn execute(key: String, var_name: Option<String>, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    key: String,
+    field: Option<String>,
+    var_name: Option<String>,
+    vault: Option<String>,
+) -> Result<()> {
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
@@ -13,19 +18,25 @@ pub async fn execute(key: String, var_name: Option<String>, vault: Option<String
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Load entry
     let entry = storage.load_entry(&key, &master_key)?;
 
-    // Decrypt the value
-    let decrypted = Crypto::decrypt(&entry.value, &master_key)?;
-    let password =
-        String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))?;
+    // Decrypt the value (or the requested field)
+    let password = refs::resolve_field(&storage, &entry, field.as_deref(), &master_key)?;
 
     // Determine variable name
-    let env_var =
-        var_name.unwrap_or_else(|| key.to_uppercase().replace('/', "_").replace('-', "_"));
+    let default_name = match &field {
+        Some(f) => format!("{}_{}", key, f),
+        None => key.clone(),
+    };
+    let env_var = var_name.unwrap_or_else(|| {
+        default_name
+            .to_uppercase()
+            .replace('/', "_")
+            .replace('-', "_")
+    });
 
     // Output export statement
     println!("export {}='{}'", env_var, password);
@@ -34,7 +45,12 @@ pub async fn execute(key: String, var_name: Option<String>, vault: Option<String
     eprintln!(
         "{} Use: {}",
         "💡".yellow(),
-        format!("eval \"$(bunker env {})\"", key).cyan()
+        format!(
+            "eval \"$(bunker env {}{})\"",
+            key,
+            field.map(|f| format!(" --field {}", f)).unwrap_or_default()
+        )
+        .cyan()
     );
 
     Ok(())
@@ -1,13 +1,14 @@
 use anyhow::{Result, anyhow};
 use std::process::Command;
 
-use crate::crypto::Crypto;
+use crate::refs;
 use crate::storage::Storage;
 use crate::utils;
 
 pub async fn execute(
     command: Vec<String>,
     key: String,
+    field: Option<String>,
     env: Option<String>,
     vault: Option<String>,
 ) -> Result<()> {
@@ -22,15 +23,13 @@ pub async fn execute(
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Load entry
     let entry = storage.load_entry(&key, &master_key)?;
 
-    // Decrypt the value
-    let decrypted = Crypto::decrypt(&entry.value, &master_key)?;
-    let password =
-        String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))?;
+    // Decrypt the value (or the requested field)
+    let password = refs::resolve_field(&storage, &entry, field.as_deref(), &master_key)?;
 
     // Prepare command
     let program = &command[0];
@@ -3,8 +3,10 @@ use colored::*;
 use std::fs;
 use std::path::PathBuf;
 
-use crate::crypto::Crypto;
+use crate::refs;
+use crate::select;
 use crate::storage::Storage;
+use crate::tags::TagFilter;
 use crate::types::ExportEntry;
 use crate::utils;
 
@@ -12,45 +14,75 @@ pub async fn execute(
     format: String,
     output: Option<PathBuf>,
     include_metadata: bool,
+    tags: Vec<String>,
+    path: Option<String>,
+    dry_run: bool,
     vault: Option<String>,
 ) -> Result<()> {
+    let filter = TagFilter::parse(&tags)?;
+
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
 
-    // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    // Get all entries, or those under the requested path
+    let mut entry_keys = storage.list_entries()?;
+    if let Some(path) = &path {
+        entry_keys = select::select(&entry_keys, path, true)?;
+    }
+
+    // Tags are plaintext metadata, so a preview needs no master key
+    if dry_run {
+        let mut count = 0;
+        for key in &entry_keys {
+            if filter.matches(&storage.load_metadata(key)?.tags) {
+                println!("  {}", key.cyan());
+                count += 1;
+            }
+        }
+        println!("{} entries would be exported (dry run)", count);
+        return Ok(());
+    }
 
-    // Get all entries
-    let entry_keys = storage.list_entries()?;
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
     let mut export_entries = Vec::new();
+    let mut failed = 0;
 
-    for key in entry_keys {
-        if let Ok(entry) = storage.load_entry(&key, &master_key) {
-            if let Ok(decrypted) = Crypto::decrypt(&entry.value, &master_key) {
-                if let Ok(value) = String::from_utf8(decrypted) {
-                    let export_entry = ExportEntry {
-                        key: entry.key,
-                        value,
-                        username: entry.metadata.username,
-                        url: entry.metadata.url,
-                        notes: if include_metadata {
-                            entry.metadata.notes
-                        } else {
-                            None
-                        },
-                        tags: if include_metadata {
-                            entry.metadata.tags
-                        } else {
-                            Vec::new()
-                        },
-                        created_at: entry.created_at,
-                        updated_at: entry.updated_at,
-                    };
-                    export_entries.push(export_entry);
-                }
+    for (key, entry) in storage.load_entries(&entry_keys, &master_key) {
+        let exported = entry.and_then(|entry| {
+            if !filter.matches(&entry.metadata.tags) {
+                return Ok(None);
+            }
+            let value = refs::resolve_field(&storage, &entry, None, &master_key)
+                .map_err(|e| anyhow!("Cannot resolve value of '{}': {}", key, e))?;
+            Ok(Some(ExportEntry {
+                key: entry.key,
+                value,
+                username: entry.metadata.username,
+                url: entry.metadata.url,
+                notes: if include_metadata {
+                    entry.metadata.notes
+                } else {
+                    None
+                },
+                tags: if include_metadata {
+                    entry.metadata.tags
+                } else {
+                    Vec::new()
+                },
+                created_at: entry.created_at,
+                updated_at: entry.updated_at,
+            }))
+        });
+        match exported {
+            Ok(Some(export_entry)) => export_entries.push(export_entry),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{} {}", "✗".red(), e);
+                failed += 1;
             }
         }
     }
@@ -114,5 +146,14 @@ pub async fn execute(
         print!("{}", content);
     }
 
+    // Report, rather than silently drop, the entries that were left out
+    if failed > 0 {
+        eprintln!(
+            "{} {} entries could not be exported",
+            "⚠️".yellow(),
+            failed.to_string().yellow()
+        );
+    }
+
     Ok(())
 }
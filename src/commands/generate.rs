@@ -1,32 +1,38 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use colored::*;
 
+use crate::cli::EntryFlags;
+use crate::config::Config;
 use crate::crypto::Crypto;
-use crate::types::GenerateOptions;
+use crate::types::{GenerateOptions, PassphraseOptions};
+use crate::utils;
+use crate::wordlist::Wordlist;
 
 pub async fn execute(
     key: Option<String>,
-    length: usize,
-    no_symbols: bool,
-    no_numbers: bool,
-    no_uppercase: bool,
+    options: GenerateOptions,
+    passphrase: Option<PassphraseOptions>,
+    copy: bool,
     vault: Option<String>,
 ) -> Result<()> {
-    let options = GenerateOptions {
-        length,
-        use_uppercase: !no_uppercase,
-        use_lowercase: true,
-        use_numbers: !no_numbers,
-        use_symbols: !no_symbols,
-        exclude_ambiguous: true,
-        custom_charset: None,
-    };
+    if let Some(passphrase_options) = passphrase {
+        return generate_passphrase(key, passphrase_options, copy, vault).await;
+    }
 
-    let password = Crypto::generate_password(&options);
+    let password = Crypto::generate_password(&options)?;
 
     if let Some(k) = key {
         // Save the generated password
-        super::add::execute(k.clone(), Some(password.clone()), false, None, vault).await?;
+        super::add::execute(
+            k.clone(),
+            Some(password.clone()),
+            false,
+            None,
+            false,
+            EntryFlags::default(),
+            vault,
+        )
+        .await?;
         println!(
             "{} Generated and saved password for '{}'",
             "✓".green().bold(),
@@ -38,5 +44,79 @@ pub async fn execute(
         println!("{}", password.green().bold());
     }
 
+    if copy {
+        copy_generated(&password)?;
+    }
+
+    Ok(())
+}
+
+async fn generate_passphrase(
+    key: Option<String>,
+    options: PassphraseOptions,
+    copy: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    if options.words == 0 {
+        return Err(anyhow!("Passphrase must contain at least one word"));
+    }
+
+    let wordlist = match &options.wordlist {
+        Some(path) => Wordlist::from_file(path)?,
+        None => Wordlist::builtin(),
+    };
+
+    let passphrase = Crypto::generate_passphrase(&options, &wordlist);
+    let entropy = Crypto::passphrase_entropy(&options, &wordlist);
+
+    if let Some(k) = key {
+        // Save the generated passphrase
+        super::add::execute(
+            k.clone(),
+            Some(passphrase.clone()),
+            false,
+            None,
+            false,
+            EntryFlags::default(),
+            vault,
+        )
+        .await?;
+        println!(
+            "{} Generated and saved passphrase for '{}'",
+            "✓".green().bold(),
+            k.cyan()
+        );
+        println!("Passphrase: {}", passphrase.yellow());
+    } else {
+        println!("{}", passphrase.green().bold());
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Entropy: {:.1} bits ({} words from a {}-word list)",
+            entropy,
+            options.words,
+            wordlist.words().len()
+        )
+        .dimmed()
+    );
+
+    if copy {
+        copy_generated(&passphrase)?;
+    }
+
+    Ok(())
+}
+
+/// Copy a generated secret using the configured clipboard timeout
+fn copy_generated(secret: &str) -> Result<()> {
+    let timeout = Config::load().map(|c| c.clipboard_timeout).unwrap_or(45);
+    utils::copy_to_clipboard(secret, timeout)?;
+    println!(
+        "{} Copied to clipboard (clears in {} seconds)",
+        "📋".green().bold(),
+        timeout.to_string().yellow()
+    );
     Ok(())
 }
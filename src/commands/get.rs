@@ -1,12 +1,18 @@
 use anyhow::{Result, anyhow};
 
-use crate::crypto::Crypto;
+use crate::refs;
+use crate::schema;
 use crate::storage::Storage;
 use crate::types::EntryType;
 use crate::utils;
 use colored::*;
 
-pub async fn execute(key: String, quiet: bool, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    key: String,
+    field: Option<String>,
+    quiet: bool,
+    vault: Option<String>,
+) -> Result<()> {
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
@@ -14,15 +20,22 @@ pub async fn execute(key: String, quiet: bool, vault: Option<String>) -> Result<
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Load entry
     let entry = storage.load_entry(&key, &master_key)?;
 
+    // A single field is printed on its own, unmasked
+    if let Some(name) = field {
+        println!(
+            "{}",
+            refs::resolve_field(&storage, &entry, Some(&name), &master_key)?
+        );
+        return Ok(());
+    }
+
     // Decrypt the actual value
-    let decrypted = Crypto::decrypt(&entry.value, &master_key)?;
-    let value =
-        String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))?;
+    let value = refs::resolve_field(&storage, &entry, None, &master_key)?;
 
     if quiet {
         // Just print the value
@@ -35,6 +48,10 @@ pub async fn execute(key: String, quiet: bool, vault: Option<String>) -> Result<
             println!("Type: {:?}", entry.metadata.entry_type);
         }
 
+        if let Some(fields) = schema::fields(&entry.metadata.entry_type) {
+            schema::print_fields(&entry.metadata, fields);
+        }
+
         if let Some(url) = &entry.metadata.url {
             println!("URL: {}", url);
         }
@@ -47,9 +64,26 @@ pub async fn execute(key: String, quiet: bool, vault: Option<String>) -> Result<
             println!("Notes: {}", notes);
         }
 
+        let mut custom: Vec<(&String, String)> = Vec::new();
+        for name in entry.metadata.custom_fields.keys() {
+            let value = refs::resolve_field(&storage, &entry, Some(name), &master_key)?;
+            custom.push((name, value));
+        }
+        for name in entry.metadata.secret_fields.keys() {
+            custom.push((name, "********".to_string()));
+        }
+        custom.sort();
+        for (name, value) in custom {
+            println!("{}: {}", name, value);
+        }
+
         if !entry.metadata.tags.is_empty() {
             println!("Tags: {}", entry.metadata.tags.join(", "));
         }
+
+        if let Some(expires_at) = entry.metadata.expires_at {
+            println!("Expires: {}", expires_at.format("%Y-%m-%d"));
+        }
     }
 
     Ok(())
@@ -1,12 +1,21 @@
 use anyhow::{Result, anyhow};
 use colored::*;
 use regex::Regex;
+use std::collections::HashMap;
 
-use crate::crypto::Crypto;
 use crate::storage::Storage;
+use crate::tags::TagFilter;
 use crate::utils;
 
-pub async fn execute(pattern: String, case_insensitive: bool, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    pattern: String,
+    case_insensitive: bool,
+    tags: Vec<String>,
+    metadata_only: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    let filter = TagFilter::parse(&tags)?;
+
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
@@ -14,7 +23,7 @@ pub async fn execute(pattern: String, case_insensitive: bool, vault: Option<Stri
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Create regex
     let regex = if case_insensitive {
@@ -23,53 +32,50 @@ pub async fn execute(pattern: String, case_insensitive: bool, vault: Option<Stri
         Regex::new(&pattern)?
     };
 
-    // Get all entries
-    let entries = storage.list_entries()?;
-    let mut matches = Vec::new();
-
-    for entry_key in entries {
-        // Load and decrypt entry
-        if let Ok(entry) = storage.load_entry(&entry_key, &master_key) {
-            if let Ok(decrypted) = Crypto::decrypt(&entry.value, &master_key) {
-                if let Ok(value) = String::from_utf8(decrypted) {
-                    // Search in key, value, and metadata
-                    let mut match_contexts = Vec::new();
-
-                    // Check key
-                    if regex.is_match(&entry_key) {
-                        match_contexts.push(("key", entry_key.clone()));
-                    }
-
-                    // Check value (masked)
-                    if regex.is_match(&value) {
-                        match_contexts.push(("value", utils::mask_password(&value, 3)));
-                    }
-
-                    // Check metadata
-                    if let Some(username) = &entry.metadata.username {
-                        if regex.is_match(username) {
-                            match_contexts.push(("username", username.clone()));
-                        }
-                    }
+    let mut entries = storage.indexed_entries(&master_key, false)?;
+    entries.retain(|(_, _, entry)| filter.matches(&entry.tags));
+
+    // Secret values are not in the index; checking them means decrypting every entry
+    let mut loaded = HashMap::new();
+    if !metadata_only {
+        let keys: Vec<_> = entries.iter().map(|(key, _, _)| key.clone()).collect();
+        for (key, entry) in storage.load_entries(&keys, &master_key) {
+            let value = entry.and_then(|entry| {
+                utils::entry_value(&entry, &master_key)
+                    .map_err(|e| anyhow!("Cannot read entry '{}': {}", key, e))
+            });
+            match value {
+                Ok(value) => {
+                    loaded.insert(key, value);
+                }
+                Err(e) => eprintln!("{} {}", "✗".red(), e),
+            }
+        }
+    }
 
-                    if let Some(url) = &entry.metadata.url {
-                        if regex.is_match(url) {
-                            match_contexts.push(("url", url.clone()));
-                        }
-                    }
+    let mut matches = Vec::new();
+    for (entry_key, _, entry) in entries {
+        // Search in key and metadata
+        let mut match_contexts = Vec::new();
+        if regex.is_match(&entry_key) {
+            match_contexts.push(("key".to_string(), entry_key.clone()));
+        }
 
-                    if let Some(notes) = &entry.metadata.notes {
-                        if regex.is_match(notes) {
-                            match_contexts.push(("notes", notes.clone()));
-                        }
-                    }
+        if let Some(value) = loaded.get(&entry_key)
+            && regex.is_match(value)
+        {
+            match_contexts.push(("value".to_string(), utils::mask_password(value, 3)));
+        }
 
-                    if !match_contexts.is_empty() {
-                        matches.push((entry_key, match_contexts));
-                    }
-                }
+        for (field, value) in entry.fields() {
+            if regex.is_match(value) {
+                match_contexts.push((field.to_string(), value.to_string()));
             }
         }
+
+        if !match_contexts.is_empty() {
+            matches.push((entry_key, match_contexts));
+        }
     }
 
     if matches.is_empty() {
@@ -1,12 +1,15 @@
 use anyhow::{Result, anyhow};
 use colored::*;
 
+use crate::crypto::Crypto;
 use crate::git::Git;
 use crate::storage::Storage;
+use crate::utils;
 
 pub async fn execute(
     key: Option<String>,
     limit: Option<usize>,
+    values: bool,
     vault: Option<String>,
 ) -> Result<()> {
     let storage = Storage::new(vault)?;
@@ -15,6 +18,10 @@ pub async fn execute(
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
 
+    if values && let Some(entry_key) = &key {
+        return show_values(&storage, entry_key, limit);
+    }
+
     let vault_path = storage.get_vault_path();
 
     // Check if git is initialized
@@ -29,10 +36,7 @@ pub async fn execute(
 
     let history = if let Some(entry_key) = key {
         // Show history for specific entry
-        let entry_path = format!(
-            "store/{}.json",
-            entry_key.replace('/', std::path::MAIN_SEPARATOR_STR)
-        );
+        let entry_path = Storage::entry_git_path(&entry_key)?;
         Git::log_file(vault_path, &entry_path, limit)?
     } else {
         // Show general vault history
@@ -79,3 +83,38 @@ pub async fn execute(
 
     Ok(())
 }
+
+/// Show the previous values kept in an entry
+fn show_values(storage: &Storage, key: &str, limit: Option<usize>) -> Result<()> {
+    let master_key = utils::get_master_key(storage)?;
+    let entry = storage.load_entry(key, &master_key)?;
+
+    if entry.metadata.history.is_empty() {
+        println!("{}", "No previous values recorded".yellow());
+        return Ok(());
+    }
+
+    println!("{} Previous values of '{}':", "📜".green(), key.cyan());
+    let shown = limit.unwrap_or(usize::MAX);
+    for (i, previous) in entry.metadata.history.iter().take(shown).enumerate() {
+        let decrypted = Crypto::decrypt(&previous.value, &master_key)?;
+        let value =
+            String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))?;
+        let set = previous.set_at.map_or("unknown".to_string(), |set_at| {
+            set_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        });
+        let replaced = previous.changed_at.format("%Y-%m-%d %H:%M:%S");
+        println!(
+            "  {} {}  {}",
+            format!("{}.", i + 1).yellow(),
+            format!("set {}, replaced {}", set, replaced).dimmed(),
+            value
+        );
+    }
+    println!(
+        "\n{}",
+        format!("Restore one with: bunker revert {} --to <N>", key).dimmed()
+    );
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ use std::path::PathBuf;
 use uuid::Uuid;
 
 use crate::crypto::Crypto;
+use crate::key;
 use crate::storage::Storage;
 use crate::types::{Entry, EntryMetadata, EntryType, ExportEntry};
 use crate::utils;
@@ -23,7 +24,7 @@ pub async fn execute(
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Read file
     let content = fs::read_to_string(&file)?;
@@ -73,12 +74,17 @@ pub async fn execute(
         _ => return Err(anyhow!("Unsupported format: {}. Use json or csv", format)),
     };
 
+    // Keys from the file are validated up front, so a bad one imports nothing
+    let keys = key::normalize_all(import_entries.iter().map(|e| e.key.as_str()))?;
+
+    let history_limit = storage.load_config()?.history_limit;
     let mut imported = 0;
     let mut skipped = 0;
 
-    for import_entry in import_entries {
+    for (import_entry, key) in import_entries.into_iter().zip(keys) {
         // Check if entry exists
-        if storage.load_entry(&import_entry.key, &master_key).is_ok() && !overwrite {
+        let existing = storage.load_entry(key.as_str(), &master_key).ok();
+        if existing.is_some() && !overwrite {
             skipped += 1;
             continue;
         }
@@ -91,17 +97,23 @@ pub async fn execute(
             url: import_entry.url,
             username: import_entry.username,
             custom_fields: std::collections::HashMap::new(),
+            secret_fields: std::collections::HashMap::new(),
             expires_at: None,
             auto_type: None,
+            derivation: None,
+            policy: None,
+            history: Vec::new(),
+            fields: std::collections::BTreeMap::new(),
+            attachments: Vec::new(),
         };
 
         // Encrypt value
         let encrypted_value = Crypto::encrypt(import_entry.value.as_bytes(), &master_key)?;
 
         // Create entry
-        let entry = Entry {
+        let mut entry = Entry {
             id: Uuid::new_v4(),
-            key: import_entry.key,
+            key: key.into_string(),
             value: encrypted_value,
             metadata,
             created_at: import_entry.created_at,
@@ -109,6 +121,11 @@ pub async fn execute(
             accessed_at: None,
         };
 
+        // An overwritten entry keeps its history and attachments
+        if let Some(existing) = existing {
+            entry.inherit_from(existing, history_limit);
+        }
+
         // Store entry
         storage.store_entry(&entry, &master_key)?;
         imported += 1;
@@ -18,13 +18,17 @@ pub async fn execute(file: PathBuf, password: String, name: String) -> Result<()
 
     // Check if vault already exists
     let storage = Storage::new(Some(name.clone()))?;
-    if storage.vault_exists() {
+    let _lock = if storage.vault_exists() {
         if !utils::prompt_confirm(&format!("Vault '{}' already exists. Overwrite?", name))? {
             return Ok(());
         }
-        // Remove existing vault
-        fs::remove_dir_all(storage.get_vault_path())?;
-    }
+        // Keep other processes out until the imported vault is in place
+        let lock = storage.lock()?;
+        storage.destroy_vault()?;
+        Some(lock)
+    } else {
+        None
+    };
 
     println!(
         "{} Importing vault from {}...",
@@ -0,0 +1,27 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+
+use crate::cli::IndexAction;
+use crate::storage::Storage;
+use crate::utils;
+
+pub async fn execute(action: IndexAction, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    match action {
+        IndexAction::Rebuild => {
+            let master_key = utils::get_master_key(&storage)?;
+            let count = storage.rebuild_index(&master_key)?;
+            println!(
+                "{} Search index rebuilt ({} entries)",
+                "✓".green().bold(),
+                count
+            );
+            Ok(())
+        }
+    }
+}
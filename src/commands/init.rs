@@ -1,19 +1,53 @@
 use anyhow::{Result, anyhow};
 use chrono::Utc;
 use colored::*;
+use std::path::Path;
 use uuid::Uuid;
 
 use crate::cli::Cli;
 use crate::config::Config;
 use crate::crypto::Crypto;
 use crate::git::Git;
-use crate::storage::Storage;
-use crate::types::{EncryptionConfig, VaultConfig};
+use crate::home;
+use crate::storage::{self, PROJECT_DIR, Storage};
+use crate::types::{
+    DEFAULT_HISTORY_LIMIT, DEFAULT_TRASH_RETENTION_DAYS, EncryptionConfig, VaultConfig,
+};
 use crate::utils;
 
-pub async fn execute(name: String, non_interactive: bool, vault: Option<String>) -> Result<()> {
-    let vault_name = vault.unwrap_or(name.clone());
-    let storage = Storage::new(Some(vault_name.clone()))?;
+pub async fn execute(
+    name: Option<String>,
+    non_interactive: bool,
+    single_file: bool,
+    here: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    let (storage, vault_name) = if here {
+        let project = std::env::current_dir()?.join(PROJECT_DIR);
+        // In the home directory, ./.bunker is where bunker keeps everything
+        let home = home::locate()?;
+        if [&home.data, &home.config]
+            .iter()
+            .any(|dir| same_dir(dir, &project))
+        {
+            return Err(anyhow!(
+                "{} is bunker's own home directory; run 'init --here' inside a project instead",
+                project.display()
+            ));
+        }
+        let storage = Storage::project(project);
+        let vault_name = storage.get_vault_name().to_string();
+        (storage, vault_name)
+    } else {
+        let vault_name = vault
+            .or(name)
+            .ok_or_else(|| anyhow!("Give a vault name, or --here for a project vault"))?;
+        let mut storage = Storage::new(Some(vault_name.clone()))?;
+        if single_file && !storage.is_single_file() {
+            storage = Storage::single_file(Storage::vault_file(&vault_name)?);
+        }
+        (storage, vault_name)
+    };
 
     if storage.vault_exists() {
         return Err(anyhow!("Vault '{}' already exists", vault_name));
@@ -47,39 +81,66 @@ pub async fn execute(name: String, non_interactive: bool, vault: Option<String>)
         git_remote: None,
         auto_sync: true,
         auto_lock_minutes: Some(15),
+        history_limit: DEFAULT_HISTORY_LIMIT,
+        trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
     };
 
-    // Initialize vault
+    // Initialize vault; a single-file vault is encrypted from the start
+    let master_key = Crypto::derive_key(&password, config.id.as_bytes())?;
+    storage.unlock(&master_key)?;
     storage.init_vault(config.clone())?;
 
     // Set up permanent master key storage
-    let master_key = Crypto::derive_key(&password, config.id.as_bytes())?;
     storage.store_master_key_permanently(&master_key)?;
 
-    // Initialize git repository
-    if !non_interactive {
-        if utils::prompt_confirm("Initialize git repository for version control?")? {
-            Git::init(storage.get_vault_path())?;
-            Git::commit(storage.get_vault_path(), "Initial vault setup")?;
+    // The project's own repository tracks a project vault; keep the files
+    // that only matter on this machine out of it
+    if here {
+        storage::write_atomic(
+            storage.get_vault_path().join(".gitignore"),
+            ".lock\n.index\n*.tmp\n",
+        )?;
+    }
 
-            if let Some(remote) = utils::prompt_input_optional("Git remote URL (optional)")? {
-                Git::add_remote(storage.get_vault_path(), &remote)?;
+    // Initialize git repository; a single-file vault is copied around instead
+    if !non_interactive
+        && !storage.is_single_file()
+        && !here
+        && utils::prompt_confirm("Initialize git repository for version control?")?
+    {
+        Git::init(storage.get_vault_path())?;
+        Git::commit(storage.get_vault_path(), "Initial vault setup")?;
 
-                let mut updated_config = config;
-                updated_config.git_remote = Some(remote);
-                storage.save_config(&updated_config)?;
-            }
+        if let Some(remote) = utils::prompt_input_optional("Git remote URL (optional)")? {
+            Git::add_remote(storage.get_vault_path(), &remote)?;
+
+            let mut updated_config = config;
+            updated_config.git_remote = Some(remote);
+            storage.save_config(&updated_config)?;
         }
     }
 
     // Update global config
     let mut global_config = Config::load()?;
-    if global_config.default_vault == "default" && vault_name != "default" {
+    // A vault given by path is not made the default, since the path may be relative,
+    // nor is a project vault, which is found from the working directory instead
+    if global_config.default_vault == "default"
+        && vault_name != "default"
+        && storage.get_vault_name() == vault_name
+        && !here
+    {
         global_config.default_vault = vault_name.clone();
         global_config.save()?;
     }
 
-    if !non_interactive {
+    if here {
+        println!(
+            "{} Project vault '{}' created in {}; commit it with your code",
+            "✓".green().bold(),
+            vault_name.cyan(),
+            PROJECT_DIR
+        );
+    } else if !non_interactive {
         Cli::print_init_success(&vault_name);
     } else {
         println!("Vault '{}' initialized", vault_name);
@@ -87,3 +148,11 @@ pub async fn execute(name: String, non_interactive: bool, vault: Option<String>)
 
     Ok(())
 }
+
+/// Whether two paths name the same directory, following symlinks where they exist
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -1,18 +1,55 @@
 use anyhow::{Result, anyhow};
 use colored::*;
 
+use crate::select;
 use crate::storage::Storage;
+use crate::tags::TagFilter;
 use crate::utils;
 
-pub async fn execute(path: Option<String>, flat: bool, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    path: Option<String>,
+    flat: bool,
+    tags: Vec<String>,
+    include_trash: bool,
+    vault: Option<String>,
+) -> Result<()> {
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
 
-    // List all entries
-    let entries = storage.list_entries()?;
+    let filter = TagFilter::parse(&tags)?;
+
+    // List all entries, with aliases shown as "alias → target"
+    let mut entries = storage.list_entries()?;
+    for (alias, target) in storage.list_aliases()? {
+        entries.push(format!("{} → {}", alias, target));
+    }
+    entries.sort();
+
+    // Tags are plaintext metadata; an alias matches when its target does
+    if !filter.is_empty() {
+        let mut matched = Vec::new();
+        for entry in entries {
+            let key = entry.split(" → ").next().unwrap_or(&entry);
+            if let Ok(metadata) = storage.load_metadata(key)
+                && filter.matches(&metadata.tags)
+            {
+                matched.push(entry);
+            }
+        }
+        entries = matched;
+    }
+
+    if include_trash {
+        for (key, _) in storage.list_trash()? {
+            if filter.matches(&storage.load_trashed(&key)?.entry.metadata.tags) {
+                entries.push(format!("{} (trashed)", key));
+            }
+        }
+        entries.sort();
+    }
 
     if entries.is_empty() {
         println!("{}", "No passwords stored yet".yellow());
@@ -23,9 +60,22 @@ pub async fn execute(path: Option<String>, flat: bool, vault: Option<String>) ->
         return Ok(());
     }
 
-    // Filter by path if provided
+    // Filter by path or glob if provided, ignoring the alias and trash markers
     let filtered: Vec<String> = if let Some(p) = path {
-        entries.into_iter().filter(|e| e.starts_with(&p)).collect()
+        let mut filtered = Vec::new();
+        for entry in entries {
+            let key = entry.split(" → ").next().unwrap_or(&entry);
+            let key = key.trim_end_matches(" (trashed)");
+            let matched = if select::is_glob(&p) {
+                select::glob_match(&p, key)?
+            } else {
+                key.starts_with(&p)
+            };
+            if matched {
+                filtered.push(entry);
+            }
+        }
+        filtered
     } else {
         entries
     };
@@ -0,0 +1,67 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+
+use crate::git::Git;
+use crate::key::EntryKey;
+use crate::storage::Storage;
+
+pub async fn execute(
+    target: String,
+    alias: String,
+    force: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Compare against the stored form of the alias, as the chain holds normalized keys
+    let alias = EntryKey::parse(&alias)?.into_string();
+
+    if !storage.entry_exists(&target)? {
+        return Err(anyhow!("Entry '{}' not found", target));
+    }
+    if storage.resolve_chain(&target)?.contains(&alias) {
+        return Err(anyhow!(
+            "'{}' already resolves through '{}'; linking would create a loop",
+            target,
+            alias
+        ));
+    }
+
+    match storage.read_alias(&alias)? {
+        Some(existing) if !force => {
+            return Err(anyhow!(
+                "'{}' is already an alias for '{}'. Use --force to replace it",
+                alias,
+                existing
+            ));
+        }
+        Some(_) => {}
+        None if storage.entry_exists(&alias)? => {
+            return Err(anyhow!("Entry '{}' already exists", alias));
+        }
+        None => {}
+    }
+
+    storage.store_alias(&alias, &target)?;
+
+    // Commit if git enabled
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(
+            storage.get_vault_path(),
+            &format!("Link {} to {}", alias, target),
+        )?;
+
+        let config = storage.load_config()?;
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+
+    println!("{} '{}' → '{}'", "🔗".green(), alias.cyan(), target.cyan());
+
+    Ok(())
+}
@@ -0,0 +1,543 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+
+use crate::diff;
+use crate::git::Git;
+use crate::storage::Storage;
+use crate::types::{Entry, MasterKey};
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Strategy {
+    Skip,
+    Overwrite,
+    NewestWins,
+    KeepBoth,
+    Interactive,
+}
+
+impl Strategy {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "newest-wins" => Ok(Self::NewestWins),
+            "keep-both" => Ok(Self::KeepBoth),
+            "interactive" => Ok(Self::Interactive),
+            _ => Err(anyhow!(
+                "Unsupported strategy: {}. Use skip, overwrite, newest-wins, keep-both or interactive",
+                name
+            )),
+        }
+    }
+}
+
+/// What happens to a conflicting entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Resolution {
+    Skip,
+    Overwrite,
+    KeepBoth,
+}
+
+struct Conflict {
+    key: String,
+    source: Entry,
+    target: Entry,
+    changed: Vec<String>,
+}
+
+pub async fn execute(
+    source: String,
+    into: Option<String>,
+    strategy: String,
+    suffix: Option<String>,
+    dry_run: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    let strategy = Strategy::parse(&strategy)?;
+
+    let source_storage = Storage::new(Some(source.clone()))?;
+    if !source_storage.vault_exists() {
+        return Err(anyhow!("Vault '{}' does not exist", source));
+    }
+    let target_storage = Storage::new(into.or(vault))?;
+    if !target_storage.vault_exists() {
+        return Err(anyhow!(
+            "Vault '{}' does not exist",
+            target_storage.get_vault_name()
+        ));
+    }
+    let target = target_storage.get_vault_name().to_string();
+    if source == target {
+        return Err(anyhow!("Cannot merge vault '{}' into itself", source));
+    }
+    let suffix = suffix.unwrap_or_else(|| format!("-{}", source));
+    let _lock = target_storage.lock()?;
+
+    let source_key = utils::get_master_key(&source_storage)?;
+    let target_key = utils::get_master_key(&target_storage)?;
+
+    let outcome = merge(
+        &source_storage,
+        &target_storage,
+        &source_key,
+        &target_key,
+        strategy,
+        &suffix,
+        dry_run,
+    )?;
+
+    // One commit for the whole merge
+    if outcome.changes > 0 && Git::is_repo(target_storage.get_vault_path())? {
+        Git::commit(
+            target_storage.get_vault_path(),
+            &format!("Merge {} entries from vault {}", outcome.changes, source),
+        )?;
+
+        let config = target_storage.load_config()?;
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(target_storage.get_vault_path());
+        }
+    }
+
+    if outcome.unreadable > 0 {
+        return Err(anyhow!(
+            "{} entries of '{}' could not be read and were not merged",
+            outcome.unreadable,
+            source
+        ));
+    }
+
+    Ok(())
+}
+
+/// What a merge changed, and how many source entries it could not read
+struct Outcome {
+    changes: usize,
+    unreadable: usize,
+}
+
+/// Merge the entries and aliases of one open vault into another
+fn merge(
+    source_storage: &Storage,
+    target_storage: &Storage,
+    source_key: &MasterKey,
+    target_key: &MasterKey,
+    strategy: Strategy,
+    suffix: &str,
+    dry_run: bool,
+) -> Result<Outcome> {
+    let source = source_storage.get_vault_name();
+    let target = target_storage.get_vault_name();
+
+    // Sort every source entry into new, identical or conflicting; a key that is
+    // an alias in the target clashes instead, as storing there would replace it
+    let mut new = Vec::new();
+    let mut identical = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut entry_clashes = Vec::new();
+    let mut unreadable = 0;
+    let source_keys = source_storage.list_entries()?;
+    for (key, source_entry) in source_storage.load_entries(&source_keys, source_key) {
+        let source_entry = match source_entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("{} {}", "✗".red(), e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        if target_storage.read_alias(&key)?.is_some() {
+            entry_clashes.push(key);
+            continue;
+        }
+        if !target_storage.entry_stored(&key)? {
+            new.push(source_entry);
+            continue;
+        }
+        let target_entry = target_storage.load_entry(&key, target_key)?;
+        let changed = diff::changed_fields(
+            &diff::flatten(Some(source_storage), &source_entry, source_key)?,
+            &diff::flatten(Some(target_storage), &target_entry, target_key)?,
+        );
+        if changed.is_empty() {
+            identical.push(key);
+        } else {
+            conflicts.push(Conflict {
+                key,
+                source: source_entry,
+                target: target_entry,
+                changed,
+            });
+        }
+    }
+
+    // Aliases are added when the name is free; a clash is reported, never resolved
+    let mut new_aliases = Vec::new();
+    let mut alias_clashes = Vec::new();
+    for (alias, alias_target) in source_storage.list_aliases()? {
+        match target_storage.read_alias(&alias)? {
+            Some(existing) if existing == alias_target => {}
+            None if !target_storage.entry_stored(&alias)? => {
+                new_aliases.push((alias, alias_target))
+            }
+            _ => alias_clashes.push(alias),
+        }
+    }
+
+    println!(
+        "{} Merging '{}' into '{}'\n",
+        "🔀".blue(),
+        source.cyan(),
+        target.cyan()
+    );
+    print_section("New", new.iter().map(|e| e.key.clone()).collect(), |s| {
+        s.green()
+    });
+    print_section("Identical", identical.clone(), |s| s.normal());
+    println!("{} ({})", "Conflicting".yellow().bold(), conflicts.len());
+    for conflict in &conflicts {
+        let newer = if conflict.source.updated_at > conflict.target.updated_at {
+            source
+        } else {
+            target
+        };
+        println!(
+            "  {}  changed: {}  (newer in '{}')",
+            conflict.key.yellow(),
+            conflict.changed.join(", "),
+            newer
+        );
+    }
+    if !entry_clashes.is_empty() {
+        print_section(
+            &format!("Aliases in '{}', skipped", target),
+            entry_clashes.clone(),
+            |s| s.yellow(),
+        );
+    }
+    if !new_aliases.is_empty() || !alias_clashes.is_empty() {
+        println!(
+            "{} ({} new, {} clashing and skipped)",
+            "Aliases".bold(),
+            new_aliases.len(),
+            alias_clashes.len()
+        );
+        for alias in &alias_clashes {
+            println!("  {}", alias.yellow());
+        }
+    }
+    println!();
+
+    if dry_run {
+        if strategy != Strategy::Interactive {
+            let mut resolutions = Vec::new();
+            for conflict in &conflicts {
+                resolutions.push(resolve(strategy, conflict, source, target)?);
+            }
+            let count = |r: Resolution| resolutions.iter().filter(|&&x| x == r).count();
+            println!(
+                "With '{}': {} to add, {} overwritten, {} kept both, {} skipped (dry run)",
+                strategy_name(strategy),
+                new.len(),
+                count(Resolution::Overwrite),
+                count(Resolution::KeepBoth),
+                count(Resolution::Skip)
+            );
+        } else {
+            println!("{} entries to add (dry run)", new.len());
+        }
+        return Ok(Outcome {
+            changes: 0,
+            unreadable,
+        });
+    }
+
+    let limit = target_storage.load_config()?.history_limit;
+    let (mut added, mut overwritten, mut kept_both, mut skipped) = (0, 0, 0, 0);
+
+    for entry in new {
+        adopt(
+            target_storage,
+            source_storage,
+            entry,
+            source_key,
+            target_key,
+        )?;
+        added += 1;
+    }
+
+    for conflict in conflicts {
+        match resolve(strategy, &conflict, source, target)? {
+            Resolution::Skip => skipped += 1,
+            Resolution::Overwrite => {
+                // The replaced value and history stay recoverable with `bunker revert`
+                let Conflict {
+                    source: entry,
+                    target: previous,
+                    ..
+                } = conflict;
+                let mut entry =
+                    target_storage.adopt_entry(entry, source_storage, source_key, target_key)?;
+                let blobs: Vec<String> = previous
+                    .metadata
+                    .attachments
+                    .iter()
+                    .map(|a| a.blob.clone())
+                    .collect();
+                entry.inherit_from(previous, limit);
+                target_storage.store_entry(&entry, target_key)?;
+                // Attachments replaced by a source attachment of the same name
+                for blob in blobs {
+                    target_storage.delete_attachment_if_unused(&blob)?;
+                }
+                overwritten += 1;
+            }
+            Resolution::KeepBoth => {
+                let mut entry = conflict.source;
+                entry.key = free_key(target_storage, &conflict.key, suffix)?;
+                println!("  {} → {}", conflict.key.cyan(), entry.key.cyan());
+                adopt(
+                    target_storage,
+                    source_storage,
+                    entry,
+                    source_key,
+                    target_key,
+                )?;
+                kept_both += 1;
+            }
+        }
+    }
+
+    for (alias, alias_target) in &new_aliases {
+        target_storage.store_alias(alias, alias_target)?;
+    }
+
+    println!(
+        "{} Merged '{}' into '{}': {} added, {} overwritten, {} kept both, {} skipped, {} identical",
+        "✓".green().bold(),
+        source.cyan(),
+        target.cyan(),
+        added,
+        overwritten,
+        kept_both,
+        skipped + entry_clashes.len(),
+        identical.len()
+    );
+
+    Ok(Outcome {
+        changes: added + overwritten + kept_both + new_aliases.len(),
+        unreadable,
+    })
+}
+
+fn resolve(
+    strategy: Strategy,
+    conflict: &Conflict,
+    source: &str,
+    target: &str,
+) -> Result<Resolution> {
+    Ok(match strategy {
+        Strategy::Skip => Resolution::Skip,
+        Strategy::Overwrite => Resolution::Overwrite,
+        Strategy::KeepBoth => Resolution::KeepBoth,
+        Strategy::NewestWins => {
+            if conflict.source.updated_at > conflict.target.updated_at {
+                Resolution::Overwrite
+            } else {
+                Resolution::Skip
+            }
+        }
+        Strategy::Interactive => {
+            println!(
+                "\n{} differs in: {}",
+                conflict.key.yellow().bold(),
+                conflict.changed.join(", ")
+            );
+            println!(
+                "  {}: updated {}",
+                source,
+                conflict.source.updated_at.format("%Y-%m-%d %H:%M:%S")
+            );
+            println!(
+                "  {}: updated {}",
+                target,
+                conflict.target.updated_at.format("%Y-%m-%d %H:%M:%S")
+            );
+            println!("  {} Keep '{}' version (skip)", "1.".blue(), target);
+            println!("  {} Use '{}' version (overwrite)", "2.".blue(), source);
+            println!("  {} Keep both", "3.".blue());
+            loop {
+                match utils::prompt_input("Choice (1-3): ")?.trim() {
+                    "1" => break Resolution::Skip,
+                    "2" => break Resolution::Overwrite,
+                    "3" => break Resolution::KeepBoth,
+                    _ => println!("{} Invalid choice", "⚠️".yellow()),
+                }
+            }
+        }
+    })
+}
+
+fn strategy_name(strategy: Strategy) -> &'static str {
+    match strategy {
+        Strategy::Skip => "skip",
+        Strategy::Overwrite => "overwrite",
+        Strategy::NewestWins => "newest-wins",
+        Strategy::KeepBoth => "keep-both",
+        Strategy::Interactive => "interactive",
+    }
+}
+
+/// Re-encrypt a source entry for the target vault and store it
+fn adopt(
+    target: &Storage,
+    source: &Storage,
+    entry: Entry,
+    source_key: &MasterKey,
+    target_key: &MasterKey,
+) -> Result<()> {
+    let entry = target.adopt_entry(entry, source, source_key, target_key)?;
+    target.store_entry(&entry, target_key)
+}
+
+/// First free key made of `key`, the suffix and, if needed, a counter
+fn free_key(storage: &Storage, key: &str, suffix: &str) -> Result<String> {
+    let mut candidate = format!("{}{}", key, suffix);
+    let mut n = 2;
+    while storage.entry_exists(&candidate)? || storage.read_alias(&candidate)?.is_some() {
+        candidate = format!("{}{}-{}", key, suffix, n);
+        n += 1;
+    }
+    Ok(candidate)
+}
+
+fn print_section(title: &str, keys: Vec<String>, color: impl Fn(&str) -> ColoredString) {
+    println!("{} ({})", title.bold(), keys.len());
+    for key in keys {
+        println!("  {}", color(&key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Crypto;
+    use crate::storage::testing::{test_entry, test_storage};
+    use crate::types::Attachment;
+    use chrono::Utc;
+
+    fn value(storage: &Storage, key: &str, master_key: &MasterKey) -> String {
+        let entry = storage.load_entry(key, master_key).unwrap();
+        utils::entry_value(&entry, master_key).unwrap()
+    }
+
+    #[test]
+    fn test_overwrite_keeps_target_history_and_attachments() {
+        let (source, target) = (test_storage(), test_storage());
+        let (source_key, target_key) = (MasterKey::new(vec![1; 32]), MasterKey::new(vec![2; 32]));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"certificate").unwrap();
+        let (blob, size) = target.store_attachment(file.path(), &target_key).unwrap();
+        let mut existing = test_entry("db", b"current", &target_key);
+        existing.push_history(Crypto::encrypt(b"oldest", &target_key).unwrap(), 10);
+        existing.metadata.attachments.push(Attachment {
+            name: "ca.pem".to_string(),
+            blob: blob.clone(),
+            size,
+            added_at: Utc::now(),
+        });
+        target.store_entry(&existing, &target_key).unwrap();
+        source
+            .store_entry(&test_entry("db", b"incoming", &source_key), &source_key)
+            .unwrap();
+
+        let outcome = merge(
+            &source,
+            &target,
+            &source_key,
+            &target_key,
+            Strategy::Overwrite,
+            "-source",
+            false,
+        )
+        .unwrap();
+        assert_eq!(outcome.changes, 1);
+
+        let merged = target.load_entry("db", &target_key).unwrap();
+        assert_eq!(value(&target, "db", &target_key), "incoming");
+        let history: Vec<Vec<u8>> = merged
+            .metadata
+            .history
+            .iter()
+            .map(|h| Crypto::decrypt(&h.value, &target_key).unwrap())
+            .collect();
+        assert_eq!(history, vec![b"current".to_vec(), b"oldest".to_vec()]);
+        assert_eq!(merged.metadata.attachments.len(), 1);
+        let mut extracted = Vec::new();
+        target
+            .read_attachment(&blob, &mut extracted, &target_key)
+            .unwrap();
+        assert_eq!(extracted, b"certificate");
+    }
+
+    #[test]
+    fn test_alias_in_target_is_not_replaced() {
+        let (source, target) = (test_storage(), test_storage());
+        let (source_key, target_key) = (MasterKey::new(vec![1; 32]), MasterKey::new(vec![2; 32]));
+
+        target
+            .store_entry(&test_entry("db/prod", b"prod", &target_key), &target_key)
+            .unwrap();
+        target.store_alias("db", "db/prod").unwrap();
+        source
+            .store_entry(&test_entry("db", b"incoming", &source_key), &source_key)
+            .unwrap();
+
+        let outcome = merge(
+            &source,
+            &target,
+            &source_key,
+            &target_key,
+            Strategy::Overwrite,
+            "-source",
+            false,
+        )
+        .unwrap();
+        assert_eq!(outcome.changes, 0);
+        assert_eq!(value(&target, "db/prod", &target_key), "prod");
+        assert_eq!(target.read_alias("db").unwrap().as_deref(), Some("db/prod"));
+    }
+
+    #[test]
+    fn test_unreadable_source_entry_is_reported() {
+        let (source, target) = (test_storage(), test_storage());
+        let (source_key, target_key) = (MasterKey::new(vec![1; 32]), MasterKey::new(vec![2; 32]));
+
+        // Written under a key the source vault does not use
+        let other_key = MasterKey::new(vec![3; 32]);
+        source
+            .store_entry(&test_entry("broken", b"x", &other_key), &other_key)
+            .unwrap();
+        source
+            .store_entry(&test_entry("fine", b"ok", &source_key), &source_key)
+            .unwrap();
+
+        let outcome = merge(
+            &source,
+            &target,
+            &source_key,
+            &target_key,
+            Strategy::Skip,
+            "-source",
+            false,
+        )
+        .unwrap();
+        assert_eq!(outcome.unreadable, 1);
+        assert_eq!(outcome.changes, 1);
+        assert_eq!(value(&target, "fine", &target_key), "ok");
+        assert!(!target.entry_exists("broken").unwrap());
+    }
+}
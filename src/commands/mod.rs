@@ -1,6 +1,10 @@
 pub mod add;
+pub mod attach;
 pub mod backup;
 pub mod copy;
+pub mod cp;
+pub mod derive;
+pub mod diff_vault;
 pub mod edit;
 pub mod env;
 pub mod exec;
@@ -12,17 +16,25 @@ pub mod grep;
 pub mod history;
 pub mod import;
 pub mod import_vault;
+pub mod index;
 pub mod init;
 pub mod list;
+pub mod ln;
 pub mod lock;
+pub mod merge_vault;
 pub mod move_cmd;
 pub mod peek;
 pub mod pull;
+pub mod refs;
 pub mod remove;
 pub mod restore;
 pub mod restore_backup;
+pub mod revert;
+pub mod rotate;
 pub mod search;
 pub mod status;
 pub mod sync;
+pub mod tag;
+pub mod trash;
 pub mod unlock;
 pub mod vault;
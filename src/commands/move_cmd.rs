@@ -1,37 +1,208 @@
 use anyhow::{Result, anyhow};
+use chrono::Utc;
 use colored::*;
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal};
 
+use crate::commands::cp;
+use crate::git::Git;
+use crate::refs::{self, Reference};
+use crate::select;
 use crate::storage::Storage;
+use crate::types::MasterKey;
 use crate::utils;
 
-pub async fn execute(from: String, to: String, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    from: String,
+    to: String,
+    recursive: bool,
+    dry_run: bool,
+    update_refs: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    if let Some((source, target)) = cp::vault_pair(&from, &to, &vault)? {
+        return cp::transfer(source, target, &from, &to, recursive, dry_run, true);
+    }
+    let (from, to) = (select::split_vault(&from).1, select::split_vault(&to).1);
+
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
 
+    let keys = select::select_keys(&storage, from, recursive)?;
+    let moves: Vec<(String, String)> = select::relocate(&keys, from, recursive, to)
+        .into_iter()
+        .filter(|(src, dest)| src != dest)
+        .collect();
+
+    // Check every destination before touching anything
+    cp::check_destinations(&storage, &moves)?;
+
+    if moves.is_empty() {
+        println!("{}", "Nothing to move".yellow());
+        return Ok(());
+    }
+
+    if dry_run {
+        cp::print_plan(&moves, "moved");
+        return Ok(());
+    }
+
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
+
+    // Saved in one go, so a single-file vault never holds half a move
+    let relinked = storage.batch(|| {
+        let mut relinked = 0;
+        for (src, dest) in &moves {
+            // Moving an alias renames the alias, not its target
+            if let Some(target) = storage.read_alias(src)? {
+                storage.store_alias(dest, &target)?;
+                storage.delete_entry(src)?;
+            } else {
+                // Load entry
+                let mut entry = storage.load_entry(src, &master_key)?;
+
+                // Update key
+                entry.key = dest.clone();
 
-    // Load entry
-    let mut entry = storage.load_entry(&from, &master_key)?;
+                // Store with new key
+                storage.store_entry(&entry, &master_key)?;
 
-    // Update key
-    entry.key = to.clone();
+                // Delete old entry
+                storage.delete_entry(src)?;
+            }
+
+            // Aliases follow the entry to its new key
+            relinked += storage.retarget_aliases(src, dest)?;
+        }
+        Ok(relinked)
+    })?;
+
+    // References written as `{{ref:old/key}}` do not follow the entry by themselves
+    let referencing = find_references(&storage, &moves, &master_key)?;
+    let mut rewritten = 0;
+    if !referencing.is_empty() {
+        println!(
+            "{} Entries referencing the moved keys:",
+            "!".yellow().bold()
+        );
+        for (key, field, reference) in &referencing {
+            println!("  {}#{} → {}", key.cyan(), field, reference);
+        }
+        let update = update_refs
+            || (io::stdin().is_terminal() && utils::prompt_confirm("Point them at the new keys?")?);
+        if update {
+            let keys: BTreeSet<&String> = referencing.iter().map(|(key, _, _)| key).collect();
+            for key in keys {
+                let mut entry = storage.load_entry(key, &master_key)?;
+                if refs::retarget(&mut entry, &moves, &master_key)? {
+                    entry.updated_at = Utc::now();
+                    storage.store_entry(&entry, &master_key)?;
+                    rewritten += 1;
+                }
+            }
+        } else {
+            println!(
+                "  These references are broken until they are updated (mv --update-refs updates them)"
+            );
+        }
+    }
 
-    // Store with new key
-    storage.store_entry(&entry, &master_key)?;
+    // One commit for the whole batch
+    let message = match moves.as_slice() {
+        [(src, dest)] => format!("Move {} to {}", src, dest),
+        _ => format!("Move {} entries from {} to {}", moves.len(), from, to),
+    };
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(storage.get_vault_path(), &message)?;
 
-    // Delete old entry
-    storage.delete_entry(&from)?;
+        let config = storage.load_config()?;
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
 
-    println!(
-        "{} Password moved from '{}' to '{}'",
-        "✓".green().bold(),
-        from.cyan(),
-        to.cyan()
-    );
+    for (src, dest) in &moves {
+        println!(
+            "{} Password moved from '{}' to '{}'",
+            "✓".green().bold(),
+            src.cyan(),
+            dest.cyan()
+        );
+    }
+    if relinked > 0 {
+        println!("  Updated {} aliases", relinked);
+    }
+    if rewritten > 0 {
+        println!("  Updated references in {} entries", rewritten);
+    }
 
     Ok(())
 }
+
+/// Find the references to the old keys of a move, as (entry, field, reference);
+/// entries that cannot be read are reported and passed over
+fn find_references(
+    storage: &Storage,
+    moves: &[(String, String)],
+    master_key: &MasterKey,
+) -> Result<Vec<(String, String, Reference)>> {
+    let mut found = Vec::new();
+    for (key, entry) in storage.load_entries(&storage.list_entries()?, master_key) {
+        let entry_refs = entry.and_then(|entry| refs::entry_refs(&entry, master_key));
+        match entry_refs {
+            Ok(entry_refs) => found.extend(
+                entry_refs
+                    .into_iter()
+                    .filter(|(_, reference)| refs::refers_to_moved(reference, moves))
+                    .map(|(field, reference)| (key.clone(), field, reference)),
+            ),
+            Err(e) => eprintln!("{} {}", "✗".red(), e),
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{disk_vault, test_entry};
+
+    #[tokio::test]
+    async fn test_move_updates_references() {
+        let (vault, master_key) = disk_vault();
+        let storage = Storage::new(Some(vault.clone())).unwrap();
+        for (key, value) in [
+            ("db/prod", "hunter2"),
+            ("app/dsn", "postgres://app:{{ref:db/prod}}@db"),
+            ("app/other", "{{ref:db/production}}"),
+        ] {
+            storage
+                .store_entry(&test_entry(key, value.as_bytes(), &master_key), &master_key)
+                .unwrap();
+        }
+
+        execute(
+            "db/prod".to_string(),
+            "db/primary".to_string(),
+            false,
+            false,
+            true,
+            Some(vault),
+        )
+        .await
+        .unwrap();
+
+        let value = |key| {
+            let entry = storage.load_entry(key, &master_key).unwrap();
+            utils::entry_value(&entry, &master_key).unwrap()
+        };
+        assert!(!storage.entry_exists("db/prod").unwrap());
+        assert_eq!(value("db/primary"), "hunter2");
+        assert_eq!(value("app/dsn"), "postgres://app:{{ref:db/primary}}@db");
+        assert_eq!(value("app/other"), "{{ref:db/production}}");
+    }
+}
@@ -1,11 +1,11 @@
 use crate::cli::Cli;
-use crate::crypto::Crypto;
+use crate::refs;
 use crate::storage::Storage;
 use crate::utils;
 use anyhow::{Result, anyhow};
 use colored::*;
 
-pub async fn execute(key: String, vault: Option<String>) -> Result<()> {
+pub async fn execute(key: String, field: Option<String>, vault: Option<String>) -> Result<()> {
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
@@ -13,15 +13,13 @@ pub async fn execute(key: String, vault: Option<String>) -> Result<()> {
     }
 
     // Get master key
-    let master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let master_key = utils::get_master_key(&storage)?;
 
     // Load entry
     let entry = storage.load_entry(&key, &master_key)?;
 
-    // Decrypt the value
-    let decrypted = Crypto::decrypt(&entry.value, &master_key)?;
-    let password =
-        String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))?;
+    // Decrypt the value (or the requested field)
+    let password = refs::resolve_field(&storage, &entry, field.as_deref(), &master_key)?;
 
     // Mask the password
     let masked = utils::mask_password(&password, 2);
@@ -28,6 +28,9 @@ pub async fn execute(vault: Option<String>) -> Result<()> {
 
     // Pull changes
     let result = Git::pull(vault_path)?;
+    if !result.is_empty() {
+        storage.invalidate_index()?;
+    }
 
     if result.is_empty() {
         println!("{} Already up to date", "✓".green().bold());
@@ -0,0 +1,134 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+
+use crate::refs::{self, Reference};
+use crate::storage::Storage;
+use crate::types::MasterKey;
+use crate::utils;
+
+pub async fn execute(key: String, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
+
+    let entry = storage.load_entry(&key, &master_key)?;
+    println!("{} References of '{}':", "🔗".green(), key.cyan().bold());
+
+    let outgoing = refs::entry_refs(&entry, &master_key)?;
+    println!("\n{}", "Uses:".bold());
+    if outgoing.is_empty() {
+        println!("  {}", "nothing".dimmed());
+    }
+    for (field, reference) in &outgoing {
+        let marker = if storage.entry_exists(&reference.key).unwrap_or(false) {
+            String::new()
+        } else {
+            format!("  {}", "(missing)".red())
+        };
+        println!("  {} → {}{}", field, reference.to_string().cyan(), marker);
+    }
+
+    let (incoming, unreadable) = incoming_refs(&storage, &entry.key, &master_key)?;
+
+    println!("\n{}", "Used by:".bold());
+    if incoming.is_empty() {
+        println!("  {}", "nothing".dimmed());
+    }
+    for (other_key, field, reference) in &incoming {
+        println!(
+            "  {}#{} → {}",
+            other_key.cyan(),
+            field,
+            reference.field.as_deref().unwrap_or("value")
+        );
+    }
+
+    if unreadable > 0 {
+        return Err(anyhow!(
+            "{} entries could not be read; they may use '{}' too",
+            unreadable,
+            key
+        ));
+    }
+
+    Ok(())
+}
+
+/// A reference made by another entry: (entry, field, reference)
+type Use = (String, String, Reference);
+
+/// Scan the vault for entries pointing at `key`, through an alias or a
+/// differently written key as well. Also returns the number of entries that
+/// could not be read.
+fn incoming_refs(
+    storage: &Storage,
+    key: &str,
+    master_key: &MasterKey,
+) -> Result<(Vec<Use>, usize)> {
+    let mut incoming = Vec::new();
+    let mut unreadable = 0;
+    let mut other_keys = storage.list_entries()?;
+    other_keys.retain(|other_key| other_key != key);
+    for (other_key, other) in storage.load_entries(&other_keys, master_key) {
+        // One unreadable entry should not hide the references of the others
+        match other.and_then(|other| refs::entry_refs(&other, master_key)) {
+            Ok(other_refs) => incoming.extend(
+                other_refs
+                    .into_iter()
+                    .filter(|(_, reference)| {
+                        storage
+                            .resolve_key(&reference.key)
+                            .is_ok_and(|target| target == key)
+                    })
+                    .map(|(field, reference)| (other_key.clone(), field, reference)),
+            ),
+            Err(e) => {
+                eprintln!("{} {}", "✗".red(), e);
+                unreadable += 1;
+            }
+        }
+    }
+    Ok((incoming, unreadable))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{test_entry, test_storage};
+
+    #[test]
+    fn test_incoming_refs_skip_unreadable_entries() {
+        let storage = test_storage();
+        let master_key = MasterKey::new(vec![1; 32]);
+        let other_key = MasterKey::new(vec![2; 32]);
+
+        storage
+            .store_entry(&test_entry("db/prod", b"secret", &master_key), &master_key)
+            .unwrap();
+        storage.store_alias("db/current", "db/prod").unwrap();
+        // Sorts before the readable entries, so it is loaded first
+        storage
+            .store_entry(
+                &test_entry("app/a", b"{{ref:db/prod}}", &other_key),
+                &other_key,
+            )
+            .unwrap();
+        storage
+            .store_entry(
+                &test_entry("app/b", b"{{ref:db/current#username}}", &master_key),
+                &master_key,
+            )
+            .unwrap();
+
+        let (incoming, unreadable) = incoming_refs(&storage, "db/prod", &master_key).unwrap();
+        assert_eq!(unreadable, 1);
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].0, "app/b");
+        assert_eq!(incoming[0].2.key, "db/current");
+    }
+}
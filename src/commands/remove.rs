@@ -1,39 +1,116 @@
 use anyhow::{Result, anyhow};
+use colored::*;
 
 use crate::cli::Cli;
 use crate::git::Git;
+use crate::select;
 use crate::storage::Storage;
 use crate::utils;
 
-pub async fn execute(key: String, force: bool, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    key: String,
+    force: bool,
+    permanent: bool,
+    recursive: bool,
+    dry_run: bool,
+    vault: Option<String>,
+) -> Result<()> {
     let storage = Storage::new(vault)?;
 
     if !storage.vault_exists() {
         return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
     }
 
+    let keys = select::select_keys(&storage, &key, recursive)?;
+
+    // Aliases are just removed; entries go to the trash unless asked otherwise
+    let mut targets = Vec::new();
+    for key in keys {
+        let trashed = !permanent && storage.read_alias(&key)?.is_none();
+        let replaces_trashed = trashed && storage.load_trashed(&key).is_ok();
+        targets.push((key, trashed, replaces_trashed));
+    }
+
+    if dry_run {
+        for (key, trashed, _) in &targets {
+            let action = if *trashed { "trash" } else { "delete" };
+            println!("  {} ({})", key.cyan(), action);
+        }
+        println!("{} entries would be removed (dry run)", targets.len());
+        return Ok(());
+    }
+
     // Confirm deletion
     if !force {
-        if !utils::prompt_confirm(&format!("Remove password '{}'?", key))? {
+        let prompt = match targets.as_slice() {
+            [(key, _, _)] if permanent => format!("Permanently delete password '{}'?", key),
+            [(key, _, true)] => format!(
+                "Remove password '{}'? This replaces the copy already in the trash",
+                key
+            ),
+            [(key, _, _)] => format!("Remove password '{}'?", key),
+            _ => {
+                for (key, _, _) in &targets {
+                    println!("  {}", key.cyan());
+                }
+                let replacing = targets.iter().filter(|t| t.2).count();
+                if permanent {
+                    format!("Permanently delete these {} passwords?", targets.len())
+                } else if replacing > 0 {
+                    format!(
+                        "Remove these {} passwords? This replaces {} copies already in the trash",
+                        targets.len(),
+                        replacing
+                    )
+                } else {
+                    format!("Remove these {} passwords?", targets.len())
+                }
+            }
+        };
+        if !utils::prompt_confirm(&prompt)? {
             println!("Cancelled");
             return Ok(());
         }
     }
 
-    // Delete entry
-    storage.delete_entry(&key)?;
+    for (key, trashed, _) in &targets {
+        if *trashed {
+            storage.trash_entry(key)?;
+        } else {
+            storage.delete_entry(key)?;
+        }
+    }
+
+    let config = storage.load_config()?;
+    storage.purge_expired_trash(config.trash_retention_days)?;
 
-    // Commit if git enabled
+    // One commit for the whole batch
+    let message = match targets.as_slice() {
+        [(key, _, _)] => format!("Remove {}", key),
+        _ => format!("Remove {} entries matching {}", targets.len(), key),
+    };
     if Git::is_repo(storage.get_vault_path())? {
-        Git::commit(storage.get_vault_path(), &format!("Remove {}", key))?;
+        Git::commit(storage.get_vault_path(), &message)?;
 
-        let config = storage.load_config()?;
         if config.auto_sync && config.git_remote.is_some() {
             let _ = Git::push(storage.get_vault_path());
         }
     }
 
-    Cli::print_entry_removed(&key);
+    for (key, _, _) in &targets {
+        Cli::print_entry_removed(key);
+    }
+    match targets.as_slice() {
+        [(key, true, _)] => println!(
+            "  Moved to trash. Restore with: {}",
+            format!("bunker trash restore {}", key).white().bold()
+        ),
+        _ if targets.iter().any(|t| t.1) => println!(
+            "  Moved to trash. See {}",
+            "bunker trash list".white().bold()
+        ),
+        _ => {}
+    }
 
     Ok(())
 }
@@ -24,11 +24,9 @@ pub async fn execute(
 
     if let Some(entry_key) = key {
         // Restore specific entry
-        let entry_path = format!(
-            "store/{}.json",
-            entry_key.replace('/', std::path::MAIN_SEPARATOR_STR)
-        );
+        let entry_path = Storage::entry_git_path(&entry_key)?;
         Git::restore_file(vault_path, &commit_hash, &entry_path)?;
+        storage.invalidate_index()?;
         println!(
             "{} Restored '{}' from commit {}",
             "✓".green().bold(),
@@ -45,6 +43,7 @@ pub async fn execute(
         }
 
         Git::restore_commit(vault_path, &commit_hash)?;
+        storage.invalidate_index()?;
         println!(
             "{} Restored vault to commit {}",
             "✓".green().bold(),
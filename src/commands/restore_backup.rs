@@ -14,25 +14,30 @@ pub async fn execute(backup_path: PathBuf, vault_name: Option<String>) -> Result
     let vault_name = vault_name.unwrap_or_else(|| "restored".to_string());
     let storage = Storage::new(Some(vault_name.clone()))?;
 
-    if storage.vault_exists() {
+    let _lock = if storage.vault_exists() {
         if !utils::prompt_confirm(&format!(
             "Vault '{}' already exists. Overwrite?",
             vault_name
         ))? {
             return Ok(());
         }
-        // Remove existing vault
-        fs::remove_dir_all(storage.get_vault_path())?;
-    }
-
-    // Create vault directory
-    fs::create_dir_all(storage.get_vault_path())?;
+        // Keep other processes out until the backup is unpacked
+        let lock = storage.lock()?;
+        storage.destroy_vault()?;
+        Some(lock)
+    } else {
+        None
+    };
+
+    // Create vault directory; backups are always of directory vaults
+    let vault_path = Storage::vault_path(&vault_name)?;
+    fs::create_dir_all(&vault_path)?;
 
     // Extract backup
     let tar_gz = fs::File::open(&backup_path)?;
     let tar = flate2::read::GzDecoder::new(tar_gz);
     let mut archive = tar::Archive::new(tar);
-    archive.unpack(storage.get_vault_path())?;
+    archive.unpack(&vault_path)?;
 
     println!(
         "{} Backup restored to vault '{}'",
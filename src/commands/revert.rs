@@ -0,0 +1,140 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+
+use crate::git::Git;
+use crate::storage::Storage;
+use crate::utils;
+
+pub async fn execute(key: String, to: usize, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
+
+    let mut entry = storage.load_entry(&key, &master_key)?;
+    let count = entry.metadata.history.len();
+    if to == 0 || to > count {
+        return Err(anyhow!(
+            "Entry '{}' has {} previous values; pick --to between 1 and {}",
+            key,
+            count,
+            count
+        ));
+    }
+
+    // The current value goes into history too, so a revert can be undone
+    let set_at = entry.value_set_at();
+    let restored = entry.metadata.history.remove(to - 1);
+    let previous = std::mem::replace(&mut entry.value, restored.value.clone());
+    let config = storage.load_config()?;
+    entry.record_history(previous, set_at, config.history_limit);
+    entry.updated_at = Utc::now();
+
+    storage.store_entry(&entry, &master_key)?;
+
+    // Commit if git enabled
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(
+            storage.get_vault_path(),
+            &format!("Revert {} to version {}", key, to),
+        )?;
+
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+
+    // Values recorded by older versions only know when they were replaced
+    let when = match restored.set_at {
+        Some(set_at) => format!("set on {}", set_at.format("%Y-%m-%d %H:%M:%S")),
+        None => format!(
+            "replaced on {}",
+            restored.changed_at.format("%Y-%m-%d %H:%M:%S")
+        ),
+    };
+    println!(
+        "{} Restored '{}' to the value {}",
+        "✓".green().bold(),
+        key.cyan(),
+        when.yellow()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Crypto;
+    use crate::storage::testing::{disk_vault, test_entry};
+    use crate::types::{Entry, MasterKey};
+
+    fn set_value(entry: &mut Entry, value: &str, limit: usize, master_key: &MasterKey) {
+        let encrypted = Crypto::encrypt(value.as_bytes(), master_key).unwrap();
+        let previous = std::mem::replace(&mut entry.value, encrypted);
+        entry.push_history(previous, limit);
+    }
+
+    fn history(entry: &Entry, master_key: &MasterKey) -> Vec<String> {
+        entry
+            .metadata
+            .history
+            .iter()
+            .map(|p| String::from_utf8(Crypto::decrypt(&p.value, master_key).unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let master_key = MasterKey::new(vec![7; 32]);
+        let mut entry = test_entry("db", b"v1", &master_key);
+        for value in ["v2", "v3", "v4", "v5"] {
+            set_value(&mut entry, value, 3, &master_key);
+        }
+        assert_eq!(history(&entry, &master_key), vec!["v4", "v3", "v2"]);
+
+        // Each value knows when it was set: when the one before it was replaced
+        let recorded = &entry.metadata.history;
+        assert_eq!(recorded[0].set_at, Some(recorded[1].changed_at));
+        assert_eq!(recorded[1].set_at, Some(recorded[2].changed_at));
+
+        // A lower limit drops the oldest values on the next change
+        set_value(&mut entry, "v6", 1, &master_key);
+        assert_eq!(history(&entry, &master_key), vec!["v5"]);
+
+        set_value(&mut entry, "v7", 0, &master_key);
+        assert!(entry.metadata.history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_revert() {
+        let (vault, master_key) = disk_vault();
+        let storage = Storage::new(Some(vault.clone())).unwrap();
+        let mut entry = test_entry("db", b"v1", &master_key);
+        let created_at = entry.created_at;
+        for value in ["v2", "v3"] {
+            set_value(&mut entry, value, 10, &master_key);
+        }
+        storage.store_entry(&entry, &master_key).unwrap();
+
+        execute("db".to_string(), 2, Some(vault.clone()))
+            .await
+            .unwrap();
+        let reverted = storage.load_entry("db", &master_key).unwrap();
+        assert_eq!(utils::entry_value(&reverted, &master_key).unwrap(), "v1");
+        // The replaced value can be restored in turn; the restored one left history
+        assert_eq!(history(&reverted, &master_key), vec!["v3", "v2"]);
+        assert_eq!(
+            reverted.metadata.history[0].set_at,
+            Some(entry.metadata.history[0].changed_at)
+        );
+        assert_eq!(entry.metadata.history[1].set_at, Some(created_at));
+
+        assert!(execute("db".to_string(), 3, Some(vault)).await.is_err());
+    }
+}
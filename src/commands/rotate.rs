@@ -0,0 +1,408 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::config::Config;
+use crate::crypto::Crypto;
+use crate::git::Git;
+use crate::select;
+use crate::storage::Storage;
+use crate::tags::TagFilter;
+use crate::types::{Entry, EntryType, GenerateOptions, MasterKey};
+use crate::utils;
+
+/// How the rotation hook receives the old and new secrets
+pub struct RotationHook {
+    pub command: String,
+    /// Pass the secrets on stdin (old, then new, one per line) instead of env vars
+    pub stdin: bool,
+}
+
+pub async fn execute(
+    key: Option<String>,
+    tags: Vec<String>,
+    path: Option<String>,
+    profile: Option<String>,
+    hook: Option<RotationHook>,
+    dry_run: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    if key.is_some() && (!tags.is_empty() || path.is_some()) {
+        return Err(anyhow!(
+            "Specify either a key or --tag/--path filters, not both"
+        ));
+    }
+    if key.is_none() && tags.is_empty() && path.is_none() {
+        return Err(anyhow!("Specify a key or at least one --tag/--path filter"));
+    }
+
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Get master key
+    let master_key = utils::get_master_key(&storage)?;
+
+    let profile = match profile {
+        Some(name) => Some(Config::load()?.profile(&name)?),
+        None => None,
+    };
+
+    let filter = TagFilter::parse(&tags)?;
+
+    // Collect the entries to rotate, setting aside those whose value is not a
+    // generated secret (a card number, a private key, a file...)
+    let mut skipped = Vec::new();
+    let mut unreadable = 0;
+    let entries = match &key {
+        Some(k) => {
+            let entry = storage.load_entry(k, &master_key)?;
+            if !rotatable(&entry) {
+                return Err(anyhow!(
+                    "Cannot rotate '{}': it is a {} entry; only passwords and database passwords are rotated",
+                    entry.key,
+                    entry.metadata.entry_type
+                ));
+            }
+            vec![entry]
+        }
+        None => {
+            // Paths select like mv and cp; an alias stands for the entry it points at
+            let mut keys = match &path {
+                Some(p) => select::select_keys(&storage, p, true)?
+                    .iter()
+                    .map(|k| storage.resolve_key(k))
+                    .collect::<Result<Vec<_>>>()?,
+                None => storage.list_entries()?,
+            };
+            keys.sort();
+            keys.dedup();
+            let mut matched = Vec::new();
+            for (_, entry) in storage.load_entries(&keys, &master_key) {
+                // One unreadable entry should not stop the others from rotating
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        eprintln!("{} {}", "✗".red(), e);
+                        unreadable += 1;
+                        continue;
+                    }
+                };
+                if !filter.matches(&entry.metadata.tags) {
+                    continue;
+                }
+                if rotatable(&entry) {
+                    matched.push(entry);
+                } else {
+                    skipped.push(entry);
+                }
+            }
+            matched
+        }
+    };
+
+    for entry in &skipped {
+        println!(
+            "{} Skipping '{}': {} entries are not rotated",
+            "-".dimmed(),
+            entry.key.cyan(),
+            entry.metadata.entry_type
+        );
+    }
+
+    if entries.is_empty() {
+        if unreadable > 0 {
+            return Err(anyhow!(
+                "No entries rotated; {} entries could not be read",
+                unreadable
+            ));
+        }
+        println!("{}", "No entries match the given filters".yellow());
+        return Ok(());
+    }
+
+    if dry_run {
+        println!(
+            "{} Would rotate {} entries:",
+            "🔄".cyan(),
+            entries.len().to_string().bold()
+        );
+        for entry in &entries {
+            println!("  {}", entry.key.cyan());
+        }
+        return Ok(());
+    }
+
+    let history_limit = storage.load_config()?.history_limit;
+    let mut rotated = Vec::new();
+    let mut failed = 0;
+
+    for entry in entries {
+        let entry_key = entry.key.clone();
+        match rotate_entry(
+            &storage,
+            entry,
+            profile.as_ref(),
+            hook.as_ref(),
+            history_limit,
+            &master_key,
+        ) {
+            Ok(()) => {
+                println!("{} Rotated '{}'", "✓".green().bold(), entry_key.cyan());
+                rotated.push(entry_key);
+            }
+            Err(e) => {
+                println!(
+                    "{} Failed to rotate '{}': {}",
+                    "✗".red().bold(),
+                    entry_key.cyan(),
+                    e
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    // Commit if git enabled
+    if !rotated.is_empty() {
+        let config = storage.load_config()?;
+        if Git::is_repo(storage.get_vault_path())? {
+            let message = if rotated.len() == 1 {
+                format!("Rotate {}", rotated[0])
+            } else {
+                format!("Rotate {} entries", rotated.len())
+            };
+            Git::commit(storage.get_vault_path(), &message)?;
+
+            if config.auto_sync && config.git_remote.is_some() {
+                let _ = Git::push(storage.get_vault_path());
+            }
+        }
+    }
+
+    if failed + unreadable > 0 {
+        return Err(anyhow!(
+            "{} of {} entries failed to rotate",
+            failed + unreadable,
+            failed + unreadable + rotated.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether an entry's value is a secret bunker can replace with a generated one:
+/// a password, or the password of a database entry
+fn rotatable(entry: &Entry) -> bool {
+    matches!(
+        entry.metadata.entry_type,
+        EntryType::Password | EntryType::Database
+    )
+}
+
+/// Generate a new value for an entry, run the hook and save it.
+///
+/// The new value is only saved once the hook has accepted it. Should saving
+/// fail after that, the value is printed so it is not lost.
+fn rotate_entry(
+    storage: &Storage,
+    entry: Entry,
+    profile: Option<&GenerateOptions>,
+    hook: Option<&RotationHook>,
+    history_limit: usize,
+    master_key: &MasterKey,
+) -> Result<()> {
+    let old_value = utils::entry_value(&entry, master_key)?;
+    let mut updated = entry.clone();
+
+    let new_value = if let Some(mut params) = entry.metadata.derivation.clone() {
+        // Derived entries rotate by bumping their counter
+        params.counter += 1;
+        if let Some(policy) = profile {
+            params.policy = policy.clone();
+        }
+        let new_value = Crypto::derive_site_password(master_key, &params)?;
+        updated.metadata.derivation = Some(params);
+        new_value
+    } else {
+        if let Some(policy) = profile {
+            updated.metadata.policy = Some(policy.clone());
+        }
+        let policy = updated.metadata.policy.clone().unwrap_or_default();
+        let new_value = Crypto::generate_password(&policy)?;
+        let previous = std::mem::replace(
+            &mut updated.value,
+            Crypto::encrypt(new_value.as_bytes(), master_key)?,
+        );
+        updated.push_history(previous, history_limit);
+        new_value
+    };
+    updated.updated_at = Utc::now();
+
+    run_hook(hook, &entry, &old_value, &new_value)?;
+
+    if let Err(e) = storage.store_entry(&updated, master_key) {
+        // The secret has already changed elsewhere, so this value is the only copy
+        eprintln!(
+            "{} The rotation hook accepted a new value for '{}' but saving it failed.\n\
+             {} Keep this value, it is not in the vault: {}",
+            "ERROR:".red().bold(),
+            entry.key,
+            "ERROR:".red().bold(),
+            new_value
+        );
+        return Err(anyhow!("new value could not be saved: {}", e));
+    }
+
+    Ok(())
+}
+
+/// Run the user-defined rotation command, failing if it exits unsuccessfully
+fn run_hook(
+    hook: Option<&RotationHook>,
+    entry: &Entry,
+    old_value: &str,
+    new_value: &str,
+) -> Result<()> {
+    let Some(hook) = hook else {
+        return Ok(());
+    };
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(&hook.command);
+    cmd.env("BUNKER_KEY", &entry.key);
+    if let Some(username) = &entry.metadata.username {
+        cmd.env("BUNKER_USERNAME", username);
+    }
+    if let Some(url) = &entry.metadata.url {
+        cmd.env("BUNKER_URL", url);
+    }
+
+    let status = if hook.stdin {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to run rotation hook: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{}\n{}", old_value, new_value)?;
+        }
+        child.wait()?
+    } else {
+        cmd.env("BUNKER_OLD_SECRET", old_value)
+            .env("BUNKER_NEW_SECRET", new_value)
+            .status()
+            .map_err(|e| anyhow!("Failed to run rotation hook: {}", e))?
+    };
+
+    if !status.success() {
+        return Err(anyhow!(
+            "rotation hook exited with status {}",
+            status.code().unwrap_or(-1)
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{disk_vault, test_entry};
+
+    fn value(storage: &Storage, key: &str, master_key: &MasterKey) -> String {
+        let entry = storage.load_entry(key, master_key).unwrap();
+        utils::entry_value(&entry, master_key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rotate_path() {
+        let (vault, master_key) = disk_vault();
+        let storage = Storage::new(Some(vault.clone())).unwrap();
+        for key in ["aws/iam", "aws/s3/backup", "awsome", "github"] {
+            storage
+                .store_entry(&test_entry(key, b"old", &master_key), &master_key)
+                .unwrap();
+        }
+        storage.store_alias("aws/current", "aws/iam").unwrap();
+
+        execute(
+            None,
+            vec![],
+            Some("aws".to_string()),
+            None,
+            None,
+            false,
+            Some(vault),
+        )
+        .await
+        .unwrap();
+
+        assert_ne!(value(&storage, "aws/iam", &master_key), "old");
+        assert_ne!(value(&storage, "aws/s3/backup", &master_key), "old");
+        assert_eq!(value(&storage, "awsome", &master_key), "old");
+        assert_eq!(value(&storage, "github", &master_key), "old");
+
+        // Reached through its alias too, but rotated only once
+        let entry = storage.load_entry("aws/iam", &master_key).unwrap();
+        assert_eq!(entry.metadata.history.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_saves_only_what_the_hook_accepted() {
+        let (vault, master_key) = disk_vault();
+        let storage = Storage::new(Some(vault.clone())).unwrap();
+        storage
+            .store_entry(&test_entry("db", b"old", &master_key), &master_key)
+            .unwrap();
+
+        // A failing hook leaves the old value in place
+        let hook = RotationHook {
+            command: "exit 1".to_string(),
+            stdin: false,
+        };
+        let result = execute(
+            Some("db".to_string()),
+            vec![],
+            None,
+            None,
+            Some(hook),
+            false,
+            Some(vault.clone()),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(value(&storage, "db", &master_key), "old");
+
+        // The hook gets the old and the new value, and the new one is saved
+        let dir = tempfile::tempdir().unwrap();
+        let seen = dir.path().join("seen");
+        let hook = RotationHook {
+            command: format!(
+                "read old; read new; printf '%s %s' \"$old\" \"$new\" > '{}'",
+                seen.display()
+            ),
+            stdin: true,
+        };
+        execute(
+            Some("db".to_string()),
+            vec![],
+            None,
+            None,
+            Some(hook),
+            false,
+            Some(vault),
+        )
+        .await
+        .unwrap();
+
+        let new_value = value(&storage, "db", &master_key);
+        assert_eq!(
+            std::fs::read_to_string(&seen).unwrap(),
+            format!("old {}", new_value)
+        );
+    }
+}
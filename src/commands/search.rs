@@ -3,11 +3,19 @@ use colored::*;
 use skim::prelude::*;
 use std::io::Cursor;
 
-use crate::crypto::Crypto;
-use crate::storage::Storage;
+use crate::cli::EntryFlags;
+use crate::storage::{self, Storage};
+use crate::tags::TagFilter;
 use crate::utils;
 
-pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()> {
+pub async fn execute(
+    query: Option<String>,
+    tags: Vec<String>,
+    include_trash: bool,
+    vault: Option<String>,
+) -> Result<()> {
+    let filter = TagFilter::parse(&tags)?;
+
     let storage = Storage::new(vault.clone())?;
 
     if !storage.vault_exists() {
@@ -15,11 +23,12 @@ pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()>
     }
 
     // Get master key for decryption
-    let master_key = utils::get_master_key(vault.clone())?;
+    let master_key = utils::get_master_key(&storage)?;
 
     if let Some(q) = query {
-        // Search with provided query through decrypted content
-        let results = storage.search_entries(&q, &master_key)?;
+        // Search the vault's search index
+        let mut results = storage.search_entries(&q, &master_key, include_trash)?;
+        results.retain(|(_, entry)| filter.matches(&entry.tags));
 
         if results.is_empty() {
             println!("{}", "No matches found".yellow());
@@ -33,21 +42,21 @@ pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()>
                 println!(
                     "  {} ({})",
                     entry_key.cyan(),
-                    entry.metadata.entry_type.to_string().yellow()
+                    entry.entry_type.to_string().yellow()
                 );
 
                 // Show matching fields
                 let mut matches = Vec::new();
 
                 // Check username
-                if let Some(username) = &entry.metadata.username {
+                if let Some(username) = &entry.username {
                     if username.to_lowercase().contains(&q.to_lowercase()) {
                         matches.push(format!("username: {}", username));
                     }
                 }
 
                 // Check notes
-                if let Some(notes) = &entry.metadata.notes {
+                if let Some(notes) = &entry.notes {
                     if notes.to_lowercase().contains(&q.to_lowercase()) {
                         matches.push(format!(
                             "notes: {}",
@@ -57,21 +66,21 @@ pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()>
                 }
 
                 // Check custom fields
-                for (field_name, field_value) in &entry.metadata.custom_fields {
+                for (field_name, field_value) in &entry.custom_fields {
                     if field_value.to_lowercase().contains(&q.to_lowercase()) {
                         matches.push(format!("{}: {}", field_name, field_value));
                     }
                 }
 
                 // Check URL
-                if let Some(url) = &entry.metadata.url {
+                if let Some(url) = &entry.url {
                     if url.to_lowercase().contains(&q.to_lowercase()) {
                         matches.push(format!("url: {}", url));
                     }
                 }
 
                 // Check tags
-                for tag in &entry.metadata.tags {
+                for tag in &entry.tags {
                     if tag.to_lowercase().contains(&q.to_lowercase()) {
                         matches.push(format!("tag: {}", tag));
                     }
@@ -87,65 +96,40 @@ pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()>
             }
         }
     } else {
-        // Interactive fuzzy search with skim - searches through decrypted content but shows clean interface
-        let entries = storage.list_entries()?;
+        // Interactive fuzzy search with skim over the indexed metadata
+        let mut indexed = Vec::new();
+        for (entry_key, trashed, entry) in storage.indexed_entries(&master_key, include_trash)? {
+            if filter.matches(&entry.tags) {
+                indexed.push((storage::trash_label(entry_key, trashed), entry));
+            }
+        }
 
-        if entries.is_empty() {
+        if indexed.is_empty() {
             println!("{}", "No passwords stored yet".yellow());
             return Ok(());
         }
 
-        // Create searchable items with decrypted content for searching but clean display
+        // Searchable lines never contain secret values
         let mut search_items = Vec::new();
 
-        for entry_key in &entries {
-            if let Ok(entry) = storage.load_entry(entry_key, &master_key) {
-                // Decrypt the password/value for searching (but don't show it)
-                let decrypted_value = match Crypto::decrypt(&entry.value, &master_key) {
-                    Ok(value) => String::from_utf8(value).unwrap_or_default(),
-                    Err(_) => String::new(),
-                };
-
-                // Build clean display text (no passwords exposed)
-                let mut display_text = format!("{} ({})", entry_key, entry.metadata.entry_type);
-
-                if let Some(username) = &entry.metadata.username {
-                    display_text.push_str(&format!(" | username: {}", username));
-                }
-
-                if let Some(notes) = &entry.metadata.notes {
-                    display_text.push_str(&format!(" | notes: {}", notes));
-                }
-
-                if let Some(url) = &entry.metadata.url {
-                    display_text.push_str(&format!(" | url: {}", url));
-                }
-
-                for tag in &entry.metadata.tags {
-                    display_text.push_str(&format!(" | tag: {}", tag));
-                }
-
-                for (field_name, field_value) in &entry.metadata.custom_fields {
-                    display_text.push_str(&format!(" | {}: {}", field_name, field_value));
-                }
-
-                // Store both clean display and searchable content (with decrypted password)
-                let searchable_content = format!("{} | {}", display_text, decrypted_value);
-                search_items.push((entry_key.clone(), display_text, searchable_content));
+        for (entry_key, entry) in &indexed {
+            let mut display_text = format!("{} ({})", entry_key, entry.entry_type);
+            for (name, value) in entry.fields() {
+                display_text.push_str(&format!(" | {}: {}", name, value));
             }
+            search_items.push((entry_key.clone(), display_text));
         }
 
-        // Use skim fuzzy finder with the searchable content (includes decrypted passwords for searching)
+        // Use skim fuzzy finder over the searchable lines
         let options = SkimOptionsBuilder::default()
-            .prompt(Some("Search (searches through all content): "))
+            .prompt(Some("Search: "))
             .preview(Some(""))
             .build()
             .unwrap();
 
-        // Use the searchable content (includes decrypted passwords) for the fuzzy finder
         let input = search_items
             .iter()
-            .map(|(_, _, searchable_content)| searchable_content.as_str())
+            .map(|(_, searchable_content)| searchable_content.as_str())
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -162,10 +146,20 @@ pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()>
             // Find the corresponding entry key by matching the searchable content
             let entry_key = search_items
                 .iter()
-                .find(|(_, _, searchable_content)| searchable_content == &selected_text)
-                .map(|(key, _, _)| key.clone())
+                .find(|(_, searchable_content)| searchable_content == &selected_text)
+                .map(|(key, _)| key.clone())
                 .ok_or_else(|| anyhow!("Failed to find selected entry"))?;
 
+            // Trashed entries have to be restored before they can be used
+            if let Some(key) = entry_key.strip_suffix(" (trashed)") {
+                println!(
+                    "'{}' is in the trash. Restore it with: {}",
+                    key.cyan(),
+                    format!("bunker trash restore {}", key).white().bold()
+                );
+                return Ok(());
+            }
+
             // Ask what to do with the selected entry
             println!("\nSelected: {}", entry_key.cyan().bold());
             println!("\nWhat would you like to do?");
@@ -177,9 +171,15 @@ pub async fn execute(query: Option<String>, vault: Option<String>) -> Result<()>
             let action = utils::prompt_input("Choice (1-4)")?;
 
             match action.as_str() {
-                "1" => super::copy::execute(entry_key.clone(), false, 45, vault.clone()).await?,
-                "2" => super::get::execute(entry_key.clone(), false, vault.clone()).await?,
-                "3" => super::edit::execute(entry_key, None, vault).await?,
+                "1" => {
+                    super::copy::execute(entry_key.clone(), None, false, 45, vault.clone()).await?
+                }
+                "2" => super::get::execute(entry_key.clone(), None, false, vault.clone()).await?,
+                "3" => {
+                    let _lock = storage.lock()?;
+                    super::edit::execute(entry_key, None, false, EntryFlags::default(), vault)
+                        .await?
+                }
                 _ => println!("Cancelled"),
             }
         }
@@ -0,0 +1,108 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+use std::collections::BTreeMap;
+
+use crate::cli::TagAction;
+use crate::git::Git;
+use crate::storage::Storage;
+use crate::tags;
+use crate::utils;
+
+pub async fn execute(action: TagAction, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    match action {
+        TagAction::Add { key, tags } => update(&storage, key, tags, true),
+        TagAction::Remove { key, tags } => update(&storage, key, tags, false),
+        TagAction::List => list(&storage),
+    }
+}
+
+fn update(storage: &Storage, key: String, tags: Vec<String>, add: bool) -> Result<()> {
+    let tags = tags
+        .iter()
+        .map(|t| tags::validate(t))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Get master key
+    let master_key = utils::get_master_key(storage)?;
+    let mut entry = storage.load_entry(&key, &master_key)?;
+
+    let before = entry.metadata.tags.clone();
+    for tag in &tags {
+        if add {
+            if !entry.metadata.tags.contains(tag) {
+                entry.metadata.tags.push(tag.clone());
+            }
+        } else {
+            entry.metadata.tags.retain(|t| t != tag);
+        }
+    }
+
+    if entry.metadata.tags == before {
+        println!("{}", "Tags unchanged".yellow());
+        return Ok(());
+    }
+
+    entry.updated_at = Utc::now();
+    storage.store_entry(&entry, &master_key)?;
+
+    // Commit if git enabled
+    if Git::is_repo(storage.get_vault_path())? {
+        let action = if add { "Tag" } else { "Untag" };
+        Git::commit(
+            storage.get_vault_path(),
+            &format!("{} {}: {}", action, key, tags.join(", ")),
+        )?;
+
+        let config = storage.load_config()?;
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+
+    let current = if entry.metadata.tags.is_empty() {
+        "none".to_string()
+    } else {
+        entry.metadata.tags.join(", ")
+    };
+    println!(
+        "{} Tags of '{}': {}",
+        "✓".green().bold(),
+        key.cyan(),
+        current
+    );
+
+    Ok(())
+}
+
+fn list(storage: &Storage) -> Result<()> {
+    // Tags live in the plaintext metadata, so no master key is needed
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for key in storage.list_entries()? {
+        for tag in storage.load_metadata(&key)?.tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    if counts.is_empty() {
+        println!("{}", "No tags in use".yellow());
+        return Ok(());
+    }
+
+    println!(
+        "{} {} tags:\n",
+        "🏷️".green(),
+        counts.len().to_string().bold()
+    );
+    for (tag, count) in counts {
+        println!("  {}  {}", tag.cyan(), count.to_string().dimmed());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use colored::*;
+
+use crate::cli::TrashAction;
+use crate::git::Git;
+use crate::storage::Storage;
+use crate::utils;
+
+pub async fn execute(action: TrashAction, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    // Drop anything past the retention period first
+    let retention = storage.load_config()?.trash_retention_days;
+    let expired = storage.purge_expired_trash(retention)?;
+    if !expired.is_empty() {
+        commit(
+            &storage,
+            &format!("Purge {} expired entries", expired.len()),
+        )?;
+    }
+
+    match action {
+        TrashAction::List => list(&storage, retention),
+        TrashAction::Restore { key } => {
+            storage.restore_from_trash(&key)?;
+            commit(&storage, &format!("Restore {} from trash", key))?;
+            println!("{} Restored '{}'", "✓".green().bold(), key.cyan());
+            Ok(())
+        }
+        TrashAction::Purge {
+            key: Some(key),
+            force,
+        } => {
+            storage.load_trashed(&key)?;
+            if !force
+                && !utils::prompt_confirm(&format!(
+                    "Permanently delete '{}'? This cannot be undone",
+                    key
+                ))?
+            {
+                println!("Cancelled");
+                return Ok(());
+            }
+            storage.purge_trashed(&key)?;
+            commit(&storage, &format!("Purge {} from trash", key))?;
+            println!("{} Purged '{}'", "✓".green().bold(), key.cyan());
+            Ok(())
+        }
+        TrashAction::Purge { key: None, force } => {
+            let trashed = storage.list_trash()?;
+            if trashed.is_empty() {
+                println!("{}", "Trash is empty".yellow());
+                return Ok(());
+            }
+            if !force
+                && !utils::prompt_confirm(&format!(
+                    "Permanently delete all {} trashed entries? This cannot be undone",
+                    trashed.len()
+                ))?
+            {
+                println!("Cancelled");
+                return Ok(());
+            }
+            for (key, _) in &trashed {
+                storage.purge_trashed(key)?;
+            }
+            commit(&storage, "Empty trash")?;
+            println!(
+                "{} Purged {} entries",
+                "✓".green().bold(),
+                trashed.len().to_string().bold()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn list(storage: &Storage, retention: u32) -> Result<()> {
+    let trashed = storage.list_trash()?;
+    if trashed.is_empty() {
+        println!("{}", "Trash is empty".yellow());
+        return Ok(());
+    }
+
+    println!(
+        "{} {} trashed entries:\n",
+        "🗑️".green(),
+        trashed.len().to_string().bold()
+    );
+    for (key, deleted_at) in &trashed {
+        let expiry = if retention == 0 {
+            String::new()
+        } else {
+            let left = retention as i64 - (Utc::now() - *deleted_at).num_days();
+            format!("  (purged in {} days)", left.max(0))
+        };
+        println!(
+            "  {}  {}{}",
+            key.cyan(),
+            deleted_at.format("%Y-%m-%d %H:%M").to_string().dimmed(),
+            expiry.dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Commit if git enabled
+fn commit(storage: &Storage, message: &str) -> Result<()> {
+    let config = storage.load_config()?;
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(storage.get_vault_path(), message)?;
+
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{disk_vault, test_entry};
+    use crate::types::Attachment;
+
+    #[tokio::test]
+    async fn test_restore_and_purge() {
+        let (vault, master_key) = disk_vault();
+        let storage = Storage::new(Some(vault.clone())).unwrap();
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"certificate").unwrap();
+        let (blob, size) = storage.store_attachment(file.path(), &master_key).unwrap();
+        let mut entry = test_entry("db/prod", b"hunter2", &master_key);
+        entry.metadata.attachments.push(Attachment {
+            name: "ca.pem".to_string(),
+            blob: blob.clone(),
+            size,
+            added_at: Utc::now(),
+        });
+        storage.store_entry(&entry, &master_key).unwrap();
+        storage
+            .store_entry(&test_entry("db/dev", b"dev", &master_key), &master_key)
+            .unwrap();
+
+        // Restored with its attachment
+        storage.trash_entry("db/prod").unwrap();
+        assert!(!storage.entry_exists("db/prod").unwrap());
+        let restore = TrashAction::Restore {
+            key: "db/prod".to_string(),
+        };
+        execute(restore, Some(vault.clone())).await.unwrap();
+        let restored = storage.load_entry("db/prod", &master_key).unwrap();
+        assert_eq!(restored.metadata.attachments.len(), 1);
+        assert!(storage.list_trash().unwrap().is_empty());
+
+        // A key taken meanwhile is not overwritten
+        storage.trash_entry("db/dev").unwrap();
+        storage
+            .store_entry(&test_entry("db/dev", b"newer", &master_key), &master_key)
+            .unwrap();
+        let restore = TrashAction::Restore {
+            key: "db/dev".to_string(),
+        };
+        assert!(execute(restore, Some(vault.clone())).await.is_err());
+        assert_eq!(storage.list_trash().unwrap().len(), 1);
+
+        // Purging one entry drops the attachments only it used
+        storage.trash_entry("db/prod").unwrap();
+        let purge = TrashAction::Purge {
+            key: Some("db/prod".to_string()),
+            force: true,
+        };
+        execute(purge, Some(vault.clone())).await.unwrap();
+        assert!(storage.load_trashed("db/prod").is_err());
+        let mut data = Vec::new();
+        assert!(
+            storage
+                .read_attachment(&blob, &mut data, &master_key)
+                .is_err()
+        );
+
+        // Emptying the trash
+        let purge = TrashAction::Purge {
+            key: None,
+            force: true,
+        };
+        execute(purge, Some(vault)).await.unwrap();
+        assert!(storage.list_trash().unwrap().is_empty());
+        assert!(storage.entry_exists("db/dev").unwrap());
+    }
+}
@@ -12,7 +12,7 @@ pub async fn execute(vault: Option<String>, duration: Option<u64>) -> Result<()>
     }
 
     // Get master key (this will create a session if needed)
-    let _master_key = utils::get_master_key(Some(storage.get_vault_name().to_string()))?;
+    let _master_key = utils::get_master_key(&storage)?;
 
     let duration_hours = duration.unwrap_or(24);
 
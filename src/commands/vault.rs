@@ -3,12 +3,13 @@ use colored::*;
 
 use crate::cli::VaultAction;
 use crate::config::Config;
+use crate::git::Git;
 use crate::storage::Storage;
 use crate::utils;
 
 pub async fn execute(action: VaultAction) -> Result<()> {
     match action {
-        VaultAction::Create { name } => create_vault(name).await,
+        VaultAction::Create { name, single_file } => create_vault(name, single_file).await,
         VaultAction::Use { name } => use_vault(name).await,
         VaultAction::List => list_vaults().await,
         VaultAction::Delete { name, force } => delete_vault(name, force).await,
@@ -20,10 +21,28 @@ pub async fn execute(action: VaultAction) -> Result<()> {
             password,
             name,
         } => crate::commands::import_vault::execute(file, password, name).await,
+        VaultAction::Merge {
+            source,
+            into,
+            strategy,
+            suffix,
+            dry_run,
+        } => {
+            crate::commands::merge_vault::execute(source, into, strategy, suffix, dry_run, None)
+                .await
+        }
+        VaultAction::Diff {
+            a,
+            b,
+            export,
+            password,
+        } => crate::commands::diff_vault::execute(a, b, export, password).await,
+        VaultAction::TrashRetention { days } => trash_retention(days, None).await,
+        VaultAction::HistoryLimit { limit } => history_limit(limit, None).await,
     }
 }
 
-async fn create_vault(name: String) -> Result<()> {
+async fn create_vault(name: String, single_file: bool) -> Result<()> {
     // Check if vault exists
     let storage = Storage::new(Some(name.clone()))?;
     if storage.vault_exists() {
@@ -31,7 +50,14 @@ async fn create_vault(name: String) -> Result<()> {
     }
 
     // Create the vault
-    super::init::execute(name.clone(), false, Some(name.clone())).await?;
+    super::init::execute(
+        Some(name.clone()),
+        false,
+        single_file,
+        false,
+        Some(name.clone()),
+    )
+    .await?;
 
     Ok(())
 }
@@ -115,10 +141,140 @@ async fn delete_vault(name: String, force: bool) -> Result<()> {
         }
     }
 
-    // Delete vault directory
-    std::fs::remove_dir_all(storage.get_vault_path())?;
+    // Delete vault directory or file, once no other process is using it
+    let _lock = storage.lock()?;
+    storage.destroy_vault()?;
 
     println!("{} Vault '{}' deleted", "✓".green().bold(), name.cyan());
 
     Ok(())
 }
+
+/// Show or change the per-entry history retention of a vault
+pub async fn history_limit(limit: Option<usize>, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    let mut config = storage.load_config()?;
+    let Some(limit) = limit else {
+        println!(
+            "Vault '{}' keeps {} previous values per entry",
+            storage.get_vault_name().cyan(),
+            config.history_limit.to_string().bold()
+        );
+        return Ok(());
+    };
+
+    let lowered = limit < config.history_limit;
+    config.history_limit = limit;
+    storage.save_config(&config)?;
+
+    // Drop values that are now past the limit; an entry that cannot be read
+    // keeps its history, and the new limit still applies to it from now on
+    let mut trimmed = 0;
+    let mut failed = 0;
+    if lowered {
+        let master_key = utils::get_master_key(&storage)?;
+        for (key, entry) in storage.load_entries(&storage.list_entries()?, &master_key) {
+            let result = entry.and_then(|mut entry| {
+                if entry.metadata.history.len() <= limit {
+                    return Ok(false);
+                }
+                entry.metadata.history.truncate(limit);
+                storage.store_entry(&entry, &master_key)?;
+                Ok(true)
+            });
+            match result {
+                Ok(true) => trimmed += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("{} Could not trim history of '{}': {}", "✗".red(), key, e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(
+            storage.get_vault_path(),
+            &format!("Set history limit to {}", limit),
+        )?;
+
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+
+    println!(
+        "{} History limit set to {}",
+        "✓".green().bold(),
+        limit.to_string().bold()
+    );
+    if trimmed > 0 {
+        println!("  Trimmed history of {} entries", trimmed);
+    }
+    if failed > 0 {
+        println!(
+            "  {} entries could not be trimmed and keep their older values",
+            failed.to_string().yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Show or change how long removed entries stay in the trash
+pub async fn trash_retention(days: Option<u32>, vault: Option<String>) -> Result<()> {
+    let storage = Storage::new(vault)?;
+
+    if !storage.vault_exists() {
+        return Err(anyhow!("Vault not initialized. Run 'bunker init' first"));
+    }
+
+    let mut config = storage.load_config()?;
+    let Some(days) = days else {
+        if config.trash_retention_days == 0 {
+            println!(
+                "Vault '{}' keeps trashed entries until they are purged",
+                storage.get_vault_name().cyan()
+            );
+        } else {
+            println!(
+                "Vault '{}' keeps trashed entries for {} days",
+                storage.get_vault_name().cyan(),
+                config.trash_retention_days.to_string().bold()
+            );
+        }
+        return Ok(());
+    };
+
+    config.trash_retention_days = days;
+    storage.save_config(&config)?;
+    let purged = storage.purge_expired_trash(days)?;
+
+    if Git::is_repo(storage.get_vault_path())? {
+        Git::commit(
+            storage.get_vault_path(),
+            &format!("Set trash retention to {} days", days),
+        )?;
+
+        if config.auto_sync && config.git_remote.is_some() {
+            let _ = Git::push(storage.get_vault_path());
+        }
+    }
+
+    println!(
+        "{} Trash retention set to {} days",
+        "✓".green().bold(),
+        days.to_string().bold()
+    );
+    if !purged.is_empty() {
+        println!("  Purged {} expired entries", purged.len());
+    }
+
+    Ok(())
+}
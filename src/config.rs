@@ -1,9 +1,12 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::home;
 use crate::storage::Storage;
+use crate::types::GenerateOptions;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -13,6 +16,9 @@ pub struct Config {
     pub auto_lock_minutes: Option<u64>,
     pub clipboard_timeout: u64,
     pub theme: Theme,
+    /// Named password generation profiles, e.g. `[profiles.aws-iam]`
+    #[serde(default)]
+    pub profiles: HashMap<String, GenerateOptions>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +39,7 @@ impl Default for Config {
                 use_colors: true,
                 use_icons: true,
             },
+            profiles: HashMap::new(),
         }
     }
 }
@@ -57,16 +64,27 @@ impl Config {
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
         let config_toml = toml::to_string_pretty(self)?;
-        fs::write(config_path, config_toml)?;
+        if let Some(config_dir) = config_path.parent() {
+            fs::create_dir_all(config_dir)?;
+        }
+        crate::storage::write_atomic(config_path, config_toml)?;
         Ok(())
     }
 
     /// Get configuration file path
     fn config_path() -> Result<PathBuf> {
-        let config_dir = Storage::base_dir()?;
+        let config_dir = home::locate()?.config;
         Ok(config_dir.join("config.toml"))
     }
 
+    /// Get a named password generation profile
+    pub fn profile(&self, name: &str) -> Result<GenerateOptions> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Generation profile '{}' not found in config.toml", name))
+    }
+
     /// Get editor command
     pub fn editor(&self) -> String {
         self.editor
@@ -86,9 +104,54 @@ impl Config {
 /// Ensure configuration directory exists
 pub fn ensure_config_dir() -> Result<()> {
     let base_dir = Storage::base_dir()?;
+    fs::create_dir_all(home::locate()?.config)?;
     fs::create_dir_all(&base_dir)?;
     fs::create_dir_all(base_dir.join("vaults"))?;
     fs::create_dir_all(base_dir.join("sessions"))?;
     fs::create_dir_all(base_dir.join("backups"))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CharClass;
+
+    #[test]
+    fn test_profile_round_trip() {
+        let config: Config = toml::from_str(
+            r#"
+            default_vault = "default"
+            auto_sync = true
+            clipboard_timeout = 45
+
+            [theme]
+            use_colors = true
+            use_icons = true
+
+            [profiles.short]
+            first_char = "lower"
+
+            [profiles.long]
+            first_char = "uppercase"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.profile("short").unwrap().first_char,
+            Some(CharClass::Lowercase)
+        );
+        assert_eq!(
+            config.profile("long").unwrap().first_char,
+            Some(CharClass::Uppercase)
+        );
+
+        let saved: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(
+            saved.profile("short").unwrap().first_char,
+            Some(CharClass::Lowercase)
+        );
+        assert!(toml::from_str::<GenerateOptions>("first_char = \"upper\"").is_ok());
+        assert!(toml::from_str::<GenerateOptions>("first_char = \"vowel\"").is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::{Result, anyhow};
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
@@ -10,14 +10,45 @@ use chacha20poly1305::{
 };
 use rand::{Rng, distributions::Alphanumeric};
 use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
 use zeroize::Zeroize;
 
-use crate::types::{EncryptedValue, GenerateOptions, MasterKey};
+use crate::types::{
+    CharClass, DerivationParams, EncryptedValue, GenerateOptions, MasterKey, PassphraseOptions,
+};
+use crate::wordlist::Wordlist;
 
 const KEY_SIZE: usize = 32;
 const NONCE_SIZE: usize = 12;
 const SALT_SIZE: usize = 32;
 
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+const AMBIGUOUS: &[char] = &['0', 'O', 'o', 'l', '1', 'I'];
+const MAX_GENERATE_ATTEMPTS: usize = 1000;
+
+const DERIVE_KDF_MEMORY: u32 = 19456; // 19 MB
+const DERIVE_KDF_ITERATIONS: u32 = 2;
+const DERIVE_KDF_PARALLELISM: u32 = 1;
+
+// Character tables of derived passwords, frozen separately from the ones above
+const DERIVE_V1_LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const DERIVE_V1_UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DERIVE_V1_DIGITS: &str = "0123456789";
+const DERIVE_V1_SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+const DERIVE_V1_AMBIGUOUS: &[char] = &['0', 'O', 'o', 'l', '1', 'I'];
+const DERIVE_V1_ATTEMPTS: usize = 1000;
+
+// Streaming encryption (attachments): a STREAM construction over ChaCha20-Poly1305.
+// Each chunk's nonce is a random 7-byte prefix, a 32-bit big-endian chunk counter
+// and a final-chunk flag, so chunks cannot be reordered, dropped or truncated.
+const STREAM_MAGIC: &[u8; 4] = b"BKS1";
+const STREAM_NONCE_PREFIX_SIZE: usize = 7;
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+const STREAM_TAG_SIZE: usize = 16;
+
 pub struct Crypto;
 
 impl Crypto {
@@ -92,47 +123,630 @@ impl Crypto {
             .is_ok())
     }
 
-    /// Generate secure password
-    pub fn generate_password(options: &GenerateOptions) -> String {
-        let mut charset = String::new();
+    /// Generate a secure password satisfying the given policy
+    pub fn generate_password(options: &GenerateOptions) -> Result<String> {
+        let mut rng = rand::thread_rng();
+        Self::generate_with(options, &mut |n| rng.gen_range(0..n))
+    }
+
+    /// Derive a site password from the master key (stateless, LessPass-style).
+    ///
+    /// The same master key, site, login, counter and policy always produce the
+    /// same password, so it never has to be stored.
+    pub fn derive_site_password(
+        master_key: &MasterKey,
+        params: &DerivationParams,
+    ) -> Result<String> {
+        let mut salt = Sha256::default();
+        salt.update(b"bunker-derive-v1");
+        for part in [params.site.as_bytes(), params.login.as_bytes()] {
+            salt.update((part.len() as u64).to_be_bytes());
+            salt.update(part);
+        }
+        salt.update(params.counter.to_be_bytes());
+        let salt = salt.finalize();
+
+        // Parameters are pinned so derived passwords never change between releases
+        let argon2 = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(
+                DERIVE_KDF_MEMORY,
+                DERIVE_KDF_ITERATIONS,
+                DERIVE_KDF_PARALLELISM,
+                Some(KEY_SIZE),
+            )
+            .map_err(|e| anyhow!("Invalid derivation parameters: {}", e))?,
+        );
+        let mut seed = vec![0u8; KEY_SIZE];
+        argon2
+            .hash_password_into(&master_key.key, &salt, &mut seed)
+            .map_err(|e| anyhow!("Password derivation failed: {}", e))?;
+
+        let mut stream = DerivedStream::new(seed);
+        Self::derive_v1(&params.policy, &mut stream)
+    }
+
+    /// Policy of derived passwords when none is given. Spelled out rather than
+    /// taken from `GenerateOptions::default()` so the defaults can change
+    /// without changing the passwords `bunker derive` gives out
+    pub fn derivation_policy() -> GenerateOptions {
+        GenerateOptions {
+            length: 20,
+            use_uppercase: true,
+            use_lowercase: true,
+            use_numbers: true,
+            use_symbols: true,
+            exclude_ambiguous: true,
+            custom_charset: None,
+            min_uppercase: 0,
+            min_lowercase: 0,
+            min_numbers: 0,
+            min_symbols: 0,
+            max_repeat: None,
+            allowed_symbols: None,
+            forbidden_chars: None,
+            prefix: None,
+            first_char: None,
+        }
+    }
+
+    /// Turn the derivation stream into a password satisfying the policy.
+    ///
+    /// This is a frozen copy of the generation algorithm with its own character
+    /// tables: any change to the order of draws or to the pools changes every
+    /// derived password, so it must never be edited. New policy options must
+    /// be ignored here (or go into a new version) when left at their default.
+    fn derive_v1(policy: &GenerateOptions, stream: &mut DerivedStream) -> Result<String> {
+        if policy.length == 0 {
+            return Err(anyhow!("Password length must be greater than zero"));
+        }
+        if policy.max_repeat == Some(0) {
+            return Err(anyhow!("Maximum repeat must be at least 1"));
+        }
+
+        let prefix = policy.prefix.clone().unwrap_or_default();
+        let prefix_len = prefix.chars().count();
+        if prefix_len > policy.length {
+            return Err(anyhow!(
+                "Prefix '{}' is longer than the password length {}",
+                prefix,
+                policy.length
+            ));
+        }
+        if let (Some(class), Some(first)) = (policy.first_char, prefix.chars().next())
+            && !class.matches(first)
+        {
+            return Err(anyhow!(
+                "Prefix '{}' does not start with a {}",
+                prefix,
+                class
+            ));
+        }
 
-        if let Some(custom) = &options.custom_charset {
-            charset = custom.clone();
+        let forbidden: Vec<char> = policy
+            .forbidden_chars
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .collect();
+        if prefix.chars().any(|c| forbidden.contains(&c)) {
+            return Err(anyhow!("Prefix '{}' contains forbidden characters", prefix));
+        }
+
+        let mut classes: Vec<(Vec<char>, usize)> = Vec::new();
+        let pool: Vec<char> = if let Some(custom) = &policy.custom_charset {
+            custom.chars().filter(|c| !forbidden.contains(c)).collect()
         } else {
-            if options.use_lowercase {
-                charset.push_str("abcdefghijklmnopqrstuvwxyz");
+            let symbols = policy
+                .allowed_symbols
+                .as_deref()
+                .unwrap_or(DERIVE_V1_SYMBOLS);
+            for (enabled, class, set, min) in [
+                (
+                    policy.use_lowercase,
+                    CharClass::Lowercase,
+                    DERIVE_V1_LOWERCASE,
+                    policy.min_lowercase,
+                ),
+                (
+                    policy.use_uppercase,
+                    CharClass::Uppercase,
+                    DERIVE_V1_UPPERCASE,
+                    policy.min_uppercase,
+                ),
+                (
+                    policy.use_numbers,
+                    CharClass::Digit,
+                    DERIVE_V1_DIGITS,
+                    policy.min_numbers,
+                ),
+                (
+                    policy.use_symbols,
+                    CharClass::Symbol,
+                    symbols,
+                    policy.min_symbols,
+                ),
+            ] {
+                if !enabled {
+                    if min > 0 {
+                        return Err(anyhow!(
+                            "Policy requires {} {} characters but that class is disabled",
+                            min,
+                            class
+                        ));
+                    }
+                    continue;
+                }
+
+                let class_pool: Vec<char> = set
+                    .chars()
+                    .filter(|c| class.matches(*c))
+                    .filter(|c| !forbidden.contains(c))
+                    .filter(|c| !(policy.exclude_ambiguous && DERIVE_V1_AMBIGUOUS.contains(c)))
+                    .collect();
+                if class_pool.is_empty() {
+                    return Err(anyhow!("No {} characters left after exclusions", class));
+                }
+
+                let in_prefix = prefix.chars().filter(|c| class.matches(*c)).count();
+                let required = min.max(1).saturating_sub(in_prefix);
+                classes.push((class_pool, required));
             }
-            if options.use_uppercase {
-                charset.push_str("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+            classes
+                .iter()
+                .flat_map(|(class_pool, _)| class_pool.iter().copied())
+                .collect()
+        };
+
+        if pool.is_empty() {
+            return Err(anyhow!("No characters available to generate a password"));
+        }
+        if let Some(class) = policy.first_char
+            && prefix.is_empty()
+            && !pool.iter().any(|c| class.matches(*c))
+        {
+            return Err(anyhow!(
+                "Policy requires a leading {} but none are allowed",
+                class
+            ));
+        }
+
+        let body_len = policy.length - prefix_len;
+        let required: usize = classes.iter().map(|(_, required)| required).sum();
+        if required > body_len {
+            return Err(anyhow!(
+                "Policy requires {} characters but only {} are available",
+                required,
+                body_len
+            ));
+        }
+
+        for _ in 0..DERIVE_V1_ATTEMPTS {
+            let mut body: Vec<char> = Vec::with_capacity(body_len);
+            for (class_pool, required) in &classes {
+                for _ in 0..*required {
+                    body.push(class_pool[stream.next_below(class_pool.len())]);
+                }
             }
-            if options.use_numbers {
-                charset.push_str("0123456789");
+            while body.len() < body_len {
+                body.push(pool[stream.next_below(pool.len())]);
             }
-            if options.use_symbols {
-                charset.push_str("!@#$%^&*()_+-=[]{}|;:,.<>?");
+
+            for i in (1..body.len()).rev() {
+                body.swap(i, stream.next_below(i + 1));
             }
 
-            // Remove ambiguous characters if requested
-            if options.exclude_ambiguous {
-                charset = charset.replace(&['0', 'O', 'o', 'l', '1', 'I'][..], "");
+            if let (Some(class), true) = (policy.first_char, prefix.is_empty()) {
+                match body.iter().position(|c| class.matches(*c)) {
+                    Some(index) => body.swap(0, index),
+                    None => continue,
+                }
+            }
+
+            // Class minimums hold by construction; only runs can break the policy
+            let password: Vec<char> = prefix.chars().chain(body).collect();
+            let longest_run = password
+                .chunk_by(|a, b| a == b)
+                .map(|run| run.len())
+                .max()
+                .unwrap_or(0);
+            if policy
+                .max_repeat
+                .is_none_or(|max_repeat| longest_run <= max_repeat)
+            {
+                return Ok(password.into_iter().collect());
             }
         }
 
-        if charset.is_empty() {
-            // Fallback to alphanumeric
-            return rand::thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(options.length)
-                .map(char::from)
-                .collect();
+        Err(anyhow!(
+            "Could not derive a password satisfying the policy. Try relaxing it"
+        ))
+    }
+
+    /// Generate a password satisfying the policy, drawing indexes from `pick`
+    fn generate_with(
+        options: &GenerateOptions,
+        pick: &mut dyn FnMut(usize) -> usize,
+    ) -> Result<String> {
+        if options.length == 0 {
+            return Err(anyhow!("Password length must be greater than zero"));
+        }
+        if options.max_repeat == Some(0) {
+            return Err(anyhow!("Maximum repeat must be at least 1"));
         }
 
-        let charset_chars: Vec<char> = charset.chars().collect();
+        let prefix = options.prefix.clone().unwrap_or_default();
+        let prefix_len = prefix.chars().count();
+        if prefix_len > options.length {
+            return Err(anyhow!(
+                "Prefix '{}' is longer than the password length {}",
+                prefix,
+                options.length
+            ));
+        }
+        if let (Some(class), Some(first)) = (options.first_char, prefix.chars().next())
+            && !class.matches(first)
+        {
+            return Err(anyhow!(
+                "Prefix '{}' does not start with a {}",
+                prefix,
+                class
+            ));
+        }
+
+        let forbidden: Vec<char> = options
+            .forbidden_chars
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .collect();
+
+        // Character pools and the number of characters required from each
+        let mut classes: Vec<(Vec<char>, usize)> = Vec::new();
+        let pool: Vec<char> = if let Some(custom) = &options.custom_charset {
+            custom.chars().filter(|c| !forbidden.contains(c)).collect()
+        } else {
+            let symbols = options.allowed_symbols.as_deref().unwrap_or(SYMBOLS);
+            for (enabled, class, set, min) in [
+                (
+                    options.use_lowercase,
+                    CharClass::Lowercase,
+                    LOWERCASE,
+                    options.min_lowercase,
+                ),
+                (
+                    options.use_uppercase,
+                    CharClass::Uppercase,
+                    UPPERCASE,
+                    options.min_uppercase,
+                ),
+                (
+                    options.use_numbers,
+                    CharClass::Digit,
+                    DIGITS,
+                    options.min_numbers,
+                ),
+                (
+                    options.use_symbols,
+                    CharClass::Symbol,
+                    symbols,
+                    options.min_symbols,
+                ),
+            ] {
+                if !enabled {
+                    if min > 0 {
+                        return Err(anyhow!(
+                            "Policy requires {} {} characters but that class is disabled",
+                            min,
+                            class
+                        ));
+                    }
+                    continue;
+                }
+
+                let class_pool: Vec<char> = set
+                    .chars()
+                    .filter(|c| class.matches(*c))
+                    .filter(|c| !forbidden.contains(c))
+                    .filter(|c| !(options.exclude_ambiguous && AMBIGUOUS.contains(c)))
+                    .collect();
+                if class_pool.is_empty() {
+                    return Err(anyhow!("No {} characters left after exclusions", class));
+                }
+
+                // Characters in the prefix count towards the minimum
+                let in_prefix = prefix.chars().filter(|c| class.matches(*c)).count();
+                let required = min.max(1).saturating_sub(in_prefix);
+                classes.push((class_pool, required));
+            }
+            classes
+                .iter()
+                .flat_map(|(class_pool, _)| class_pool.iter().copied())
+                .collect()
+        };
+
+        if pool.is_empty() {
+            return Err(anyhow!("No characters available to generate a password"));
+        }
+        if let Some(class) = options.first_char
+            && prefix.is_empty()
+            && !pool.iter().any(|c| class.matches(*c))
+        {
+            return Err(anyhow!(
+                "Policy requires a leading {} but none are allowed",
+                class
+            ));
+        }
+
+        let body_len = options.length - prefix_len;
+        let required: usize = classes.iter().map(|(_, required)| required).sum();
+        if required > body_len {
+            return Err(anyhow!(
+                "Policy requires {} characters but only {} are available",
+                required,
+                body_len
+            ));
+        }
+
+        for _ in 0..MAX_GENERATE_ATTEMPTS {
+            let mut body: Vec<char> = Vec::with_capacity(body_len);
+            for (class_pool, required) in &classes {
+                for _ in 0..*required {
+                    body.push(class_pool[pick(class_pool.len())]);
+                }
+            }
+            while body.len() < body_len {
+                body.push(pool[pick(pool.len())]);
+            }
+
+            // Fisher-Yates shuffle
+            for i in (1..body.len()).rev() {
+                body.swap(i, pick(i + 1));
+            }
+
+            if let (Some(class), true) = (options.first_char, prefix.is_empty()) {
+                match body.iter().position(|c| class.matches(*c)) {
+                    Some(index) => body.swap(0, index),
+                    None => continue,
+                }
+            }
+
+            let password: String = prefix.chars().chain(body).collect();
+            if Self::policy_violations(&password, options).is_empty() {
+                return Ok(password);
+            }
+        }
+
+        Err(anyhow!(
+            "Could not generate a password satisfying the policy. Try relaxing it"
+        ))
+    }
+
+    /// List the ways a password breaks the given policy
+    fn policy_violations(password: &str, options: &GenerateOptions) -> Vec<String> {
+        let mut violations = Vec::new();
+        let chars: Vec<char> = password.chars().collect();
+
+        if options.custom_charset.is_none() {
+            for (enabled, class, min) in [
+                (
+                    options.use_lowercase,
+                    CharClass::Lowercase,
+                    options.min_lowercase,
+                ),
+                (
+                    options.use_uppercase,
+                    CharClass::Uppercase,
+                    options.min_uppercase,
+                ),
+                (options.use_numbers, CharClass::Digit, options.min_numbers),
+                (options.use_symbols, CharClass::Symbol, options.min_symbols),
+            ] {
+                let count = chars.iter().filter(|c| class.matches(**c)).count();
+                if enabled && count < min.max(1) {
+                    violations.push(format!(
+                        "needs at least {} {} characters",
+                        min.max(1),
+                        class
+                    ));
+                }
+            }
+        }
+
+        if let Some(forbidden) = &options.forbidden_chars
+            && chars.iter().any(|c| forbidden.contains(*c))
+        {
+            violations.push("contains forbidden characters".to_string());
+        }
+
+        if let Some(max_repeat) = options.max_repeat {
+            let longest_run = chars
+                .chunk_by(|a, b| a == b)
+                .map(|run| run.len())
+                .max()
+                .unwrap_or(0);
+            if longest_run > max_repeat {
+                violations.push(format!(
+                    "repeats a character more than {} times in a row",
+                    max_repeat
+                ));
+            }
+        }
+
+        if let Some(prefix) = &options.prefix
+            && !password.starts_with(prefix.as_str())
+        {
+            violations.push(format!("does not start with '{}'", prefix));
+        }
+
+        if let Some(class) = options.first_char
+            && !chars.first().is_some_and(|c| class.matches(*c))
+        {
+            violations.push(format!("does not start with a {}", class));
+        }
+
+        violations
+    }
+
+    /// Generate a diceware-style passphrase from a wordlist
+    pub fn generate_passphrase(options: &PassphraseOptions, wordlist: &Wordlist) -> String {
         let mut rng = rand::thread_rng();
+        let words = wordlist.words();
+
+        let mut chosen: Vec<String> = (0..options.words)
+            .map(|_| {
+                let word = &words[rng.gen_range(0..words.len())];
+                if options.capitalize {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first.to_uppercase().chain(chars).collect(),
+                        None => String::new(),
+                    }
+                } else {
+                    word.clone()
+                }
+            })
+            .collect();
+
+        if options.add_digit && !chosen.is_empty() {
+            let position = rng.gen_range(0..chosen.len());
+            let digit = rng.gen_range(0..10);
+            chosen[position].push_str(&digit.to_string());
+        }
+
+        chosen.join(&options.separator)
+    }
+
+    /// Estimate the entropy of a generated passphrase, in bits
+    pub fn passphrase_entropy(options: &PassphraseOptions, wordlist: &Wordlist) -> f64 {
+        let mut bits = options.words as f64 * wordlist.bits_per_word();
+        if options.add_digit && options.words > 0 {
+            // Random digit placed after a random word
+            bits += 10f64.log2() + (options.words as f64).log2();
+        }
+        bits
+    }
 
-        (0..options.length)
-            .map(|_| charset_chars[rng.gen_range(0..charset_chars.len())])
-            .collect()
+    /// Encrypt a stream in chunks, returning the number of plaintext bytes
+    pub fn encrypt_stream<R: Read, W: Write>(
+        reader: &mut R,
+        writer: &mut W,
+        key: &MasterKey,
+    ) -> Result<u64> {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key.key));
+        let mut prefix = [0u8; STREAM_NONCE_PREFIX_SIZE];
+        rand::thread_rng().fill(&mut prefix);
+
+        writer.write_all(STREAM_MAGIC)?;
+        writer.write_all(&prefix)?;
+
+        let mut current = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut next = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut current_len = read_full(reader, &mut current)?;
+        let mut counter: u32 = 0;
+        let mut total = 0u64;
+
+        loop {
+            // Look ahead one chunk to know whether this one is the last
+            let next_len = read_full(reader, &mut next)?;
+            let last = next_len == 0;
+
+            let nonce = stream_nonce(&prefix, counter, last);
+            let ciphertext = cipher
+                .encrypt(&nonce, &current[..current_len])
+                .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+            writer.write_all(&ciphertext)?;
+            total += current_len as u64;
+
+            if last {
+                break;
+            }
+            counter = counter
+                .checked_add(1)
+                .ok_or_else(|| anyhow!("Stream too large to encrypt"))?;
+            std::mem::swap(&mut current, &mut next);
+            current_len = next_len;
+        }
+
+        current.zeroize();
+        next.zeroize();
+        writer.flush()?;
+        Ok(total)
+    }
+
+    /// Decrypt a stream produced by `encrypt_stream`, returning the number of plaintext bytes
+    pub fn decrypt_stream<R: Read, W: Write>(
+        reader: &mut R,
+        writer: &mut W,
+        key: &MasterKey,
+    ) -> Result<u64> {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key.key));
+
+        let mut magic = [0u8; 4];
+        let mut prefix = [0u8; STREAM_NONCE_PREFIX_SIZE];
+        reader
+            .read_exact(&mut magic)
+            .and_then(|_| reader.read_exact(&mut prefix))
+            .map_err(|_| anyhow!("Encrypted stream is truncated"))?;
+        if &magic != STREAM_MAGIC {
+            return Err(anyhow!("Not an encrypted bunker stream"));
+        }
+
+        let chunk_size = STREAM_CHUNK_SIZE + STREAM_TAG_SIZE;
+        let mut current = vec![0u8; chunk_size];
+        let mut next = vec![0u8; chunk_size];
+        let mut current_len = read_full(reader, &mut current)?;
+        let mut counter: u32 = 0;
+        let mut total = 0u64;
+
+        loop {
+            if current_len < STREAM_TAG_SIZE {
+                return Err(anyhow!("Encrypted stream is truncated"));
+            }
+            let next_len = read_full(reader, &mut next)?;
+            let last = next_len == 0;
+
+            let nonce = stream_nonce(&prefix, counter, last);
+            let mut plaintext = cipher
+                .decrypt(&nonce, &current[..current_len])
+                .map_err(|_| anyhow!("Decryption failed: stream is corrupted or truncated"))?;
+            writer.write_all(&plaintext)?;
+            total += plaintext.len() as u64;
+            plaintext.zeroize();
+
+            if last {
+                break;
+            }
+            counter = counter
+                .checked_add(1)
+                .ok_or_else(|| anyhow!("Encrypted stream is too large"))?;
+            std::mem::swap(&mut current, &mut next);
+            current_len = next_len;
+        }
+
+        writer.flush()?;
+        Ok(total)
+    }
+
+    /// Keyed content hash, used to address encrypted blobs without revealing
+    /// which files they contain
+    pub fn content_id<R: Read>(reader: &mut R, key: &MasterKey) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"bunker-content-id-v1");
+        hasher.update(&key.key);
+
+        let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+        loop {
+            let n = reader.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+        }
+        buffer.zeroize();
+
+        Ok(hex::encode(hasher.finalize()))
     }
 
     /// Create checksum for data integrity
@@ -231,6 +845,77 @@ impl Crypto {
     }
 }
 
+/// Deterministic byte stream (SHA-256 in counter mode) used for derived passwords
+struct DerivedStream {
+    seed: Vec<u8>,
+    block: u64,
+    buffer: Vec<u8>,
+}
+
+impl DerivedStream {
+    fn new(seed: Vec<u8>) -> Self {
+        Self {
+            seed,
+            block: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.buffer.len() < 4 {
+            let mut hasher = Sha256::default();
+            hasher.update(&self.seed);
+            hasher.update(self.block.to_be_bytes());
+            self.block += 1;
+            self.buffer.extend_from_slice(&hasher.finalize());
+        }
+        let bytes: Vec<u8> = self.buffer.drain(..4).collect();
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    /// Uniform index in `0..n` (rejection sampling, no modulo bias)
+    fn next_below(&mut self, n: usize) -> usize {
+        let n = n as u64;
+        let zone = (u64::from(u32::MAX) + 1) / n * n;
+        loop {
+            let value = u64::from(self.next_u32());
+            if value < zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+}
+
+impl Drop for DerivedStream {
+    fn drop(&mut self) {
+        self.seed.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+/// Nonce of a STREAM chunk: prefix || counter (big-endian) || last-chunk flag
+fn stream_nonce(prefix: &[u8; STREAM_NONCE_PREFIX_SIZE], counter: u32, last: bool) -> Nonce {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..STREAM_NONCE_PREFIX_SIZE].copy_from_slice(prefix);
+    nonce[STREAM_NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_SIZE - 1] = last as u8;
+    *Nonce::from_slice(&nonce)
+}
+
+/// Fill `buf` as far as possible, returning fewer bytes only at end of input
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -251,9 +936,201 @@ mod tests {
     #[test]
     fn test_password_generation() {
         let options = GenerateOptions::default();
-        let password = Crypto::generate_password(&options);
+        let password = Crypto::generate_password(&options).unwrap();
 
         assert_eq!(password.len(), options.length);
         assert!(!password.is_empty());
+        assert!(Crypto::policy_violations(&password, &options).is_empty());
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let options = GenerateOptions {
+            length: 12,
+            min_numbers: 3,
+            min_symbols: 2,
+            max_repeat: Some(1),
+            allowed_symbols: Some("!#".to_string()),
+            forbidden_chars: Some("aeiou".to_string()),
+            prefix: Some("X".to_string()),
+            ..GenerateOptions::default()
+        };
+
+        for _ in 0..50 {
+            let password = Crypto::generate_password(&options).unwrap();
+            assert_eq!(password.chars().count(), 12);
+            assert!(password.starts_with('X'));
+            assert!(password.chars().filter(|c| c.is_ascii_digit()).count() >= 3);
+            assert!(password.chars().filter(|c| "!#".contains(*c)).count() >= 2);
+            assert!(!password.chars().any(|c| "aeiou@$%".contains(c)));
+            assert!(password.as_bytes().windows(2).all(|w| w[0] != w[1]));
+        }
+
+        let first_digit = GenerateOptions {
+            first_char: Some(CharClass::Digit),
+            ..GenerateOptions::default()
+        };
+        let password = Crypto::generate_password(&first_digit).unwrap();
+        assert!(password.starts_with(|c: char| c.is_ascii_digit()));
+
+        let impossible = GenerateOptions {
+            length: 4,
+            min_symbols: 4,
+            ..GenerateOptions::default()
+        };
+        assert!(Crypto::generate_password(&impossible).is_err());
+    }
+
+    #[test]
+    fn test_derive_site_password() {
+        let key = MasterKey::new(vec![7u8; 32]);
+        let params = DerivationParams {
+            site: "example.com".to_string(),
+            login: "alice".to_string(),
+            counter: 1,
+            policy: GenerateOptions::default(),
+        };
+
+        let first = Crypto::derive_site_password(&key, &params).unwrap();
+        let again = Crypto::derive_site_password(&key, &params).unwrap();
+        assert_eq!(first, again);
+        assert!(Crypto::policy_violations(&first, &params.policy).is_empty());
+
+        let rotated = DerivationParams {
+            counter: 2,
+            ..params.clone()
+        };
+        assert_ne!(first, Crypto::derive_site_password(&key, &rotated).unwrap());
+
+        let other_key = MasterKey::new(vec![8u8; 32]);
+        assert_ne!(
+            first,
+            Crypto::derive_site_password(&other_key, &params).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_derive_site_password_known_answers() {
+        // Frozen outputs: if these change, every derived password has changed
+        let key = MasterKey::new((0u8..32).collect());
+        let cases = [
+            (
+                DerivationParams {
+                    site: "example.com".to_string(),
+                    login: "alice".to_string(),
+                    counter: 1,
+                    policy: Crypto::derivation_policy(),
+                },
+                "$RVr+9jq5a?Z8mpzP]TV",
+            ),
+            (
+                DerivationParams {
+                    site: "bank.example".to_string(),
+                    login: String::new(),
+                    counter: 3,
+                    policy: GenerateOptions {
+                        length: 12,
+                        use_symbols: false,
+                        max_repeat: Some(1),
+                        first_char: Some(CharClass::Uppercase),
+                        min_numbers: 3,
+                        ..Crypto::derivation_policy()
+                    },
+                },
+                "D763qmE3Ztz5",
+            ),
+            (
+                DerivationParams {
+                    site: "pin".to_string(),
+                    login: "bob".to_string(),
+                    counter: 1,
+                    policy: GenerateOptions {
+                        length: 8,
+                        custom_charset: Some("0123456789".to_string()),
+                        prefix: Some("42".to_string()),
+                        ..Crypto::derivation_policy()
+                    },
+                },
+                "42901561",
+            ),
+        ];
+
+        for (params, expected) in cases {
+            assert_eq!(
+                Crypto::derive_site_password(&key, &params).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_passphrase_generation() {
+        let wordlist = Wordlist::builtin();
+        let options = PassphraseOptions {
+            words: 5,
+            separator: ".".to_string(),
+            capitalize: true,
+            add_digit: true,
+            wordlist: None,
+        };
+        let passphrase = Crypto::generate_passphrase(&options, &wordlist);
+        let parts: Vec<&str> = passphrase.split('.').collect();
+
+        assert_eq!(parts.len(), 5);
+        assert!(parts.iter().all(|p| p.starts_with(char::is_uppercase)));
+        assert_eq!(passphrase.chars().filter(char::is_ascii_digit).count(), 1);
+
+        // 5 words from a 2048-word list
+        let plain = PassphraseOptions {
+            words: 5,
+            ..PassphraseOptions::default()
+        };
+        assert_eq!(Crypto::passphrase_entropy(&plain, &wordlist), 55.0);
+    }
+
+    #[test]
+    fn test_wordlist_parse_diceware_format() {
+        let content: String = (0..20).map(|i| format!("1111{}\tword{}\n", i, i)).collect();
+        let wordlist = Wordlist::parse(&content).unwrap();
+
+        assert_eq!(wordlist.words().len(), 20);
+        assert_eq!(wordlist.words()[0], "word0");
+        assert!(Wordlist::parse("too\nfew\n").is_err());
+    }
+
+    #[test]
+    fn test_stream_encryption() {
+        let key = MasterKey::new(vec![7u8; KEY_SIZE]);
+
+        for size in [0, 10, STREAM_CHUNK_SIZE, 2 * STREAM_CHUNK_SIZE + 5] {
+            let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let mut encrypted = Vec::new();
+            let written =
+                Crypto::encrypt_stream(&mut data.as_slice(), &mut encrypted, &key).unwrap();
+            assert_eq!(written, size as u64);
+
+            let mut decrypted = Vec::new();
+            Crypto::decrypt_stream(&mut encrypted.as_slice(), &mut decrypted, &key).unwrap();
+            assert_eq!(decrypted, data);
+
+            // Tampering and truncation are both detected
+            let mut tampered = encrypted.clone();
+            let last = tampered.len() - 1;
+            tampered[last] ^= 1;
+            assert!(
+                Crypto::decrypt_stream(&mut tampered.as_slice(), &mut Vec::new(), &key).is_err()
+            );
+            if size > STREAM_CHUNK_SIZE {
+                let header = STREAM_MAGIC.len() + STREAM_NONCE_PREFIX_SIZE;
+                let truncated = &encrypted[..header + STREAM_CHUNK_SIZE + STREAM_TAG_SIZE];
+                assert!(
+                    Crypto::decrypt_stream(&mut &truncated[..], &mut Vec::new(), &key).is_err()
+                );
+            }
+        }
+
+        let id1 = Crypto::content_id(&mut &b"same"[..], &key).unwrap();
+        let id2 = Crypto::content_id(&mut &b"same"[..], &key).unwrap();
+        assert_eq!(id1, id2);
     }
 }
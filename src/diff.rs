@@ -0,0 +1,114 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+use crate::crypto::Crypto;
+use crate::schema;
+use crate::storage::Storage;
+use crate::types::{Entry, MasterKey};
+use crate::utils;
+
+/// Decrypted view of an entry used to compare entries across vaults, keyed by
+/// field name: `value`, `type`, `username`, `url`, `notes`, `tags`, `expires`,
+/// and `field:`, `custom:`, `secret:` and `attachment:` prefixed names.
+///
+/// History, ids and timestamps are left out. Attachments are represented by a
+/// hash of their content, since blob ids depend on the vault key, or only by
+/// their size when no storage is given (export files carry no attachments).
+pub fn flatten(
+    storage: Option<&Storage>,
+    entry: &Entry,
+    master_key: &MasterKey,
+) -> Result<BTreeMap<String, String>> {
+    let metadata = &entry.metadata;
+    let mut fields = BTreeMap::new();
+
+    fields.insert("value".to_string(), utils::entry_value(entry, master_key)?);
+    fields.insert("type".to_string(), metadata.entry_type.to_string());
+    let builtin = [
+        ("username", &metadata.username),
+        ("url", &metadata.url),
+        ("notes", &metadata.notes),
+    ];
+    for (name, value) in builtin {
+        if let Some(value) = value {
+            fields.insert(name.to_string(), value.clone());
+        }
+    }
+    if !metadata.tags.is_empty() {
+        let mut tags = metadata.tags.clone();
+        tags.sort();
+        fields.insert("tags".to_string(), tags.join(", "));
+    }
+    if let Some(expires) = metadata.expires_at {
+        fields.insert("expires".to_string(), expires.to_rfc3339());
+    }
+
+    for (name, value) in &metadata.fields {
+        fields.insert(
+            format!("field:{}", name),
+            schema::read_field(value, master_key)?,
+        );
+    }
+    for (name, value) in &metadata.custom_fields {
+        fields.insert(format!("custom:{}", name), value.clone());
+    }
+    for (name, encrypted) in &metadata.secret_fields {
+        let decrypted = Crypto::decrypt(encrypted, master_key)?;
+        fields.insert(
+            format!("secret:{}", name),
+            String::from_utf8_lossy(&decrypted).into_owned(),
+        );
+    }
+    for attachment in &metadata.attachments {
+        let summary = match storage {
+            Some(storage) => {
+                let mut data = Vec::new();
+                storage.read_attachment(&attachment.blob, &mut data, master_key)?;
+                let hash = hex::encode(Sha256::digest(&data));
+                data.zeroize();
+                hash
+            }
+            None => format!("{} bytes", attachment.size),
+        };
+        fields.insert(format!("attachment:{}", attachment.name), summary);
+    }
+
+    Ok(fields)
+}
+
+/// Names of the fields that differ between two flattened entries
+pub fn changed_fields(a: &BTreeMap<String, String>, b: &BTreeMap<String, String>) -> Vec<String> {
+    let mut names: Vec<&String> = a.keys().chain(b.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter(|name| a.get(*name) != b.get(*name))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_fields() {
+        let map = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let a = map(&[("value", "x"), ("url", "a.com"), ("custom:env", "prod")]);
+        let b = map(&[("value", "x"), ("url", "b.com"), ("username", "me")]);
+
+        assert_eq!(
+            changed_fields(&a, &b),
+            vec!["custom:env", "url", "username"]
+        );
+        assert!(changed_fields(&a, &a).is_empty());
+    }
+}
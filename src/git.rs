@@ -24,7 +24,10 @@ impl Git {
 
         // Create .gitignore
         let gitignore = path.join(".gitignore");
-        std::fs::write(gitignore, "*.tmp\n*.swp\n.DS_Store\nsessions/\n")?;
+        std::fs::write(
+            gitignore,
+            "*.tmp\n*.swp\n.DS_Store\n.lock\n.index\nsessions/\n",
+        )?;
 
         Ok(())
     }
@@ -38,9 +41,17 @@ impl Git {
             .index()
             .map_err(|e| anyhow!("Failed to get index: {}", e))?;
 
-        // Add all files
+        // Add all files except the vault's lock file and search index, which
+        // older vaults do not ignore
+        let mut skip_local = |path: &Path, _: &[u8]| {
+            i32::from(path == Path::new(".lock") || path == Path::new(".index"))
+        };
         index
-            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .add_all(
+                ["*"].iter(),
+                git2::IndexAddOption::DEFAULT,
+                Some(&mut skip_local),
+            )
             .map_err(|e| anyhow!("Failed to add files: {}", e))?;
 
         index
@@ -0,0 +1,126 @@
+use anyhow::{Result, anyhow};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Home chosen with `--home`, which wins over the environment
+static OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Where bunker keeps its files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BunkerHome {
+    /// Holds `vaults/`, `sessions/` and `backups/`
+    pub data: PathBuf,
+    /// Holds `config.toml`
+    pub config: PathBuf,
+}
+
+impl BunkerHome {
+    /// Data and config together in one directory
+    fn single(dir: PathBuf) -> Self {
+        Self {
+            data: dir.clone(),
+            config: dir,
+        }
+    }
+}
+
+/// Use `dir` as the bunker home for the rest of the process
+pub fn set_override(dir: &Path) -> Result<()> {
+    let dir = std::path::absolute(dir)?;
+    OVERRIDE
+        .set(dir)
+        .map_err(|_| anyhow!("Bunker home already set"))
+}
+
+/// Find the bunker home: `--home`, then `BUNKER_HOME`, then an existing
+/// `~/.bunker`, then the XDG directories if either is set, then `~/.bunker`
+pub fn locate() -> Result<BunkerHome> {
+    if let Some(dir) = OVERRIDE.get() {
+        return Ok(BunkerHome::single(dir.clone()));
+    }
+    resolve(
+        env_dir("BUNKER_HOME"),
+        dirs::home_dir(),
+        env_dir("XDG_DATA_HOME"),
+        env_dir("XDG_CONFIG_HOME"),
+    )
+}
+
+/// A directory from the environment; empty and relative values are ignored,
+/// as the XDG spec asks
+fn env_dir(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+fn resolve(
+    bunker_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+    xdg_data: Option<PathBuf>,
+    xdg_config: Option<PathBuf>,
+) -> Result<BunkerHome> {
+    if let Some(dir) = bunker_home {
+        return Ok(BunkerHome::single(dir));
+    }
+
+    // Keep using a home created before the XDG directories were supported
+    let legacy = home.as_ref().map(|home| home.join(".bunker"));
+    if let Some(legacy) = &legacy
+        && legacy.is_dir()
+    {
+        return Ok(BunkerHome::single(legacy.clone()));
+    }
+
+    if xdg_data.is_some() || xdg_config.is_some() {
+        let data = xdg_data.or_else(|| home.as_ref().map(|h| h.join(".local").join("share")));
+        let config = xdg_config.or_else(|| home.as_ref().map(|h| h.join(".config")));
+        if let (Some(data), Some(config)) = (data, config) {
+            return Ok(BunkerHome {
+                data: data.join("bunker"),
+                config: config.join("bunker"),
+            });
+        }
+    }
+
+    legacy.map(BunkerHome::single).ok_or_else(|| {
+        anyhow!("Could not determine a home directory. Set BUNKER_HOME or pass --home <dir>")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_home() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let xdg_data = root.path().join("data");
+        let xdg_config = root.path().join("config");
+
+        // BUNKER_HOME wins over everything
+        let explicit = root.path().join("explicit");
+        let resolved = resolve(
+            Some(explicit.clone()),
+            Some(home.clone()),
+            Some(xdg_data.clone()),
+            None,
+        );
+        assert_eq!(resolved.unwrap(), BunkerHome::single(explicit));
+
+        // XDG directories, each falling back to its usual place under the home
+        let resolved = resolve(None, Some(home.clone()), Some(xdg_data.clone()), None).unwrap();
+        assert_eq!(resolved.data, xdg_data.join("bunker"));
+        assert_eq!(resolved.config, home.join(".config").join("bunker"));
+
+        // ...unless there already is a ~/.bunker
+        std::fs::create_dir_all(home.join(".bunker")).unwrap();
+        let resolved = resolve(None, Some(home.clone()), Some(xdg_data), Some(xdg_config));
+        assert_eq!(resolved.unwrap(), BunkerHome::single(home.join(".bunker")));
+
+        // No home directory and nothing set, as in some containers
+        assert!(resolve(None, None, None, None).is_err());
+    }
+}
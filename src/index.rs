@@ -0,0 +1,167 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::crypto::Crypto;
+use crate::types::{EncryptedValue, EntryMetadata, EntryType, MasterKey};
+
+/// Version of the index format; an index of another version is rebuilt
+const INDEX_VERSION: u32 = 1;
+
+/// The searchable metadata of an entry, as kept in the search index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedEntry {
+    pub entry_type: EntryType,
+    pub username: Option<String>,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub custom_fields: HashMap<String, String>,
+}
+
+impl IndexedEntry {
+    pub fn from_metadata(metadata: &EntryMetadata) -> Self {
+        Self {
+            entry_type: metadata.entry_type.clone(),
+            username: metadata.username.clone(),
+            url: metadata.url.clone(),
+            notes: metadata.notes.clone(),
+            tags: metadata.tags.clone(),
+            custom_fields: metadata.custom_fields.clone(),
+        }
+    }
+
+    /// Searchable fields as (name, value) pairs, custom fields under their own name
+    pub fn fields(&self) -> Vec<(&str, &str)> {
+        let mut fields = Vec::new();
+        let builtin = [
+            ("username", &self.username),
+            ("url", &self.url),
+            ("notes", &self.notes),
+        ];
+        for (name, value) in builtin {
+            if let Some(value) = value {
+                fields.push((name, value.as_str()));
+            }
+        }
+        for tag in &self.tags {
+            fields.push(("tag", tag.as_str()));
+        }
+        let mut custom: Vec<_> = self.custom_fields.iter().collect();
+        custom.sort();
+        for (name, value) in custom {
+            fields.push((name.as_str(), value.as_str()));
+        }
+        fields
+    }
+}
+
+/// Search index of a vault.
+///
+/// Each entry's searchable metadata is encrypted on its own, under its key, so
+/// entries can be dropped or moved to and from the trash without the master key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    #[serde(default)]
+    entries: BTreeMap<String, EncryptedValue>,
+    #[serde(default)]
+    trash: BTreeMap<String, EncryptedValue>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            ..Self::default()
+        }
+    }
+
+    /// Parse a stored index, or None if it is unreadable or from another version
+    pub fn parse(data: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(data)
+            .ok()
+            .filter(|index| index.version == INDEX_VERSION)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    fn records(&mut self, trashed: bool) -> &mut BTreeMap<String, EncryptedValue> {
+        if trashed {
+            &mut self.trash
+        } else {
+            &mut self.entries
+        }
+    }
+
+    /// Index an entry's metadata, replacing what was indexed under its key
+    pub fn insert(
+        &mut self,
+        key: &str,
+        metadata: &EntryMetadata,
+        trashed: bool,
+        master_key: &MasterKey,
+    ) -> Result<()> {
+        let json = serde_json::to_vec(&IndexedEntry::from_metadata(metadata))?;
+        let encrypted = Crypto::encrypt(&json, master_key)?;
+        self.records(trashed).insert(key.to_string(), encrypted);
+        Ok(())
+    }
+
+    /// Drop a key, returning whether it was indexed
+    pub fn remove(&mut self, key: &str, trashed: bool) -> bool {
+        self.records(trashed).remove(key).is_some()
+    }
+
+    /// Move a key between the entries and the trash
+    pub fn transfer(&mut self, key: &str, to_trash: bool) {
+        if let Some(record) = self.records(!to_trash).remove(key) {
+            self.records(to_trash).insert(key.to_string(), record);
+        }
+    }
+
+    pub fn keys(&self, trashed: bool) -> Vec<String> {
+        let records = if trashed { &self.trash } else { &self.entries };
+        records.keys().cloned().collect()
+    }
+
+    /// Decrypt an indexed entry; None if it is missing or was encrypted with another key
+    pub fn get(&self, key: &str, trashed: bool, master_key: &MasterKey) -> Option<IndexedEntry> {
+        let records = if trashed { &self.trash } else { &self.entries };
+        let decrypted = Crypto::decrypt(records.get(key)?, master_key).ok()?;
+        serde_json::from_slice(&decrypted).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_index() {
+        let master_key = MasterKey::new(vec![3; 32]);
+        let metadata = EntryMetadata {
+            username: Some("alice".to_string()),
+            tags: vec!["work".to_string()],
+            ..EntryMetadata::default()
+        };
+
+        let mut index = SearchIndex::new();
+        index.insert("mail", &metadata, false, &master_key).unwrap();
+        index.transfer("mail", true);
+        assert!(index.keys(false).is_empty());
+
+        let stored = SearchIndex::parse(&index.to_json().unwrap()).unwrap();
+        let entry = stored.get("mail", true, &master_key).unwrap();
+        assert_eq!(entry.fields(), vec![("username", "alice"), ("tag", "work")]);
+        assert!(
+            stored
+                .get("mail", true, &MasterKey::new(vec![4; 32]))
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+/// Longest key accepted, in bytes
+pub const MAX_KEY_LEN: usize = 512;
+
+/// Longest path segment, in bytes, leaving room for the `.json`/`.link` extension
+const MAX_SEGMENT_LEN: usize = 250;
+
+/// Names Windows refuses as file names, whatever the extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// An entry key that is safe to turn into a path under the vault.
+///
+/// Keys are NFC-normalized `/`-separated segments. Repeated and trailing
+/// slashes are collapsed; absolute paths, `.` and `..` segments, backslashes,
+/// control characters and reserved device names are rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryKey(String);
+
+impl EntryKey {
+    pub fn parse(key: &str) -> Result<Self> {
+        let invalid = |reason: &str| anyhow!("Invalid key '{}': {}", key.escape_debug(), reason);
+
+        if key.starts_with('/') {
+            return Err(invalid("keys cannot be absolute paths"));
+        }
+        if key.chars().any(char::is_control) {
+            return Err(invalid("keys cannot contain control characters"));
+        }
+        if key.contains('\\') {
+            return Err(invalid("use '/' to separate key segments"));
+        }
+
+        let normalized: String = key.nfc().collect();
+        let segments: Vec<&str> = normalized.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            return Err(invalid("keys cannot be empty"));
+        }
+        for segment in &segments {
+            if *segment == "." || *segment == ".." {
+                return Err(invalid("keys cannot contain '.' or '..' segments"));
+            }
+            let stem = segment.split('.').next().unwrap_or(segment);
+            if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+                return Err(invalid(&format!("'{}' is a reserved name", segment)));
+            }
+            if segment.len() > MAX_SEGMENT_LEN {
+                return Err(invalid(&format!(
+                    "segments are limited to {} bytes",
+                    MAX_SEGMENT_LEN
+                )));
+            }
+        }
+
+        let joined = segments.join("/");
+        if joined.len() > MAX_KEY_LEN {
+            return Err(invalid(&format!(
+                "keys are limited to {} bytes",
+                MAX_KEY_LEN
+            )));
+        }
+        Ok(Self(joined))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl std::fmt::Display for EntryKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::str::FromStr for EntryKey {
+    type Err = anyhow::Error;
+
+    fn from_str(key: &str) -> Result<Self> {
+        Self::parse(key)
+    }
+}
+
+/// Normalize a batch of keys, failing when two of them become the same key
+pub fn normalize_all<'a>(keys: impl IntoIterator<Item = &'a str>) -> Result<Vec<EntryKey>> {
+    let mut seen: HashMap<EntryKey, &str> = HashMap::new();
+    let mut normalized = Vec::new();
+    for key in keys {
+        let parsed = EntryKey::parse(key)?;
+        if let Some(other) = seen.insert(parsed.clone(), key) {
+            return Err(anyhow!(
+                "Keys '{}' and '{}' both normalize to '{}'",
+                other,
+                key,
+                parsed
+            ));
+        }
+        normalized.push(parsed);
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_key() {
+        assert_eq!(
+            EntryKey::parse("work//db/prod/").unwrap().as_str(),
+            "work/db/prod"
+        );
+        // "e" + combining acute accent becomes a single "é"
+        assert_eq!(
+            EntryKey::parse("cafe\u{301}").unwrap().as_str(),
+            "caf\u{e9}"
+        );
+        assert_eq!(
+            EntryKey::parse("github.com").unwrap().as_str(),
+            "github.com"
+        );
+
+        for bad in [
+            "../../sessions/x",
+            "a/./b",
+            "/etc/passwd",
+            "a\0b",
+            "a\\b",
+            "",
+            "//",
+            "nul.txt",
+        ] {
+            assert!(
+                EntryKey::parse(bad).is_err(),
+                "{:?} should be rejected",
+                bad
+            );
+        }
+        assert!(EntryKey::parse(&"a".repeat(MAX_SEGMENT_LEN + 1)).is_err());
+
+        assert!(normalize_all(["a/b", "a//b"]).is_err());
+        assert_eq!(normalize_all(["a", "b"]).unwrap().len(), 2);
+    }
+}
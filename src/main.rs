@@ -1,12 +1,22 @@
+mod backend;
 mod cli;
 mod commands;
 mod config;
 mod crypto;
+mod diff;
 mod error;
 mod git;
+mod home;
+mod index;
+mod key;
+mod refs;
+mod schema;
+mod select;
 mod storage;
+mod tags;
 mod types;
 mod utils;
+mod wordlist;
 
 use anyhow::Result;
 use clap::Parser;
@@ -16,6 +26,9 @@ use colored::*;
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(dir) = &cli.home {
+        home::set_override(dir)?;
+    }
 
     // Handle no command case
     let command = match cli.command {
@@ -52,29 +65,70 @@ async fn main() -> Result<()> {
         }
     };
 
+    // Hold the vault's lock for the whole of a mutating command
+    let _lock = if command.mutates_vault() {
+        let storage = storage::Storage::new(cli.vault.clone())?;
+        if storage.vault_exists() {
+            Some(storage.lock()?)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
     // Execute command
     match command {
         Commands::Init {
             name,
             non_interactive,
-        } => commands::init::execute(name, non_interactive, cli.vault).await,
+            single_file,
+            here,
+        } => commands::init::execute(name, non_interactive, single_file, here, cli.vault).await,
 
         Commands::Add {
             key,
             value,
             note,
             file,
-        } => commands::add::execute(key, value, note, file, cli.vault).await,
+            force,
+            flags,
+        } => commands::add::execute(key, value, note, file, force, flags, cli.vault).await,
 
-        Commands::Get { key, copy, timeout } => commands::get::execute(key, copy, cli.vault).await,
+        Commands::Get {
+            key,
+            field,
+            copy,
+            timeout,
+        } => commands::get::execute(key, field, copy, cli.vault).await,
 
-        Commands::Edit { key, value } => commands::edit::execute(key, value, cli.vault).await,
+        Commands::Edit {
+            key,
+            value,
+            editor,
+            flags,
+        } => commands::edit::execute(key, value, editor, flags, cli.vault).await,
 
-        Commands::Remove { key, force } => commands::remove::execute(key, force, cli.vault).await,
+        Commands::Remove {
+            key,
+            force,
+            permanent,
+            recursive,
+            dry_run,
+        } => commands::remove::execute(key, force, permanent, recursive, dry_run, cli.vault).await,
 
-        Commands::List { tree } => commands::list::execute(None, tree, cli.vault).await,
+        Commands::List {
+            path,
+            tree,
+            include_trash,
+            tags,
+        } => commands::list::execute(path, tree, tags, include_trash, cli.vault).await,
 
-        Commands::Search { query } => commands::search::execute(query, cli.vault).await,
+        Commands::Search {
+            query,
+            include_trash,
+            tags,
+        } => commands::search::execute(query, tags, include_trash, cli.vault).await,
 
         Commands::Generate {
             length,
@@ -84,38 +138,154 @@ async fn main() -> Result<()> {
             symbols,
             no_ambiguous,
             charset,
+            min_upper,
+            min_lower,
+            min_digits,
+            min_symbols,
+            max_repeat,
+            allowed_symbols,
+            forbid,
+            prefix,
+            first_char,
+            profile,
             copy,
+            words,
+            separator,
+            capitalize,
+            add_digit,
+            wordlist,
         } => {
-            let options = types::GenerateOptions {
-                length,
-                use_uppercase: uppercase,
-                use_lowercase: lowercase,
-                use_numbers: numbers,
-                use_symbols: symbols,
-                exclude_ambiguous: no_ambiguous,
-                custom_charset: charset,
+            // Start from the named profile (if any), then apply explicit flags
+            let mut options = match &profile {
+                Some(name) => config::Config::load()?.profile(name)?,
+                None => types::GenerateOptions::default(),
             };
-            commands::generate::execute(None, length, !symbols, !numbers, !uppercase, cli.vault)
+            if uppercase || lowercase || numbers || symbols {
+                options.use_uppercase = uppercase;
+                options.use_lowercase = lowercase;
+                options.use_numbers = numbers;
+                options.use_symbols = symbols;
+            }
+            if no_ambiguous {
+                options.exclude_ambiguous = true;
+            }
+            if let Some(length) = length {
+                options.length = length;
+            }
+            options.custom_charset = charset.or(options.custom_charset);
+            options.min_uppercase = min_upper.unwrap_or(options.min_uppercase);
+            options.min_lowercase = min_lower.unwrap_or(options.min_lowercase);
+            options.min_numbers = min_digits.unwrap_or(options.min_numbers);
+            options.min_symbols = min_symbols.unwrap_or(options.min_symbols);
+            options.max_repeat = max_repeat.or(options.max_repeat);
+            options.allowed_symbols = allowed_symbols.or(options.allowed_symbols);
+            options.forbidden_chars = forbid.or(options.forbidden_chars);
+            options.prefix = prefix.or(options.prefix);
+            options.first_char = first_char.or(options.first_char);
+
+            let passphrase = words.map(|words| types::PassphraseOptions {
+                words,
+                separator,
+                capitalize,
+                add_digit,
+                wordlist,
+            });
+            commands::generate::execute(None, options, passphrase, copy, cli.vault).await
+        }
+
+        Commands::Derive {
+            site,
+            login,
+            counter,
+            key,
+            length,
+            no_symbols,
+            profile,
+            copy,
+            no_save,
+        } => {
+            // Only override the saved policy when a policy flag is given
+            let policy = if profile.is_some() || length.is_some() || no_symbols {
+                let mut options = match &profile {
+                    Some(name) => config::Config::load()?.profile(name)?,
+                    None => crypto::Crypto::derivation_policy(),
+                };
+                if let Some(length) = length {
+                    options.length = length;
+                }
+                if no_symbols {
+                    options.use_symbols = false;
+                    options.min_symbols = 0;
+                }
+                Some(options)
+            } else {
+                None
+            };
+            commands::derive::execute(site, login, counter, key, policy, copy, no_save, cli.vault)
                 .await
         }
 
-        Commands::Copy { key, timeout } => {
-            commands::copy::execute(key, false, timeout, cli.vault).await
+        Commands::Rotate {
+            key,
+            tags,
+            path,
+            profile,
+            hook,
+            hook_stdin,
+            dry_run,
+        } => {
+            let hook = hook.map(|command| commands::rotate::RotationHook {
+                command,
+                stdin: hook_stdin,
+            });
+            commands::rotate::execute(key, tags, path, profile, hook, dry_run, cli.vault).await
         }
 
-        Commands::Peek { key, chars } => commands::peek::execute(key, cli.vault).await,
+        Commands::Copy {
+            key,
+            field,
+            timeout,
+        } => commands::copy::execute(key, field, false, timeout, cli.vault).await,
 
-        Commands::Move { from, to } => commands::move_cmd::execute(from, to, cli.vault).await,
+        Commands::Peek { key, field, chars } => {
+            commands::peek::execute(key, field, cli.vault).await
+        }
 
-        Commands::Exec { command, key, env } => {
-            commands::exec::execute(command, key, env, cli.vault).await
+        Commands::Move {
+            from,
+            to,
+            recursive,
+            dry_run,
+            update_refs,
+        } => {
+            commands::move_cmd::execute(from, to, recursive, dry_run, update_refs, cli.vault).await
         }
 
+        Commands::Cp {
+            from,
+            to,
+            recursive,
+            dry_run,
+        } => commands::cp::execute(from, to, recursive, dry_run, cli.vault).await,
+
+        Commands::Exec {
+            command,
+            key,
+            field,
+            env,
+        } => commands::exec::execute(command, key, field, env, cli.vault).await,
+
         Commands::Export {
             format,
             output,
             metadata,
-        } => commands::export::execute(format, output, metadata, cli.vault).await,
+            tags,
+            path,
+            dry_run,
+        } => {
+            commands::export::execute(format, output, metadata, tags, path, dry_run, cli.vault)
+                .await
+        }
 
         Commands::Import {
             file,
@@ -126,7 +296,17 @@ async fn main() -> Result<()> {
         Commands::Grep {
             pattern,
             ignore_case,
-        } => commands::grep::execute(pattern, ignore_case, cli.vault).await,
+            tags,
+            metadata_only,
+        } => commands::grep::execute(pattern, ignore_case, tags, metadata_only, cli.vault).await,
+
+        Commands::Index { action } => commands::index::execute(action, cli.vault).await,
+
+        Commands::Trash { action } => commands::trash::execute(action, cli.vault).await,
+
+        Commands::Tag { action } => commands::tag::execute(action, cli.vault).await,
+
+        Commands::Attach { action } => commands::attach::execute(action, cli.vault).await,
 
         Commands::Git { action } => match action {
             GitAction::Sync => commands::sync::execute(None, cli.vault).await,
@@ -138,8 +318,8 @@ async fn main() -> Result<()> {
         },
 
         Commands::Vault { action } => match action {
-            VaultAction::Create { name } => {
-                commands::vault::execute(cli::VaultAction::Create { name }).await
+            VaultAction::Create { name, single_file } => {
+                commands::vault::execute(cli::VaultAction::Create { name, single_file }).await
             }
             VaultAction::Use { name } => {
                 commands::vault::execute(cli::VaultAction::Use { name }).await
@@ -156,6 +336,28 @@ async fn main() -> Result<()> {
                 password,
                 name,
             } => commands::import_vault::execute(file, password, name).await,
+            VaultAction::Merge {
+                source,
+                into,
+                strategy,
+                suffix,
+                dry_run,
+            } => {
+                commands::merge_vault::execute(source, into, strategy, suffix, dry_run, cli.vault)
+                    .await
+            }
+            VaultAction::Diff {
+                a,
+                b,
+                export,
+                password,
+            } => commands::diff_vault::execute(a, b, export, password).await,
+            VaultAction::TrashRetention { days } => {
+                commands::vault::trash_retention(days, cli.vault).await
+            }
+            VaultAction::HistoryLimit { limit } => {
+                commands::vault::history_limit(limit, cli.vault).await
+            }
         },
 
         Commands::Lock => commands::lock::execute(cli.vault).await,
@@ -170,10 +372,22 @@ async fn main() -> Result<()> {
             commands::restore_backup::execute(backup, vault_name).await
         }
 
-        Commands::History { key, limit } => {
-            commands::history::execute(key, Some(limit), cli.vault).await
+        Commands::History { key, limit, values } => {
+            commands::history::execute(key, Some(limit), values, cli.vault).await
         }
 
-        Commands::Env { key, var } => commands::env::execute(key, var, cli.vault).await,
+        Commands::Ln {
+            target,
+            alias,
+            force,
+        } => commands::ln::execute(target, alias, force, cli.vault).await,
+
+        Commands::Refs { key } => commands::refs::execute(key, cli.vault).await,
+
+        Commands::Revert { key, to } => commands::revert::execute(key, to, cli.vault).await,
+
+        Commands::Env { key, field, var } => {
+            commands::env::execute(key, field, var, cli.vault).await
+        }
     }
 }
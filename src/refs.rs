@@ -0,0 +1,305 @@
+use anyhow::{Result, anyhow};
+
+use crate::crypto::Crypto;
+use crate::key::EntryKey;
+use crate::schema;
+use crate::storage::Storage;
+use crate::types::{EncryptedValue, Entry, FieldValue, MasterKey};
+use crate::utils;
+
+const REF_OPEN: &str = "{{ref:";
+const REF_CLOSE: &str = "}}";
+
+/// A `{{ref:key#field}}` reference to another entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub key: String,
+    /// Field of the referenced entry; the main value when not given
+    pub field: Option<String>,
+}
+
+impl Reference {
+    fn node(&self) -> String {
+        node(&self.key, self.field.as_deref())
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "{}#{}", self.key, field),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+/// Find the references in a value
+pub fn parse(value: &str) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    let mut rest = value;
+    while let Some((_, reference, after)) = next_ref(rest) {
+        refs.push(reference);
+        rest = after;
+    }
+    refs
+}
+
+/// Get a field of an entry with its references resolved
+pub fn resolve_field(
+    storage: &Storage,
+    entry: &Entry,
+    field: Option<&str>,
+    master_key: &MasterKey,
+) -> Result<String> {
+    let raw = utils::entry_field(entry, field, master_key)?;
+    let mut stack = vec![node(&entry.key, field)];
+    resolve_with(&raw, storage, master_key, &mut stack)
+}
+
+/// List the references made by an entry as (field, reference) pairs
+pub fn entry_refs(entry: &Entry, master_key: &MasterKey) -> Result<Vec<(String, Reference)>> {
+    let metadata = &entry.metadata;
+    let mut values = vec![("value".to_string(), utils::entry_value(entry, master_key)?)];
+    for (name, value) in &metadata.fields {
+        values.push((name.clone(), schema::read_field(value, master_key)?));
+    }
+    for (name, value) in &metadata.custom_fields {
+        values.push((name.clone(), value.clone()));
+    }
+    for (name, encrypted) in &metadata.secret_fields {
+        let decrypted = Crypto::decrypt(encrypted, master_key)?;
+        values.push((
+            name.clone(),
+            String::from_utf8_lossy(&decrypted).into_owned(),
+        ));
+    }
+
+    let mut refs = Vec::new();
+    for (name, value) in values {
+        for reference in parse(&value) {
+            refs.push((name.clone(), reference));
+        }
+    }
+    refs.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(refs)
+}
+
+/// Point the references of an entry at new keys after a move, given as
+/// (old key, new key) pairs; returns whether anything was rewritten.
+///
+/// A derived entry's value is computed, so only its fields are rewritten.
+pub fn retarget(
+    entry: &mut Entry,
+    moves: &[(String, String)],
+    master_key: &MasterKey,
+) -> Result<bool> {
+    let rewrite_secret = |encrypted: &mut EncryptedValue| -> Result<bool> {
+        let decrypted = Crypto::decrypt(encrypted, master_key)?;
+        match rewrite(&String::from_utf8_lossy(&decrypted), moves) {
+            Some(value) => {
+                *encrypted = Crypto::encrypt(value.as_bytes(), master_key)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    };
+
+    let mut changed = false;
+    if entry.metadata.derivation.is_none() {
+        changed |= rewrite_secret(&mut entry.value)?;
+    }
+    let metadata = &mut entry.metadata;
+    for value in metadata.fields.values_mut() {
+        changed |= match value {
+            FieldValue::Plain(plain) => rewrite_plain(plain, moves),
+            FieldValue::Secret(encrypted) => rewrite_secret(encrypted)?,
+        };
+    }
+    for value in metadata.custom_fields.values_mut() {
+        changed |= rewrite_plain(value, moves);
+    }
+    for encrypted in metadata.secret_fields.values_mut() {
+        changed |= rewrite_secret(encrypted)?;
+    }
+    Ok(changed)
+}
+
+fn rewrite_plain(value: &mut String, moves: &[(String, String)]) -> bool {
+    match rewrite(value, moves) {
+        Some(rewritten) => {
+            *value = rewritten;
+            true
+        }
+        None => false,
+    }
+}
+
+/// The new key of a reference to a moved entry, however the key was written
+fn moved_to<'a>(reference: &Reference, moves: &'a [(String, String)]) -> Option<&'a str> {
+    let key = EntryKey::parse(&reference.key).ok()?;
+    moves
+        .iter()
+        .find(|(from, _)| from == key.as_str())
+        .map(|(_, to)| to.as_str())
+}
+
+/// Rewrite the references to moved entries in a value, or None if it has none;
+/// other references are left exactly as written
+fn rewrite(value: &str, moves: &[(String, String)]) -> Option<String> {
+    let mut rewritten = String::new();
+    let mut changed = false;
+    let mut rest = value;
+
+    while let Some((before, reference, after)) = next_ref(rest) {
+        rewritten.push_str(before);
+        match moved_to(&reference, moves) {
+            Some(to) => {
+                let moved = Reference {
+                    key: to.to_string(),
+                    field: reference.field,
+                };
+                rewritten.push_str(&format!("{}{}{}", REF_OPEN, moved, REF_CLOSE));
+                changed = true;
+            }
+            None => rewritten.push_str(&rest[before.len()..rest.len() - after.len()]),
+        }
+        rest = after;
+    }
+
+    rewritten.push_str(rest);
+    changed.then_some(rewritten)
+}
+
+/// Whether a reference names one of the old keys of a move
+pub fn refers_to_moved(reference: &Reference, moves: &[(String, String)]) -> bool {
+    moved_to(reference, moves).is_some()
+}
+
+fn resolve_with(
+    value: &str,
+    storage: &Storage,
+    master_key: &MasterKey,
+    stack: &mut Vec<String>,
+) -> Result<String> {
+    let mut resolved = String::new();
+    let mut rest = value;
+
+    while let Some((before, reference, after)) = next_ref(rest) {
+        resolved.push_str(before);
+
+        let node = reference.node();
+        if stack.contains(&node) {
+            let mut cycle = stack.clone();
+            cycle.push(node);
+            return Err(anyhow!("Reference cycle: {}", cycle.join(" → ")));
+        }
+
+        // Only a missing entry makes a broken reference; other failures keep their cause
+        if !storage.entry_exists(&reference.key)? {
+            return Err(anyhow!("Broken reference '{}': entry not found", reference));
+        }
+        let target = storage
+            .load_entry(&reference.key, master_key)
+            .map_err(|e| anyhow!("Cannot resolve reference '{}': {}", reference, e))?;
+        let raw = utils::entry_field(&target, reference.field.as_deref(), master_key)?;
+
+        stack.push(node);
+        resolved.push_str(&resolve_with(&raw, storage, master_key, stack)?);
+        stack.pop();
+
+        rest = after;
+    }
+
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Split off the first reference: (text before it, reference, text after it)
+fn next_ref(value: &str) -> Option<(&str, Reference, &str)> {
+    let start = value.find(REF_OPEN)?;
+    let inner_start = start + REF_OPEN.len();
+    let end = inner_start + value[inner_start..].find(REF_CLOSE)?;
+
+    let inner = value[inner_start..end].trim();
+    let reference = match inner.split_once('#') {
+        Some((key, field)) => Reference {
+            key: key.trim().to_string(),
+            field: Some(field.trim().to_string()),
+        },
+        None => Reference {
+            key: inner.to_string(),
+            field: None,
+        },
+    };
+
+    Some((&value[..start], reference, &value[end + REF_CLOSE.len()..]))
+}
+
+fn node(key: &str, field: Option<&str>) -> String {
+    format!("{}#{}", key, field.unwrap_or("value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{test_entry, test_storage};
+
+    #[test]
+    fn test_parse_references() {
+        let refs = parse("postgres://app:{{ref:infra/db/prod#password}}@{{ref:infra/db/host}}/app");
+        assert_eq!(
+            refs,
+            vec![
+                Reference {
+                    key: "infra/db/prod".to_string(),
+                    field: Some("password".to_string()),
+                },
+                Reference {
+                    key: "infra/db/host".to_string(),
+                    field: None,
+                },
+            ]
+        );
+        assert!(parse("no {{ref:unterminated").is_empty());
+    }
+
+    #[test]
+    fn test_rewrite_moved_references() {
+        let moves = vec![("db/prod".to_string(), "db/primary".to_string())];
+        assert_eq!(
+            rewrite(
+                "{{ref:db//prod#user}}:{{ref: db/prod }}@{{ref:db/host}}",
+                &moves
+            )
+            .as_deref(),
+            Some("{{ref:db/primary#user}}:{{ref:db/primary}}@{{ref:db/host}}")
+        );
+        assert_eq!(rewrite("{{ref:db/production}}", &moves), None);
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let storage = test_storage();
+        let master_key = MasterKey::new(vec![1; 32]);
+        let other_key = MasterKey::new(vec![2; 32]);
+
+        let entry = test_entry("app", b"{{ref:db/gone}}", &master_key);
+        let error = resolve_field(&storage, &entry, None, &master_key).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Broken reference 'db/gone': entry not found"
+        );
+
+        // An entry that exists but cannot be read is not reported as missing
+        storage
+            .store_entry(&test_entry("db/prod", b"secret", &other_key), &other_key)
+            .unwrap();
+        let entry = test_entry("app", b"{{ref:db/prod}}", &master_key);
+        let error = resolve_field(&storage, &entry, None, &master_key).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Cannot resolve reference 'db/prod': ")
+        );
+    }
+}
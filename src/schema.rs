@@ -0,0 +1,509 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::crypto::Crypto;
+use crate::types::{Entry, EntryMetadata, EntryType, FieldValue, MasterKey};
+use crate::utils;
+
+/// A field of a typed entry
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub label: &'static str,
+    /// Encrypted individually and masked in `get` output
+    pub secret: bool,
+    pub required: bool,
+    /// Holds the contents of a file, so a prompted `@path` reads that file
+    pub file: bool,
+    pub validate: Option<fn(&str) -> Result<()>>,
+}
+
+const fn field(name: &'static str, label: &'static str) -> FieldSpec {
+    FieldSpec {
+        name,
+        label,
+        secret: false,
+        required: false,
+        file: false,
+        validate: None,
+    }
+}
+
+const fn secret(name: &'static str, label: &'static str) -> FieldSpec {
+    FieldSpec {
+        secret: true,
+        ..field(name, label)
+    }
+}
+
+// The first field of each schema is the primary one: it is stored as the entry
+// value, so `copy`, `exec` and `env` use it directly.
+
+const CARD_FIELDS: &[FieldSpec] = &[
+    FieldSpec {
+        required: true,
+        validate: Some(validate_card_number),
+        ..secret("number", "Card number")
+    },
+    field("cardholder", "Cardholder"),
+    FieldSpec {
+        required: true,
+        validate: Some(validate_expiry),
+        ..field("expiry", "Expiry (MM/YY)")
+    },
+    FieldSpec {
+        validate: Some(validate_cvv),
+        ..secret("cvv", "CVV")
+    },
+];
+
+const IDENTITY_FIELDS: &[FieldSpec] = &[
+    FieldSpec {
+        required: true,
+        ..field("name", "Full name")
+    },
+    field("email", "Email"),
+    field("phone", "Phone"),
+    field("address", "Address"),
+    secret("national_id", "National ID"),
+    secret("passport", "Passport number"),
+    secret("license", "Driver's license"),
+];
+
+const DATABASE_FIELDS: &[FieldSpec] = &[
+    FieldSpec {
+        required: true,
+        ..secret("password", "Password")
+    },
+    field("engine", "Engine (postgresql, mysql, ...)"),
+    FieldSpec {
+        required: true,
+        ..field("host", "Host")
+    },
+    FieldSpec {
+        validate: Some(validate_port),
+        ..field("port", "Port")
+    },
+    FieldSpec {
+        required: true,
+        ..field("username", "User")
+    },
+    field("database", "Database name"),
+];
+
+const SSH_KEY_FIELDS: &[FieldSpec] = &[
+    FieldSpec {
+        required: true,
+        file: true,
+        validate: Some(validate_private_key),
+        ..secret("private_key", "Private key")
+    },
+    FieldSpec {
+        file: true,
+        validate: Some(validate_public_key),
+        ..field("public_key", "Public key")
+    },
+    secret("passphrase", "Passphrase"),
+];
+
+/// Get the field schema of an entry type, if it has one
+pub fn fields(entry_type: &EntryType) -> Option<&'static [FieldSpec]> {
+    match entry_type {
+        EntryType::Card => Some(CARD_FIELDS),
+        EntryType::Identity => Some(IDENTITY_FIELDS),
+        EntryType::Database => Some(DATABASE_FIELDS),
+        EntryType::SshKey => Some(SSH_KEY_FIELDS),
+        _ => None,
+    }
+}
+
+/// Prompt for every field of a typed entry.
+///
+/// For file fields such as SSH keys, `@path` reads the value from that file;
+/// everywhere else `@` is an ordinary character.
+/// When `current` is given, an empty answer keeps the current value. `primary`
+/// pre-fills the primary field, e.g. from `add --file`.
+pub fn prompt_fields(
+    schema: &[FieldSpec],
+    current: Option<&BTreeMap<String, String>>,
+    primary: Option<String>,
+) -> Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+
+    for (i, spec) in schema.iter().enumerate() {
+        if i == 0
+            && let Some(value) = &primary
+        {
+            values.insert(spec.name.to_string(), value.clone());
+            continue;
+        }
+
+        let existing = current.and_then(|c| c.get(spec.name));
+        let prompt = match existing {
+            Some(_) => format!("{} (empty to keep)", spec.label),
+            None if spec.required => spec.label.to_string(),
+            None => format!("{} (optional)", spec.label),
+        };
+
+        // Ask again until the value passes validation
+        loop {
+            let input = if spec.secret && !spec.file {
+                read_secret(&prompt)?
+            } else {
+                utils::prompt_input_optional(&prompt)?
+            };
+
+            let value = match input {
+                Some(v) if spec.file && v.starts_with('@') => match read_field_file(&v[1..]) {
+                    Ok(contents) => Some(contents),
+                    Err(e) => {
+                        println!("{} {}: {}", "⚠️".yellow(), spec.label, e);
+                        continue;
+                    }
+                },
+                Some(v) => Some(v),
+                None => existing.cloned(),
+            };
+
+            let error = match (&value, spec.validate) {
+                (Some(v), Some(check)) => check(v).err(),
+                (None, _) if spec.required => Some(anyhow!("required")),
+                _ => None,
+            };
+            if let Some(e) = error {
+                println!("{} {}: {}", "⚠️".yellow(), spec.label, e);
+                continue;
+            }
+
+            if let Some(v) = value {
+                values.insert(spec.name.to_string(), v);
+            }
+            break;
+        }
+    }
+
+    validate(schema, &values)?;
+    Ok(values)
+}
+
+/// Validate field values against a schema
+pub fn validate(schema: &[FieldSpec], values: &BTreeMap<String, String>) -> Result<()> {
+    for name in values.keys() {
+        if !schema.iter().any(|spec| spec.name == name) {
+            return Err(anyhow!("Unknown field '{}'", name));
+        }
+    }
+
+    for spec in schema {
+        match values.get(spec.name).filter(|v| !v.trim().is_empty()) {
+            Some(value) => {
+                if let Some(check) = spec.validate {
+                    check(value).map_err(|e| anyhow!("{}: {}", spec.label, e))?;
+                }
+            }
+            None if spec.required => return Err(anyhow!("{} is required", spec.label)),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Store field values on an entry: the primary field becomes the entry value and
+/// the others go to `metadata.fields`, secret ones encrypted individually
+pub fn apply_fields(
+    entry: &mut Entry,
+    schema: &[FieldSpec],
+    values: &BTreeMap<String, String>,
+    master_key: &MasterKey,
+) -> Result<()> {
+    let primary = &schema[0];
+    let primary_value = values.get(primary.name).cloned().unwrap_or_default();
+    entry.value = Crypto::encrypt(primary_value.as_bytes(), master_key)?;
+
+    entry.metadata.fields.clear();
+    for spec in &schema[1..] {
+        let Some(value) = values.get(spec.name).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let field_value = if spec.secret {
+            FieldValue::Secret(Crypto::encrypt(value.as_bytes(), master_key)?)
+        } else {
+            FieldValue::Plain(value.clone())
+        };
+        entry
+            .metadata
+            .fields
+            .insert(spec.name.to_string(), field_value);
+    }
+
+    Ok(())
+}
+
+/// Decrypt all fields of a typed entry, including the primary one
+pub fn read_fields(
+    entry: &Entry,
+    schema: &[FieldSpec],
+    master_key: &MasterKey,
+) -> Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+    values.insert(
+        schema[0].name.to_string(),
+        utils::entry_value(entry, master_key)?,
+    );
+    for (name, value) in &entry.metadata.fields {
+        values.insert(name.clone(), read_field(value, master_key)?);
+    }
+    Ok(values)
+}
+
+/// Get the plaintext of a single field value
+pub fn read_field(value: &FieldValue, master_key: &MasterKey) -> Result<String> {
+    match value {
+        FieldValue::Plain(v) => Ok(v.clone()),
+        FieldValue::Secret(encrypted) => {
+            let decrypted = Crypto::decrypt(encrypted, master_key)?;
+            String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode field: {}", e))
+        }
+    }
+}
+
+/// Print the fields of a typed entry, masking secret ones
+pub fn print_fields(metadata: &EntryMetadata, schema: &[FieldSpec]) {
+    for spec in &schema[1..] {
+        let Some(value) = metadata.fields.get(spec.name) else {
+            continue;
+        };
+        let display = match value {
+            FieldValue::Plain(v) if v.contains('\n') => format!("\n{}", v.trim_end()),
+            FieldValue::Plain(v) => v.clone(),
+            FieldValue::Secret(_) => "********".to_string(),
+        };
+        println!("{}: {}", label(spec), display);
+    }
+
+    if metadata.entry_type == EntryType::Database {
+        println!("Connection: {}", connection_string(metadata).cyan());
+    }
+}
+
+/// Build a connection string for a database entry, with the password masked
+pub fn connection_string(metadata: &EntryMetadata) -> String {
+    let plain = |name: &str| match metadata.fields.get(name) {
+        Some(FieldValue::Plain(v)) => v.clone(),
+        _ => String::new(),
+    };
+
+    let engine = match plain("engine").to_lowercase().as_str() {
+        "" | "postgres" | "postgresql" => "postgresql".to_string(),
+        other => other.to_string(),
+    };
+    let host = plain("host");
+    let host = if host.parse::<std::net::Ipv6Addr>().is_ok() {
+        format!("[{}]", host)
+    } else {
+        percent_encode(&host)
+    };
+    let port = plain("port");
+    let database = plain("database");
+
+    format!(
+        "{}://{}:****@{}{}{}",
+        engine,
+        percent_encode(&plain("username")),
+        host,
+        if port.is_empty() {
+            String::new()
+        } else {
+            format!(":{}", port)
+        },
+        if database.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", percent_encode(&database))
+        }
+    )
+}
+
+/// Escape everything but the characters a URL component may hold as they are
+fn percent_encode(component: &str) -> String {
+    let mut encoded = String::new();
+    for byte in component.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Check a card number with the Luhn algorithm
+pub fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+
+    if !(12..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+fn validate_card_number(value: &str) -> Result<()> {
+    if luhn_valid(value) {
+        Ok(())
+    } else {
+        Err(anyhow!("not a valid card number (Luhn check failed)"))
+    }
+}
+
+fn validate_expiry(value: &str) -> Result<()> {
+    let (month, year) = value
+        .split_once('/')
+        .ok_or_else(|| anyhow!("expected MM/YY"))?;
+    let month: u32 = month.trim().parse().map_err(|_| anyhow!("invalid month"))?;
+    let year = year.trim();
+    if !(1..=12).contains(&month) {
+        return Err(anyhow!("month must be between 01 and 12"));
+    }
+    if !(year.len() == 2 || year.len() == 4) || !year.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("expected MM/YY or MM/YYYY"));
+    }
+    Ok(())
+}
+
+fn validate_cvv(value: &str) -> Result<()> {
+    if (3..=4).contains(&value.len()) && value.chars().all(|c| c.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(anyhow!("must be 3 or 4 digits"))
+    }
+}
+
+fn validate_port(value: &str) -> Result<()> {
+    match value.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err(anyhow!("must be a number between 1 and 65535")),
+    }
+}
+
+fn validate_private_key(value: &str) -> Result<()> {
+    if value.contains("PRIVATE KEY-----") {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "expected a PEM/OpenSSH private key (use @path to read a file)"
+        ))
+    }
+}
+
+fn validate_public_key(value: &str) -> Result<()> {
+    let algorithm = value.split_whitespace().next().unwrap_or_default();
+    if algorithm.starts_with("ssh-")
+        || algorithm.starts_with("ecdsa-")
+        || algorithm.starts_with("sk-")
+    {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "expected an OpenSSH public key (ssh-ed25519 AAAA...)"
+        ))
+    }
+}
+
+fn label(spec: &FieldSpec) -> &'static str {
+    // Strip format hints such as "(MM/YY)" from the label
+    spec.label
+        .split_once(" (")
+        .map(|(label, _)| label)
+        .unwrap_or(spec.label)
+}
+
+fn read_secret(prompt: &str) -> Result<Option<String>> {
+    let value = dialoguer::Password::new()
+        .with_prompt(prompt)
+        .allow_empty_password(true)
+        .interact()
+        .map_err(|e| anyhow!("Failed to read input: {}", e))?;
+    Ok(if value.is_empty() { None } else { Some(value) })
+}
+
+fn read_field_file(path: &str) -> Result<String> {
+    let path = expand_home(path);
+    fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_validation() {
+        assert!(luhn_valid("4111 1111 1111 1111"));
+        assert!(luhn_valid("5555-5555-5555-4444"));
+        assert!(!luhn_valid("4111 1111 1111 1112"));
+        assert!(!luhn_valid("4111"));
+        assert!(validate_expiry("09/27").is_ok());
+        assert!(validate_expiry("13/27").is_err());
+        assert!(validate_cvv("123").is_ok());
+        assert!(validate_cvv("12a").is_err());
+
+        let mut values = BTreeMap::new();
+        values.insert("number".to_string(), "4111111111111111".to_string());
+        assert!(validate(CARD_FIELDS, &values).is_err()); // expiry missing
+        values.insert("expiry".to_string(), "09/27".to_string());
+        assert!(validate(CARD_FIELDS, &values).is_ok());
+    }
+
+    #[test]
+    fn test_connection_string() {
+        let mut metadata = EntryMetadata::default();
+        for (name, value) in [
+            ("engine", "postgres"),
+            ("username", "app@corp"),
+            ("host", "db/primary"),
+            ("port", "5432"),
+            ("database", "my db"),
+        ] {
+            metadata
+                .fields
+                .insert(name.to_string(), FieldValue::Plain(value.to_string()));
+        }
+        assert_eq!(
+            connection_string(&metadata),
+            "postgresql://app%40corp:****@db%2Fprimary:5432/my%20db"
+        );
+
+        metadata
+            .fields
+            .insert("host".to_string(), FieldValue::Plain("::1".to_string()));
+        assert!(connection_string(&metadata).contains("@[::1]:5432/"));
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Result, anyhow};
+use regex::Regex;
+
+use crate::storage::Storage;
+
+/// Whether a key pattern uses glob syntax
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Match a key against a glob. `*` and `?` stay within one path segment,
+/// `**` spans any number of segments.
+pub fn glob_match(pattern: &str, key: &str) -> Result<bool> {
+    Ok(glob_regex(pattern)?.is_match(key))
+}
+
+/// Select keys by exact name, by glob, or by path prefix when recursive
+pub fn select(keys: &[String], pattern: &str, recursive: bool) -> Result<Vec<String>> {
+    if is_glob(pattern) {
+        let regex = glob_regex(pattern)?;
+        return Ok(keys.iter().filter(|k| regex.is_match(k)).cloned().collect());
+    }
+
+    let trimmed = pattern.trim_end_matches('/');
+    if recursive {
+        let prefix = format!("{}/", trimmed);
+        return Ok(keys
+            .iter()
+            .filter(|k| *k == trimmed || k.starts_with(&prefix))
+            .cloned()
+            .collect());
+    }
+
+    Ok(keys.iter().filter(|k| *k == trimmed).cloned().collect())
+}
+
+/// Split a `vault:key` address into its vault and key.
+///
+/// The part before the first `:` names a vault only when a vault of that name
+/// exists, so keys such as `smtp:587` are left alone. A leading `:` addresses
+/// the current vault explicitly: `:work:db` is the key `work:db`.
+pub fn split_vault(address: &str) -> (Option<&str>, &str) {
+    match address.split_once(':') {
+        Some(("", key)) => (None, key),
+        Some((vault, key)) if !vault.contains('/') && vault_exists(vault) => (Some(vault), key),
+        _ => (None, address),
+    }
+}
+
+fn vault_exists(name: &str) -> bool {
+    Storage::new(Some(name.to_string())).is_ok_and(|storage| storage.vault_exists())
+}
+
+/// Select entries and aliases of a vault, failing when nothing matches
+pub fn select_keys(storage: &Storage, pattern: &str, recursive: bool) -> Result<Vec<String>> {
+    let mut keys = storage.list_entries()?;
+    keys.extend(storage.list_aliases()?.into_iter().map(|(alias, _)| alias));
+    keys.sort();
+
+    let selected = select(&keys, pattern, recursive)?;
+    if selected.is_empty() {
+        return Err(if is_glob(pattern) || recursive {
+            anyhow!("No entries match '{}'", pattern)
+        } else {
+            anyhow!("Entry '{}' not found", pattern)
+        });
+    }
+    Ok(selected)
+}
+
+/// Map the keys selected by a pattern onto a destination.
+///
+/// Keys keep their path below the pattern's literal directory, so moving
+/// `old-team/**` to `new-team/` turns `old-team/db/prod` into `new-team/db/prod`.
+/// A single exact key is renamed to `to`, or placed inside it when `to` ends in `/`.
+pub fn relocate(
+    keys: &[String],
+    pattern: &str,
+    recursive: bool,
+    to: &str,
+) -> Vec<(String, String)> {
+    let single = !is_glob(pattern) && !recursive;
+    if single && !to.ends_with('/') {
+        return keys.iter().map(|k| (k.clone(), to.to_string())).collect();
+    }
+
+    let base = if single || is_glob(pattern) {
+        let literal = &pattern[..pattern.find(['*', '?']).unwrap_or(pattern.len())];
+        &literal[..literal.rfind('/').map_or(0, |i| i + 1)]
+    } else {
+        pattern.trim_end_matches('/')
+    };
+    let to = to.trim_end_matches('/');
+
+    keys.iter()
+        .map(|key| {
+            let rest = key
+                .strip_prefix(base)
+                .map(|r| r.trim_start_matches('/'))
+                .filter(|r| !r.is_empty())
+                .unwrap_or_else(|| key.rsplit('/').next().unwrap_or(key));
+            let dest = if to.is_empty() {
+                rest.to_string()
+            } else {
+                format!("{}/{}", to, rest)
+            };
+            (key.clone(), dest)
+        })
+        .collect()
+}
+
+fn glob_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` may also match no directories at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_and_relocate() {
+        let keys: Vec<String> = ["old-team/db/prod", "old-team/web", "old-teams/x", "other"]
+            .iter()
+            .map(|k| k.to_string())
+            .collect();
+
+        assert!(glob_match("old-team/*", "old-team/web").unwrap());
+        assert!(!glob_match("old-team/*", "old-team/db/prod").unwrap());
+        assert!(glob_match("**/prod", "old-team/db/prod").unwrap());
+        assert_eq!(select(&keys, "old-team/**", false).unwrap().len(), 2);
+        assert_eq!(select(&keys, "old-team/", true).unwrap().len(), 2);
+        assert_eq!(select(&keys, "old-team", false).unwrap().len(), 0);
+
+        let moves = relocate(
+            &select(&keys, "old-team/**", false).unwrap(),
+            "old-team/**",
+            false,
+            "new-team/",
+        );
+        assert_eq!(moves[0].1, "new-team/db/prod");
+        assert_eq!(
+            relocate(&keys[3..], "other", false, "archive/")[0].1,
+            "archive/other"
+        );
+        assert_eq!(
+            relocate(&keys[3..], "other", false, "renamed")[0].1,
+            "renamed"
+        );
+        assert_eq!(
+            relocate(&keys[..2], "old-team", true, "new")[1].1,
+            "new/web"
+        );
+    }
+}
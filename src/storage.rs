@@ -1,17 +1,155 @@
+use crate::backend::{
+    CONTAINER_EXTENSION, DirBackend, FileBackend, KeySource, MemoryBackend, Record, VaultBackend,
+    check_blob_id,
+};
 use crate::crypto::Crypto;
-use crate::types::{EncryptedValue, Entry, EntryMetadata, MasterKey, Session, VaultConfig};
+use crate::home;
+use crate::index::{IndexedEntry, SearchIndex};
+use crate::key::{self, EntryKey};
+use crate::types::{
+    Alias, EncryptedValue, Entry, EntryMetadata, FieldValue, MasterKey, Session, TrashedEntry,
+    VaultConfig,
+};
 use anyhow::{Result, anyhow};
 use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
-use chrono::Utc;
-use git2;
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use uuid::Uuid;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+/// How long a command waits for another process to release a vault
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Directory of a project vault, at the root of the project
+pub const PROJECT_DIR: &str = ".bunker";
+
+/// Fewest entries worth handing to a thread of their own in `load_entries`
+const BULK_LOAD_BATCH: usize = 16;
+
+/// Vaults locked by this process, so a command can lock a vault it already holds
+static HELD_LOCKS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Advisory lock on a vault, held for the whole of a mutating command and
+/// released on drop
+pub struct VaultLock {
+    path: PathBuf,
+    /// None when this process already held the lock
+    file: Option<fs::File>,
+}
+
+impl Drop for VaultLock {
+    fn drop(&mut self) {
+        if self.file.take().is_some()
+            && let Ok(mut held) = HELD_LOCKS.lock()
+        {
+            held.retain(|p| p != &self.path);
+        }
+    }
+}
+
+/// Replace a file so it is either fully written or left untouched: the data goes
+/// to a temporary file next to it, is flushed to disk, then renamed into place
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref();
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid path {}", path.display()))?;
+
+    // Named like the *.tmp files the vault's .gitignore skips
+    let mut temp = tempfile::Builder::new()
+        .prefix(".bunker-")
+        .suffix(".tmp")
+        .tempfile_in(dir)?;
+    temp.write_all(contents.as_ref())?;
+    temp.as_file().sync_all()?;
+    temp.persist(path)
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e.error))?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    fs::File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Mark a trashed key the way listings and searches show it
+pub fn trash_label(key: String, trashed: bool) -> String {
+    if trashed {
+        format!("{} (trashed)", key)
+    } else {
+        key
+    }
+}
+
+/// Version of the export format; 1.0 exports carry entries only
+const EXPORT_VERSION: &str = "2.0";
+
+/// Decrypted payload of an export file, every record in stored form
+#[derive(Serialize, Deserialize)]
+struct ExportPayload {
+    version: String,
+    vault_config: VaultConfig,
+    #[serde(default)]
+    entries: BTreeMap<String, String>,
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    trash: BTreeMap<String, String>,
+    /// Encrypted attachment blobs, base64-encoded
+    #[serde(default)]
+    attachments: BTreeMap<String, String>,
+    exported_at: Option<DateTime<Utc>>,
+}
+
+impl ExportPayload {
+    /// Check every attachment blob id, both the stored blobs and those the
+    /// entries point to, before any of them is written
+    fn check_blob_ids(&self) -> Result<()> {
+        for id in self.attachments.keys() {
+            check_blob_id(id)?;
+        }
+        for data in self.entries.values() {
+            check_attachments(&serde_json::from_str::<Entry>(data)?.metadata)?;
+        }
+        for data in self.trash.values() {
+            check_attachments(&serde_json::from_str::<TrashedEntry>(data)?.entry.metadata)?;
+        }
+        Ok(())
+    }
+}
+
+/// Indexed entries as (key, trashed, metadata)
+type IndexedEntries = Vec<(String, bool, IndexedEntry)>;
+
+/// Normalize a key before handing it to the backend
+fn normalize(key: &str) -> Result<String> {
+    Ok(EntryKey::parse(key)?.into_string())
+}
+
+/// Check the blob ids of an entry's attachments, which are read from the
+/// entry's own JSON and so may have been tampered with
+fn check_attachments(metadata: &EntryMetadata) -> Result<()> {
+    for attachment in &metadata.attachments {
+        check_blob_id(&attachment.blob)?;
+    }
+    Ok(())
+}
 
 pub struct Storage {
     vault_path: PathBuf,
     vault_name: String,
+    backend: Box<dyn VaultBackend>,
+    single_file: bool,
+    /// Name of the vault's session file, unique even where vault names are not
+    session_name: String,
+    /// Search index, once read
+    index: Mutex<Option<SearchIndex>>,
 }
 
 impl Storage {
@@ -20,6 +158,11 @@ impl Storage {
         let vault_name = match vault_name {
             Some(name) => name,
             None => {
+                // A project's own vault wins over the configured default
+                if let Some(path) = Self::find_project_vault(&std::env::current_dir()?) {
+                    return Ok(Self::project(path));
+                }
+
                 // Load from config, fallback to "default"
                 match crate::config::Config::load() {
                     Ok(config) => config.default_vault,
@@ -27,21 +170,132 @@ impl Storage {
                 }
             }
         };
+
+        // A path to a single-file vault, e.g. on a USB stick
+        if let Some(path) = Self::container_path(&vault_name) {
+            return Ok(Self::single_file(path));
+        }
+
         let vault_path = Self::vault_path(&vault_name)?;
+        let container = Self::vault_file(&vault_name)?;
+        if !vault_path.is_dir() && container.is_file() {
+            return Ok(Self::single_file(container));
+        }
 
         Ok(Self {
+            backend: Box::new(DirBackend::new(vault_path.clone())),
             vault_path,
+            session_name: vault_name.clone(),
             vault_name,
+            single_file: false,
+            index: Mutex::new(None),
         })
     }
 
+    /// Storage for a project vault, the `.bunker` directory at `path`, named
+    /// after the project directory
+    pub fn project(path: PathBuf) -> Self {
+        let vault_name = path
+            .parent()
+            .and_then(|project| project.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        // Two projects, or a project and a named vault, may share a name
+        let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+        let session_name = format!("{}-{}", vault_name, &hex::encode(digest)[..12]);
+        Self {
+            backend: Box::new(DirBackend::new(path.clone())),
+            vault_path: path,
+            vault_name,
+            single_file: false,
+            session_name,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// Find the project vault for a directory: a `.bunker` directory holding a
+    /// vault in it or the nearest of its parents, the way git finds `.git`
+    pub fn find_project_vault(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_DIR))
+            .find(|candidate| candidate.join(".vault").is_file())
+    }
+
+    /// Storage for a single-file vault, named after the file
+    pub fn single_file(path: PathBuf) -> Self {
+        let vault_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // Files on two sticks, or a file and a named vault, may share a stem
+        let digest = Sha256::digest(Self::canonical_path(&path).to_string_lossy().as_bytes());
+        let session_name = format!("file-{}", &hex::encode(digest)[..16]);
+        let key_session = session_name.clone();
+        let key_source: KeySource =
+            Box::new(move |config| Self::session_master_key(&key_session, config).ok());
+        Self {
+            backend: Box::new(FileBackend::new(path.clone(), key_source)),
+            vault_path: path,
+            session_name,
+            vault_name,
+            single_file: true,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// Absolute form of a path with symlinks resolved, so every spelling of a
+    /// file names the same session; a file not created yet is resolved through
+    /// its directory
+    fn canonical_path(path: &Path) -> PathBuf {
+        if let Ok(canonical) = fs::canonicalize(path) {
+            return canonical;
+        }
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        match (absolute.parent(), absolute.file_name()) {
+            (Some(dir), Some(name)) => fs::canonicalize(dir)
+                .map(|dir| dir.join(name))
+                .unwrap_or(absolute.clone()),
+            _ => absolute,
+        }
+    }
+
+    /// Storage that lives only in memory, for tests
+    #[cfg(test)]
+    pub fn in_memory(vault_name: &str) -> Self {
+        Self {
+            vault_path: PathBuf::new(),
+            vault_name: vault_name.to_string(),
+            backend: Box::new(MemoryBackend::default()),
+            single_file: false,
+            session_name: vault_name.to_string(),
+            index: Mutex::new(None),
+        }
+    }
+
+    /// The file a vault name refers to when it is a path ending in `.bvault`
+    fn container_path(vault_name: &str) -> Option<PathBuf> {
+        let path = Path::new(vault_name);
+        let is_container = path
+            .extension()
+            .is_some_and(|ext| ext == CONTAINER_EXTENSION);
+        (is_container && (path.components().count() > 1 || path.is_file())).then(|| path.into())
+    }
+
     /// Get vault base path
     pub fn vault_path(vault_name: &str) -> Result<PathBuf> {
         let base_dir = Self::base_dir()?;
         Ok(base_dir.join("vaults").join(vault_name))
     }
 
-    /// Get this vault's path
+    /// Path of a named single-file vault
+    pub fn vault_file(vault_name: &str) -> Result<PathBuf> {
+        Ok(Self::base_dir()?
+            .join("vaults")
+            .join(format!("{}.{}", vault_name, CONTAINER_EXTENSION)))
+    }
+
+    /// Get this vault's path: its directory, or its file for a single-file vault
     pub fn get_vault_path(&self) -> &PathBuf {
         &self.vault_path
     }
@@ -51,56 +305,178 @@ impl Storage {
         &self.vault_name
     }
 
-    /// Get base directory for bunker
+    /// Whether the vault is a single file rather than a directory
+    pub fn is_single_file(&self) -> bool {
+        self.single_file
+    }
+
+    /// Get base directory for bunker, which holds vaults, sessions and backups
     pub fn base_dir() -> Result<PathBuf> {
-        let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
-        Ok(home.join(".bunker"))
+        Ok(home::locate()?.data)
     }
 
     /// Initialize a new vault
     pub fn init_vault(&self, config: VaultConfig) -> Result<()> {
-        // Create directory structure
-        fs::create_dir_all(&self.vault_path)?;
-        fs::create_dir_all(self.vault_path.join("store"))?;
-
-        // Save config
-        let config_path = self.vault_path.join(".vault");
-        let config_json = serde_json::to_string_pretty(&config)?;
-        fs::write(config_path, config_json)?;
+        self.backend.create(&config)?;
 
         // Initialize git if needed
-        if config.git_remote.is_some() {
+        if config.git_remote.is_some() && !self.single_file {
             git2::Repository::init(&self.vault_path)?;
         }
 
         Ok(())
     }
 
+    /// Give the vault its master key. Single-file vaults are encrypted as a
+    /// whole and check the key here; other vaults accept any key.
+    pub fn unlock(&self, key: &MasterKey) -> Result<()> {
+        self.backend.unlock(key)
+    }
+
+    /// Delete the vault and everything in it
+    pub fn destroy_vault(&self) -> Result<()> {
+        self.backend.destroy()
+    }
+
+    /// Take the vault's advisory lock, waiting up to `BUNKER_LOCK_TIMEOUT`
+    /// seconds (10 by default) for another bunker process to finish
+    pub fn lock(&self) -> Result<VaultLock> {
+        let timeout = std::env::var("BUNKER_LOCK_TIMEOUT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(LOCK_TIMEOUT);
+        self.lock_within(timeout)?.ok_or_else(|| {
+            anyhow!(
+                "Vault '{}' is busy: another bunker process is using it (waited {}s)",
+                self.vault_name,
+                timeout.as_secs()
+            )
+        })
+    }
+
+    /// Take the vault's lock only if no other process holds it
+    pub fn try_lock(&self) -> Result<Option<VaultLock>> {
+        self.lock_within(Duration::ZERO)
+    }
+
+    /// Take the vault's lock, or None if it is still busy after `timeout`.
+    /// Whatever was read before is read again under the lock.
+    fn lock_within(&self, timeout: Duration) -> Result<Option<VaultLock>> {
+        let Some(path) = self.backend.lock_path() else {
+            return Ok(Some(VaultLock {
+                path: PathBuf::new(),
+                file: None,
+            }));
+        };
+        let mut held = HELD_LOCKS
+            .lock()
+            .map_err(|_| anyhow!("Vault lock registry poisoned"))?;
+        if held.contains(&path) {
+            return Ok(Some(VaultLock { path, file: None }));
+        }
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        let started = Instant::now();
+        let mut waiting = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(fs::TryLockError::WouldBlock) => {
+                    if started.elapsed() >= timeout {
+                        return Ok(None);
+                    }
+                    if !waiting {
+                        eprintln!(
+                            "Waiting for another bunker process to release vault '{}'...",
+                            self.vault_name
+                        );
+                        waiting = true;
+                    }
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(fs::TryLockError::Error(e)) => {
+                    return Err(anyhow!("Failed to lock vault '{}': {}", self.vault_name, e));
+                }
+            }
+        }
+
+        held.push(path.clone());
+        let lock = VaultLock {
+            path,
+            file: Some(file),
+        };
+        drop(held);
+
+        // Another process may have changed the vault while we waited
+        self.backend.reload()?;
+        if let Ok(mut cached) = self.index.try_lock() {
+            *cached = None;
+        }
+        Ok(Some(lock))
+    }
+
+    /// Whether this process holds the vault's lock
+    fn holds_lock(&self) -> Result<bool> {
+        let Some(path) = self.backend.lock_path() else {
+            return Ok(true);
+        };
+        let held = HELD_LOCKS
+            .lock()
+            .map_err(|_| anyhow!("Vault lock registry poisoned"))?;
+        Ok(held.contains(&path))
+    }
+
     /// Check if vault exists
     pub fn vault_exists(&self) -> bool {
-        self.vault_path.exists() && self.vault_path.join(".vault").exists()
+        self.backend.exists()
     }
 
     /// Check if entry exists
     pub fn entry_exists(&self, key: &str) -> Result<bool> {
-        let entry_path = self.entry_path(key);
-        Ok(entry_path.exists())
+        self.backend
+            .contains(Record::Entry, &self.resolve_key(key)?)
+    }
+
+    /// Whether an entry is stored under the key itself, rather than reached
+    /// through an alias
+    pub fn entry_stored(&self, key: &str) -> Result<bool> {
+        self.backend.contains(Record::Entry, &normalize(key)?)
     }
 
     /// Load vault configuration
     pub fn load_config(&self) -> Result<VaultConfig> {
-        let config_path = self.vault_path.join(".vault");
-        let config_data = fs::read_to_string(config_path)?;
-        let config: VaultConfig = serde_json::from_str(&config_data)?;
-        Ok(config)
+        self.backend.load_config()
     }
 
     /// Save vault configuration
     pub fn save_config(&self, config: &VaultConfig) -> Result<()> {
-        let config_path = self.vault_path.join(".vault");
-        let config_json = serde_json::to_string_pretty(config)?;
-        fs::write(config_path, config_json)?;
-        Ok(())
+        self.backend.save_config(config)
+    }
+
+    /// Make several changes as one, so a single-file vault is written once.
+    /// If `f` fails, a single-file vault keeps none of its changes.
+    pub fn batch<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let mut f = Some(f);
+        let mut output = None;
+        let result = self.backend.batch(&mut || {
+            let f = f.take().ok_or_else(|| anyhow!("Batch already ran"))?;
+            output = Some(f()?);
+            Ok(())
+        });
+        if result.is_err()
+            && let Ok(mut cached) = self.index.try_lock()
+        {
+            // It may hold changes that were not saved
+            *cached = None;
+        }
+        result?;
+        output.ok_or_else(|| anyhow!("Batch did not run"))
     }
 
     /// Store an entry
@@ -109,35 +485,92 @@ impl Storage {
         let value_json = serde_json::to_vec(&entry.value)?;
         let encrypted_value = Crypto::encrypt(&value_json, key)?;
 
-        // Create entry with encrypted value
+        // Create entry with encrypted value, under its normalized key
         let stored_entry = Entry {
+            key: EntryKey::parse(&entry.key)?.into_string(),
             value: encrypted_value,
             ..entry.clone()
         };
 
-        // Store in filesystem
-        let entry_path = self.entry_path(&entry.key);
-        if let Some(parent) = entry_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-
         let entry_json = serde_json::to_string_pretty(&stored_entry)?;
-        fs::write(entry_path, entry_json)?;
-
-        Ok(())
+        self.batch(|| {
+            // A real entry replaces an alias of the same name
+            self.backend.remove(Record::Alias, &stored_entry.key)?;
+            self.backend
+                .write(Record::Entry, &stored_entry.key, &entry_json)?;
+            self.update_index(|index| {
+                index.insert(&stored_entry.key, &stored_entry.metadata, false, key)
+            })
+        })
     }
 
     /// Load an entry
     pub fn load_entry(&self, key: &str, master_key: &MasterKey) -> Result<Entry> {
-        let entry_path = self.entry_path(key);
-        if !entry_path.exists() {
-            return Err(anyhow!("Entry '{}' not found", key));
+        let chain = self.resolve_chain(key)?;
+        let resolved = chain.last().map_or(key, String::as_str);
+        let Some(entry) = self.read_stored(resolved)? else {
+            // The chain holds more than the key itself only when an alias was followed
+            if let [alias, .., _] = chain.as_slice() {
+                return Err(anyhow!(
+                    "Alias '{}' points to missing entry '{}'",
+                    alias,
+                    resolved
+                ));
+            }
+            return Err(anyhow!("Entry '{}' not found", resolved));
+        };
+
+        Self::decrypt_stored(entry, master_key)
+    }
+
+    /// Load and decrypt many entries at once, spread over a few threads.
+    ///
+    /// Results come back in the order of `keys`, each with its own error, so a
+    /// bulk command can decide whether one unreadable entry should stop it.
+    pub fn load_entries(
+        &self,
+        keys: &[String],
+        master_key: &MasterKey,
+    ) -> Vec<(String, Result<Entry>)> {
+        let load = |key: &String| {
+            let entry = self
+                .load_entry(key, master_key)
+                .map_err(|e| anyhow!("Cannot read entry '{}': {}", key, e));
+            (key.clone(), entry)
+        };
+
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(keys.len().div_ceil(BULK_LOAD_BATCH));
+        if threads <= 1 {
+            return keys.iter().map(load).collect();
         }
 
-        let entry_data = fs::read_to_string(entry_path)?;
-        let mut entry: Entry = serde_json::from_str(&entry_data)?;
+        let chunk_size = keys.len().div_ceil(threads);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = keys
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || chunk.iter().map(load).collect::<Vec<_>>()))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("entry loader panicked"))
+                .collect()
+        })
+    }
+
+    /// Read an entry as stored, with its value still encrypted
+    fn read_stored(&self, key: &str) -> Result<Option<Entry>> {
+        let Some(data) = self.backend.read(Record::Entry, &normalize(key)?)? else {
+            return Ok(None);
+        };
+        let entry: Entry = serde_json::from_str(&data)?;
+        check_attachments(&entry.metadata)?;
+        Ok(Some(entry))
+    }
 
-        // Decrypt the value
+    /// Undo the outer encryption applied by `store_entry`
+    fn decrypt_stored(mut entry: Entry, master_key: &MasterKey) -> Result<Entry> {
         let decrypted_value = Crypto::decrypt(&entry.value, master_key)?;
         let value: EncryptedValue = serde_json::from_slice(&decrypted_value)?;
         entry.value = value;
@@ -145,141 +578,527 @@ impl Storage {
         Ok(entry)
     }
 
+    /// Load an entry's metadata without decrypting anything
+    pub fn load_metadata(&self, key: &str) -> Result<EntryMetadata> {
+        match self.read_stored(&self.resolve_key(key)?)? {
+            Some(entry) => Ok(entry.metadata),
+            None => Err(anyhow!("Entry '{}' not found", key)),
+        }
+    }
+
     /// Delete an entry
     pub fn delete_entry(&self, key: &str) -> Result<()> {
-        let entry_path = self.entry_path(key);
-        let entry_path_clone = entry_path.clone();
-        if !entry_path.exists() {
-            return Err(anyhow!("Entry '{}' not found", key));
+        self.batch(|| {
+            let key = normalize(key)?;
+
+            // Attachment metadata is readable without the master key
+            let Some(entry) = self.read_stored(&key)? else {
+                // Removing an alias leaves its target alone
+                if self.backend.remove(Record::Alias, &key)? {
+                    return Ok(());
+                }
+                return Err(anyhow!("Entry '{}' not found", key));
+            };
+
+            self.backend.remove(Record::Entry, &key)?;
+            self.update_index(|index| {
+                index.remove(&key, false);
+                Ok(())
+            })?;
+
+            for attachment in &entry.metadata.attachments {
+                self.delete_attachment_if_unused(&attachment.blob)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Move an entry to the trash, replacing an older trashed copy of the same key
+    pub fn trash_entry(&self, key: &str) -> Result<()> {
+        self.batch(|| {
+            let key = normalize(key)?;
+            let Some(entry) = self.read_stored(&key)? else {
+                return Err(anyhow!("Entry '{}' not found", key));
+            };
+
+            let replaced = if self.backend.contains(Record::Trashed, &key)? {
+                Some(self.load_trashed(&key)?)
+            } else {
+                None
+            };
+            let trashed = TrashedEntry {
+                deleted_at: Utc::now(),
+                entry,
+            };
+            self.backend.write(
+                Record::Trashed,
+                &key,
+                &serde_json::to_string_pretty(&trashed)?,
+            )?;
+            self.backend.remove(Record::Entry, &key)?;
+            self.update_index(|index| {
+                index.transfer(&key, true);
+                Ok(())
+            })?;
+
+            if let Some(old) = replaced {
+                for attachment in &old.entry.metadata.attachments {
+                    self.delete_attachment_if_unused(&attachment.blob)?;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Read a trashed entry without decrypting anything
+    pub fn load_trashed(&self, key: &str) -> Result<TrashedEntry> {
+        let Some(data) = self.backend.read(Record::Trashed, &normalize(key)?)? else {
+            return Err(anyhow!("'{}' is not in the trash", key));
+        };
+        let trashed: TrashedEntry = serde_json::from_str(&data)?;
+        check_attachments(&trashed.entry.metadata)?;
+        Ok(trashed)
+    }
+
+    /// List trashed entries with their deletion time
+    pub fn list_trash(&self) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let mut trashed = Vec::new();
+        for key in self.backend.list(Record::Trashed)? {
+            let deleted_at = self.load_trashed(&key)?.deleted_at;
+            trashed.push((key, deleted_at));
         }
+        Ok(trashed)
+    }
+
+    /// Move a trashed entry back into the store
+    pub fn restore_from_trash(&self, key: &str) -> Result<()> {
+        self.batch(|| {
+            let key = normalize(key)?;
+            let trashed = self.load_trashed(&key)?;
+            if self.backend.contains(Record::Entry, &key)?
+                || self.backend.contains(Record::Alias, &key)?
+            {
+                return Err(anyhow!(
+                    "Entry '{}' already exists; move it away before restoring",
+                    key
+                ));
+            }
+
+            self.backend.write(
+                Record::Entry,
+                &key,
+                &serde_json::to_string_pretty(&trashed.entry)?,
+            )?;
+            self.backend.remove(Record::Trashed, &key)?;
+            self.update_index(|index| {
+                index.transfer(&key, false);
+                Ok(())
+            })
+        })
+    }
+
+    /// Permanently delete a trashed entry and any attachments only it used
+    pub fn purge_trashed(&self, key: &str) -> Result<()> {
+        self.batch(|| {
+            let trashed = self.load_trashed(key)?;
+            let key = normalize(key)?;
+            self.backend.remove(Record::Trashed, &key)?;
+            self.update_index(|index| {
+                index.remove(&key, true);
+                Ok(())
+            })?;
+
+            for attachment in &trashed.entry.metadata.attachments {
+                self.delete_attachment_if_unused(&attachment.blob)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Purge entries that have been in the trash longer than the retention period
+    pub fn purge_expired_trash(&self, retention_days: u32) -> Result<Vec<String>> {
+        self.batch(|| {
+            if retention_days == 0 {
+                return Ok(Vec::new());
+            }
+
+            let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+            let mut purged = Vec::new();
+            for (key, deleted_at) in self.list_trash()? {
+                if deleted_at < cutoff {
+                    self.purge_trashed(&key)?;
+                    purged.push(key);
+                }
+            }
+            Ok(purged)
+        })
+    }
+
+    /// Create or replace an alias pointing at another key
+    pub fn store_alias(&self, alias: &str, target: &str) -> Result<()> {
+        let link = Alias {
+            target: normalize(target)?,
+            created_at: Utc::now(),
+        };
+        self.backend.write(
+            Record::Alias,
+            &normalize(alias)?,
+            &serde_json::to_string_pretty(&link)?,
+        )
+    }
 
-        fs::remove_file(entry_path)?;
+    /// Get the target of an alias, or None if the key is not an alias
+    pub fn read_alias(&self, key: &str) -> Result<Option<String>> {
+        match self.backend.read(Record::Alias, &normalize(key)?)? {
+            Some(data) => {
+                let link: Alias = serde_json::from_str(&data)?;
+                Ok(Some(link.target))
+            }
+            None => Ok(None),
+        }
+    }
 
-        // Clean up empty directories
-        let mut parent = entry_path_clone.parent();
-        while let Some(dir) = parent {
-            if dir == self.vault_path.join("store") {
+    /// Follow aliases from a key, returning every key visited (the last one is the entry)
+    pub fn resolve_chain(&self, key: &str) -> Result<Vec<String>> {
+        let key = normalize(key)?;
+        let mut chain = vec![key.clone()];
+        let mut current = key;
+        while !self.backend.contains(Record::Entry, &current)? {
+            let Some(target) = self.read_alias(&current)? else {
                 break;
+            };
+            if chain.contains(&target) {
+                chain.push(target);
+                return Err(anyhow!("Alias loop: {}", chain.join(" → ")));
             }
-            if fs::read_dir(dir)?.next().is_none() {
-                fs::remove_dir(dir)?;
+            chain.push(target.clone());
+            current = target;
+        }
+        Ok(chain)
+    }
+
+    /// Follow aliases to the key of the entry they point at
+    pub fn resolve_key(&self, key: &str) -> Result<String> {
+        let mut chain = self.resolve_chain(key)?;
+        Ok(chain.pop().unwrap_or_else(|| key.to_string()))
+    }
+
+    /// List all aliases as (alias, target) pairs
+    pub fn list_aliases(&self) -> Result<Vec<(String, String)>> {
+        let mut aliases = Vec::new();
+        for key in self.backend.list(Record::Alias)? {
+            if let Some(target) = self.read_alias(&key)? {
+                aliases.push((key, target));
             }
-            parent = dir.parent();
         }
+        Ok(aliases)
+    }
 
-        Ok(())
+    /// Point the aliases of one key at another, returning how many were updated
+    pub fn retarget_aliases(&self, from: &str, to: &str) -> Result<usize> {
+        self.batch(|| {
+            let mut updated = 0;
+            for (alias, target) in self.list_aliases()? {
+                if target == from {
+                    self.store_alias(&alias, to)?;
+                    updated += 1;
+                }
+            }
+            Ok(updated)
+        })
     }
 
-    /// List all entries
-    pub fn list_entries(&self) -> Result<Vec<String>> {
-        let store_path = self.vault_path.join("store");
-        if !store_path.exists() {
-            return Ok(Vec::new());
+    /// Encrypt a file into a content-addressed blob, returning its id and size.
+    /// Identical files are stored once.
+    pub fn store_attachment(&self, source: &Path, key: &MasterKey) -> Result<(String, u64)> {
+        let open = || {
+            fs::File::open(source)
+                .map_err(|e| anyhow!("Failed to open {}: {}", source.display(), e))
+        };
+        let size = open()?.metadata()?.len();
+        let id = self.store_blob(|| Ok(io::BufReader::new(open()?)), key)?;
+        Ok((id, size))
+    }
+
+    /// Re-encrypt an attachment blob of another vault into this one, returning its new id
+    pub fn copy_attachment(
+        &self,
+        source: &Storage,
+        id: &str,
+        source_key: &MasterKey,
+        key: &MasterKey,
+    ) -> Result<String> {
+        let mut data = Vec::new();
+        source.read_attachment(id, &mut data, source_key)?;
+        let result = self.store_blob(|| Ok(data.as_slice()), key);
+        data.zeroize();
+        result
+    }
+
+    /// Encrypt content read twice from `open` (once to address it, once to store it)
+    fn store_blob<R: Read>(&self, open: impl Fn() -> Result<R>, key: &MasterKey) -> Result<String> {
+        let id = Crypto::content_id(&mut open()?, key)?;
+        if self.backend.has_blob(&id)? {
+            return Ok(id);
         }
 
-        let mut entries = Vec::new();
-        self.walk_entries(&store_path, &store_path, &mut entries)?;
-        entries.sort();
-        Ok(entries)
+        self.backend.write_blob(&id, &mut |mut writer| {
+            Crypto::encrypt_stream(&mut open()?, &mut writer, key)?;
+            Ok(())
+        })?;
+        Ok(id)
     }
 
-    /// Walk directory tree for entries
-    fn walk_entries(&self, base: &Path, dir: &Path, entries: &mut Vec<String>) -> Result<()> {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    /// Decrypt an attachment blob into a writer
+    pub fn read_attachment<W: Write>(
+        &self,
+        id: &str,
+        writer: &mut W,
+        key: &MasterKey,
+    ) -> Result<u64> {
+        check_blob_id(id)?;
+        let mut reader = self
+            .backend
+            .open_blob(id)?
+            .ok_or_else(|| anyhow!("Attachment blob {} is missing from the vault", id))?;
+        Crypto::decrypt_stream(&mut reader, writer, key)
+    }
 
-            if path.is_dir() {
-                self.walk_entries(base, &path, entries)?;
-            } else if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                let relative = path
-                    .strip_prefix(base)?
-                    .to_string_lossy()
-                    .replace(".json", "")
-                    .replace(std::path::MAIN_SEPARATOR, "/");
-                entries.push(relative);
+    /// Delete an attachment blob unless another entry still references it
+    pub fn delete_attachment_if_unused(&self, id: &str) -> Result<bool> {
+        check_blob_id(id)?;
+        for key in self.list_entries()? {
+            let Some(entry) = self.read_stored(&key)? else {
+                continue;
+            };
+            if entry.metadata.attachments.iter().any(|a| a.blob == id) {
+                return Ok(false);
             }
         }
-        Ok(())
+        // Trashed entries may still be restored with their attachments
+        for (key, _) in self.list_trash()? {
+            let trashed = self.load_trashed(&key)?;
+            if trashed
+                .entry
+                .metadata
+                .attachments
+                .iter()
+                .any(|a| a.blob == id)
+            {
+                return Ok(false);
+            }
+        }
+
+        self.backend.remove_blob(id)?;
+        Ok(true)
     }
 
-    /// Search entries through decrypted content
-    pub fn search_entries(&self, query: &str, key: &MasterKey) -> Result<Vec<(String, Entry)>> {
-        let entries = self.list_entries()?;
-        let mut results = Vec::new();
+    /// Re-encrypt an entry loaded from another vault for this one.
+    ///
+    /// The value, history, secret fields and attachments are decrypted with the
+    /// source vault's key. A derived password depends on the master key, so its
+    /// current value is stored instead of the derivation parameters.
+    pub fn adopt_entry(
+        &self,
+        mut entry: Entry,
+        source: &Storage,
+        source_key: &MasterKey,
+        key: &MasterKey,
+    ) -> Result<Entry> {
+        let reencrypt = |value: &EncryptedValue| -> Result<EncryptedValue> {
+            let mut plain = Crypto::decrypt(value, source_key)?;
+            let result = Crypto::encrypt(&plain, key);
+            plain.zeroize();
+            result
+        };
 
-        for entry_key in entries {
-            if let Ok(entry) = self.load_entry(&entry_key, key) {
-                // Decrypt the password/value to search through it
-                let decrypted_value = Crypto::decrypt(&entry.value, key)?;
-                let decrypted_str = String::from_utf8(decrypted_value)?;
+        if let Some(params) = entry.metadata.derivation.take() {
+            let mut value = Crypto::derive_site_password(source_key, &params)?;
+            entry.value = Crypto::encrypt(value.as_bytes(), key)?;
+            value.zeroize();
+        } else {
+            entry.value = reencrypt(&entry.value)?;
+        }
 
-                // Check if query matches any of these fields:
-                let query_lower = query.to_lowercase();
-                let mut found_match = false;
+        let metadata = &mut entry.metadata;
+        for previous in &mut metadata.history {
+            previous.value = reencrypt(&previous.value)?;
+        }
+        for value in metadata.secret_fields.values_mut() {
+            *value = reencrypt(value)?;
+        }
+        for value in metadata.fields.values_mut() {
+            if let FieldValue::Secret(encrypted) = value {
+                *encrypted = reencrypt(encrypted)?;
+            }
+        }
+        for attachment in &mut metadata.attachments {
+            attachment.blob = self.copy_attachment(source, &attachment.blob, source_key, key)?;
+        }
 
-                // 1. Entry key (filename)
-                if entry_key.to_lowercase().contains(&query_lower) {
-                    found_match = true;
-                }
+        Ok(entry)
+    }
 
-                // 2. Decrypted password/value
-                if decrypted_str.to_lowercase().contains(&query_lower) {
-                    found_match = true;
-                }
+    /// List all entries
+    pub fn list_entries(&self) -> Result<Vec<String>> {
+        self.backend.list(Record::Entry)
+    }
 
-                // 3. Username
-                if let Some(username) = &entry.metadata.username {
-                    if username.to_lowercase().contains(&query_lower) {
-                        found_match = true;
-                    }
-                }
+    /// Search the indexed metadata of entries, and of trashed entries if asked,
+    /// for a case-insensitive query; trashed keys are marked " (trashed)"
+    pub fn search_entries(
+        &self,
+        query: &str,
+        key: &MasterKey,
+        include_trash: bool,
+    ) -> Result<Vec<(String, IndexedEntry)>> {
+        let query = query.to_lowercase();
+        let mut results = Vec::new();
+        for (entry_key, trashed, entry) in self.indexed_entries(key, include_trash)? {
+            let found = entry_key.to_lowercase().contains(&query)
+                || entry
+                    .fields()
+                    .iter()
+                    .any(|(_, value)| value.to_lowercase().contains(&query));
+            if found {
+                results.push((trash_label(entry_key, trashed), entry));
+            }
+        }
+        Ok(results)
+    }
 
-                // 4. Notes
-                if let Some(notes) = &entry.metadata.notes {
-                    if notes.to_lowercase().contains(&query_lower) {
-                        found_match = true;
-                    }
-                }
+    /// Decrypt the search index once, returning every indexed entry as
+    /// (key, trashed, metadata).
+    ///
+    /// The index is brought in line with the stored keys first, so entries
+    /// written by a git pull or an older version of bunker are indexed as they
+    /// are found. Changes to existing entries made outside bunker need
+    /// `bunker index rebuild`.
+    pub fn indexed_entries(&self, key: &MasterKey, include_trash: bool) -> Result<IndexedEntries> {
+        let mut cached = self
+            .index
+            .lock()
+            .map_err(|_| anyhow!("Search index poisoned"))?;
+        let mut index = match cached.take() {
+            Some(index) => index,
+            None => self.read_index()?.unwrap_or_else(SearchIndex::new),
+        };
 
-                // 5. URL
-                if let Some(url) = &entry.metadata.url {
-                    if url.to_lowercase().contains(&query_lower) {
-                        found_match = true;
-                    }
+        let (changed, mut results) = self.reconcile_index(&mut index, key, include_trash)?;
+
+        // Only write the index under the vault's lock, so a search never
+        // overwrites what a command changing the vault wrote meanwhile. If
+        // another process is busy, the fixed index is kept for this run only.
+        if changed {
+            if self.holds_lock()? {
+                self.backend.write_index(&index.to_json()?)?;
+            } else if let Some(_lock) = self.try_lock()? {
+                // Start over from what is on disk now that nobody can change it
+                index = self.read_index()?.unwrap_or_else(SearchIndex::new);
+                let (changed, fresh) = self.reconcile_index(&mut index, key, include_trash)?;
+                if changed {
+                    self.backend.write_index(&index.to_json()?)?;
                 }
+                results = fresh;
+            }
+        }
+        *cached = Some(index);
+        Ok(results)
+    }
 
-                // 6. Tags
-                for tag in &entry.metadata.tags {
-                    if tag.to_lowercase().contains(&query_lower) {
-                        found_match = true;
-                        break;
-                    }
+    /// Bring an index in line with the stored keys, returning whether it
+    /// changed and every indexed entry as (key, trashed, metadata)
+    fn reconcile_index(
+        &self,
+        index: &mut SearchIndex,
+        key: &MasterKey,
+        include_trash: bool,
+    ) -> Result<(bool, IndexedEntries)> {
+        let mut changed = false;
+        let mut results = Vec::new();
+        for trashed in [false, true] {
+            let record = if trashed {
+                Record::Trashed
+            } else {
+                Record::Entry
+            };
+            let stored = self.backend.list(record)?;
+            let stored_set: HashSet<&String> = stored.iter().collect();
+            for indexed in index.keys(trashed) {
+                if !stored_set.contains(&indexed) {
+                    changed |= index.remove(&indexed, trashed);
                 }
+            }
 
-                // 7. Custom fields
-                for (field_name, field_value) in &entry.metadata.custom_fields {
-                    if field_value.to_lowercase().contains(&query_lower) {
-                        found_match = true;
-                        break;
+            for entry_key in stored {
+                let entry = match index.get(&entry_key, trashed, key) {
+                    Some(entry) => entry,
+                    None => {
+                        // Not indexed yet, or indexed under another key
+                        let metadata = if trashed {
+                            self.load_trashed(&entry_key)?.entry.metadata
+                        } else {
+                            self.load_metadata(&entry_key)?
+                        };
+                        index.insert(&entry_key, &metadata, trashed, key)?;
+                        changed = true;
+                        IndexedEntry::from_metadata(&metadata)
                     }
-                }
-
-                if found_match {
-                    results.push((entry_key, entry));
+                };
+                if !trashed || include_trash {
+                    results.push((entry_key, trashed, entry));
                 }
             }
         }
+        Ok((changed, results))
+    }
 
-        Ok(results)
+    /// Index every entry from scratch, returning how many were indexed
+    pub fn rebuild_index(&self, key: &MasterKey) -> Result<usize> {
+        self.invalidate_index()?;
+        let entries = self.indexed_entries(key, true)?;
+        Ok(entries.len())
+    }
+
+    /// Drop the search index after files changed behind bunker's back (git
+    /// pulls and restores); it is rebuilt by the next search
+    pub fn invalidate_index(&self) -> Result<()> {
+        self.backend.remove_index()?;
+        *self
+            .index
+            .lock()
+            .map_err(|_| anyhow!("Search index poisoned"))? = None;
+        Ok(())
     }
 
-    /// Get entry path
-    fn entry_path(&self, key: &str) -> PathBuf {
-        let safe_key = key.replace('/', std::path::MAIN_SEPARATOR_STR);
-        self.vault_path
-            .join("store")
-            .join(format!("{}.json", safe_key))
+    fn read_index(&self) -> Result<Option<SearchIndex>> {
+        Ok(self
+            .backend
+            .read_index()?
+            .and_then(|data| SearchIndex::parse(&data)))
+    }
+
+    /// Apply a change to the search index, if the vault has one yet
+    fn update_index(&self, change: impl FnOnce(&mut SearchIndex) -> Result<()>) -> Result<()> {
+        let mut cached = self
+            .index
+            .lock()
+            .map_err(|_| anyhow!("Search index poisoned"))?;
+        if cached.is_none() {
+            *cached = self.read_index()?;
+        }
+        let Some(index) = cached.as_mut() else {
+            return Ok(());
+        };
+        change(index)?;
+        self.backend.write_index(&index.to_json()?)
+    }
+
+    /// Path of an entry's file relative to the vault, as tracked by git
+    pub fn entry_git_path(key: &str) -> Result<String> {
+        Ok(format!("store/{}", DirBackend::key_file(key)?))
     }
 
     /// Store session with encrypted master key
@@ -287,9 +1106,9 @@ impl Storage {
         let session_dir = Self::base_dir()?.join("sessions");
         fs::create_dir_all(&session_dir)?;
 
-        let session_path = session_dir.join(format!("{}.session", session.vault_name));
+        let session_path = session_dir.join(format!("{}.session", self.session_name));
         let session_json = serde_json::to_string(session)?;
-        fs::write(session_path, session_json)?;
+        write_atomic(session_path, session_json)?;
 
         Ok(())
     }
@@ -298,8 +1117,6 @@ impl Storage {
     pub fn store_master_key_permanently(&self, master_key: &MasterKey) -> Result<()> {
         let config = self.load_config()?;
 
-        // Use vault ID as encryption key material
-        let vault_key = config.id.as_bytes();
         let salt = Crypto::generate_salt();
 
         // Derive encryption key from vault ID
@@ -327,8 +1144,13 @@ impl Storage {
 
     /// Load master key from permanent storage
     pub fn load_master_key_permanently(&self) -> Result<MasterKey> {
-        let session = self.load_session()?;
-        let config = self.load_config()?;
+        Self::session_master_key(&self.session_name, &self.load_config()?)
+    }
+
+    /// Master key kept in a vault's session, found without a `Storage` so that
+    /// a single-file vault can unlock itself
+    fn session_master_key(session_name: &str, config: &VaultConfig) -> Result<MasterKey> {
+        let session = Self::read_session(session_name)?;
 
         // Derive encryption key from vault ID
         let encryption_key = Crypto::derive_key(&config.id.to_string(), &session.salt)?;
@@ -365,9 +1187,13 @@ impl Storage {
 
     /// Load session
     pub fn load_session(&self) -> Result<Session> {
+        Self::read_session(&self.session_name)
+    }
+
+    fn read_session(session_name: &str) -> Result<Session> {
         let session_path = Self::base_dir()?
             .join("sessions")
-            .join(format!("{}.session", self.vault_name));
+            .join(format!("{}.session", session_name));
 
         if !session_path.exists() {
             return Err(anyhow!("No active session"));
@@ -391,7 +1217,7 @@ impl Storage {
     pub fn clear_session(&self) -> Result<()> {
         let session_path = Self::base_dir()?
             .join("sessions")
-            .join(format!("{}.session", self.vault_name));
+            .join(format!("{}.session", self.session_name));
 
         if session_path.exists() {
             fs::remove_file(session_path)?;
@@ -410,13 +1236,24 @@ impl Storage {
         let mut vaults = Vec::new();
         for entry in fs::read_dir(vaults_dir)? {
             let entry = entry?;
-            if entry.path().is_dir() {
-                if let Some(name) = entry.file_name().to_str() {
-                    if entry.path().join(".vault").exists() {
-                        vaults.push(name.to_string());
-                    }
-                }
-            }
+            let path = entry.path();
+            let name = if path.is_dir() {
+                entry
+                    .file_name()
+                    .to_str()
+                    .filter(|_| path.join(".vault").exists())
+                    .map(str::to_string)
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == CONTAINER_EXTENSION)
+            {
+                path.file_stem()
+                    .and_then(|s| s.to_str())
+                    .map(str::to_string)
+            } else {
+                None
+            };
+            vaults.extend(name);
         }
 
         vaults.sort();
@@ -425,26 +1262,55 @@ impl Storage {
 
     /// Export vault
     pub fn export_vault(&self, password: &str) -> Result<Vec<u8>> {
-        // Collect all entries
-        let entries = self.list_entries()?;
-        let mut vault_data = HashMap::new();
+        let mut payload = ExportPayload {
+            version: EXPORT_VERSION.to_string(),
+            vault_config: self.load_config()?,
+            entries: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            trash: BTreeMap::new(),
+            attachments: BTreeMap::new(),
+            exported_at: Some(Utc::now()),
+        };
 
-        for entry_key in entries {
-            let entry_path = self.entry_path(&entry_key);
-            let entry_data = fs::read_to_string(entry_path)?;
-            vault_data.insert(entry_key, entry_data);
+        // Every record in stored form, and the blobs their attachments point to
+        let mut blobs = HashSet::new();
+        for (record, records) in [
+            (Record::Entry, &mut payload.entries),
+            (Record::Alias, &mut payload.aliases),
+            (Record::Trashed, &mut payload.trash),
+        ] {
+            for key in self.backend.list(record)? {
+                let Some(data) = self.backend.read(record, &key)? else {
+                    continue;
+                };
+                let attachments = match record {
+                    Record::Entry => serde_json::from_str::<Entry>(&data)?.metadata.attachments,
+                    Record::Trashed => {
+                        serde_json::from_str::<TrashedEntry>(&data)?
+                            .entry
+                            .metadata
+                            .attachments
+                    }
+                    Record::Alias => Vec::new(),
+                };
+                blobs.extend(attachments.into_iter().map(|a| a.blob));
+                records.insert(key, data);
+            }
+        }
+        for id in blobs {
+            if let Some(mut reader) = self.backend.open_blob(&id)? {
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                payload.attachments.insert(id, BASE64.encode(&data));
+            }
         }
 
-        // Include vault config
-        let config = self.load_config()?;
-        let export_data = serde_json::json!({
-            "version": "1.0",
-            "vault_config": config,
-            "entries": vault_data,
-            "exported_at": Utc::now(),
-        });
+        Self::encode_export(&payload, password)
+    }
 
-        let json_data = serde_json::to_vec(&export_data)?;
+    /// Encrypt an export payload into an export file
+    fn encode_export(payload: &ExportPayload, password: &str) -> Result<Vec<u8>> {
+        let json_data = serde_json::to_vec(payload)?;
 
         // Encrypt with password
         let (ciphertext, nonce, salt) = Crypto::encrypt_with_password(&json_data, password)?;
@@ -452,7 +1318,7 @@ impl Storage {
         // Create final export
         let export = serde_json::json!({
             "bunker_export": true,
-            "version": "1.0",
+            "version": EXPORT_VERSION,
             "encrypted_data": BASE64.encode(&ciphertext),
             "nonce": BASE64.encode(&nonce),
             "salt": BASE64.encode(&salt),
@@ -464,6 +1330,64 @@ impl Storage {
 
     /// Import vault
     pub fn import_vault(data: &[u8], password: &str, vault_name: &str) -> Result<()> {
+        let mut payload = Self::decode_export(data, password)?;
+
+        // Create new vault
+        let storage = Storage::new(Some(vault_name.to_string()))?;
+
+        // Preserve the original vault ID so the KDF salt remains consistent across devices
+        // This ensures the derived master key matches the one used to encrypt the entries
+        payload.vault_config.name = vault_name.to_string();
+        storage.init_vault(payload.vault_config.clone())?;
+        storage.write_payload(&payload)
+    }
+
+    /// Open an export as a vault held in memory, without creating anything on disk
+    pub fn open_export(data: &[u8], password: &str) -> Result<Self> {
+        let payload = Self::decode_export(data, password)?;
+        let storage = Self {
+            vault_path: PathBuf::new(),
+            vault_name: payload.vault_config.name.clone(),
+            backend: Box::new(MemoryBackend::default()),
+            single_file: false,
+            session_name: payload.vault_config.name.clone(),
+            index: Mutex::new(None),
+        };
+        storage.backend.create(&payload.vault_config)?;
+        storage.write_payload(&payload)?;
+        Ok(storage)
+    }
+
+    /// Whether an export file carries attachment blobs, which 1.0 exports did not
+    pub fn export_has_attachments(data: &[u8]) -> bool {
+        serde_json::from_slice::<serde_json::Value>(data)
+            .is_ok_and(|export| export["version"].as_str() != Some("1.0"))
+    }
+
+    /// Write an export's records and attachment blobs into this vault
+    fn write_payload(&self, payload: &ExportPayload) -> Result<()> {
+        self.batch(|| {
+            for (record, records) in [
+                (Record::Entry, &payload.entries),
+                (Record::Alias, &payload.aliases),
+                (Record::Trashed, &payload.trash),
+            ] {
+                let keys = key::normalize_all(records.keys().map(String::as_str))?;
+                for (key, data) in keys.iter().zip(records.values()) {
+                    self.backend.write(record, key.as_str(), data)?;
+                }
+            }
+            for (id, encoded) in &payload.attachments {
+                let data = BASE64.decode(encoded)?;
+                self.backend
+                    .write_blob(id, &mut |writer| Ok(writer.write_all(&data)?))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Check and decrypt an export file's payload
+    fn decode_export(data: &[u8], password: &str) -> Result<ExportPayload> {
         let import_data: serde_json::Value = serde_json::from_slice(data)?;
 
         // Verify it's a bunker export
@@ -498,29 +1422,463 @@ impl Storage {
 
         // Decrypt
         let decrypted = Crypto::decrypt_with_password(&ciphertext, &nonce, &salt, password)?;
-        let vault_data: serde_json::Value = serde_json::from_slice(&decrypted)?;
+        let payload: ExportPayload = serde_json::from_slice(&decrypted)
+            .map_err(|e| anyhow!("Invalid bunker export file: {}", e))?;
+        payload
+            .check_blob_ids()
+            .map_err(|e| anyhow!("Invalid bunker export file: {}", e))?;
+        Ok(payload)
+    }
+}
 
-        // Create new vault
-        let storage = Storage::new(Some(vault_name.to_string()))?;
+/// Vaults and entries for tests across the crate
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use crate::types::EncryptionConfig;
+    use std::sync::OnceLock;
+    use uuid::Uuid;
+
+    pub fn test_config() -> VaultConfig {
+        VaultConfig {
+            id: Uuid::new_v4(),
+            name: "test".to_string(),
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+            encryption: EncryptionConfig::default(),
+            git_remote: None,
+            auto_sync: false,
+            auto_lock_minutes: None,
+            history_limit: 10,
+            trash_retention_days: 30,
+        }
+    }
 
-        // Extract and save config
-        let mut config: VaultConfig = serde_json::from_value(vault_data["vault_config"].clone())?;
-        // Preserve the original vault ID so the KDF salt remains consistent across devices
-        // This ensures the derived master key matches the one used to encrypt the entries
-        config.name = vault_name.to_string();
-        storage.init_vault(config)?;
-
-        // Import entries
-        if let Some(entries) = vault_data["entries"].as_object() {
-            for (key, value) in entries {
-                let entry_path = storage.entry_path(key);
-                if let Some(parent) = entry_path.parent() {
-                    fs::create_dir_all(parent)?;
+    /// An initialized vault held in memory
+    pub fn test_storage() -> Storage {
+        let storage = Storage::in_memory("test");
+        storage.init_vault(test_config()).unwrap();
+        storage
+    }
+
+    /// A new vault on disk, in a home shared by every test, whose key is
+    /// stored so that commands can be run through their `execute`
+    pub fn disk_vault() -> (String, MasterKey) {
+        static HOME: OnceLock<PathBuf> = OnceLock::new();
+        HOME.get_or_init(|| {
+            let home = tempfile::tempdir().unwrap().keep();
+            crate::home::set_override(&home).unwrap();
+            home
+        });
+
+        let name = format!("test-{}", Uuid::new_v4().simple());
+        let storage = Storage::new(Some(name.clone())).unwrap();
+        storage.init_vault(test_config()).unwrap();
+        let master_key = MasterKey::new(Crypto::generate_salt());
+        storage.store_master_key_permanently(&master_key).unwrap();
+        (name, master_key)
+    }
+
+    pub fn test_entry(key: &str, value: &[u8], master_key: &MasterKey) -> Entry {
+        Entry {
+            id: Uuid::new_v4(),
+            key: key.to_string(),
+            value: Crypto::encrypt(value, master_key).unwrap(),
+            metadata: EntryMetadata::default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            accessed_at: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+    use crate::types::Attachment;
+
+    #[test]
+    fn test_in_memory_storage() {
+        let storage = test_storage();
+        let master_key = MasterKey::new(vec![7; 32]);
+        assert!(storage.vault_exists());
+
+        let entry = test_entry("work//db", b"hunter2", &master_key);
+        storage.store_entry(&entry, &master_key).unwrap();
+        storage.store_alias("db", "work/db").unwrap();
+
+        let loaded = storage.load_entry("db", &master_key).unwrap();
+        assert_eq!(loaded.key, "work/db");
+        assert_eq!(
+            Crypto::decrypt(&loaded.value, &master_key).unwrap(),
+            b"hunter2"
+        );
+        assert_eq!(storage.list_entries().unwrap(), vec!["work/db"]);
+
+        storage.trash_entry("work/db").unwrap();
+        assert!(!storage.entry_exists("work/db").unwrap());
+        storage.restore_from_trash("work/db").unwrap();
+        assert!(storage.list_trash().unwrap().is_empty());
+        assert!(storage.entry_exists("db").unwrap());
+    }
+
+    #[test]
+    fn test_single_file_is_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.bvault");
+        let master_key = MasterKey::new(vec![7; 32]);
+
+        let storage = Storage::single_file(path.clone());
+        storage.unlock(&master_key).unwrap();
+        storage.init_vault(test_config()).unwrap();
+        let entry = test_entry("work/db", b"hunter2", &master_key);
+        storage.store_entry(&entry, &master_key).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("work/db"));
+
+        // The config is readable without the key, the records are not
+        let reopened = Storage::single_file(path.clone());
+        assert_eq!(reopened.load_config().unwrap().name, "test");
+        assert!(reopened.list_entries().is_err());
+        assert!(reopened.unlock(&MasterKey::new(vec![8; 32])).is_err());
+        reopened.unlock(&master_key).unwrap();
+        assert_eq!(reopened.list_entries().unwrap(), vec!["work/db"]);
+    }
+
+    #[test]
+    fn test_lock_contention() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::project(dir.path().join(PROJECT_DIR));
+        storage.init_vault(test_config()).unwrap();
+
+        // Another process holding the lock
+        let other = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(storage.backend.lock_path().unwrap())
+            .unwrap();
+        other.lock().unwrap();
+        assert!(storage.try_lock().unwrap().is_none());
+        assert!(
+            storage
+                .lock_within(Duration::from_millis(200))
+                .unwrap()
+                .is_none()
+        );
+
+        other.unlock().unwrap();
+        let lock = storage.try_lock().unwrap();
+        assert!(lock.is_some());
+        assert!(storage.holds_lock().unwrap());
+        // Taking it again in the same process does not wait for ourselves
+        assert!(storage.try_lock().unwrap().is_some());
+        assert!(other.try_lock().is_err());
+
+        drop(lock);
+        assert!(!storage.holds_lock().unwrap());
+        other.try_lock().unwrap();
+    }
+
+    #[test]
+    fn test_lock_rereads_single_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shared.bvault");
+        let master_key = MasterKey::new(vec![7; 32]);
+
+        let first = Storage::single_file(path.clone());
+        first.unlock(&master_key).unwrap();
+        first.init_vault(test_config()).unwrap();
+        assert!(first.list_entries().unwrap().is_empty());
+
+        // Written by another process while the first one had the file loaded
+        let second = Storage::single_file(path);
+        second.unlock(&master_key).unwrap();
+        second
+            .store_entry(&test_entry("db", b"hunter2", &master_key), &master_key)
+            .unwrap();
+
+        assert!(first.list_entries().unwrap().is_empty());
+        let _lock = first.lock().unwrap();
+        assert_eq!(first.list_entries().unwrap(), vec!["db"]);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("record.json");
+        let leftovers = || {
+            fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".tmp"))
+                .count()
+        };
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(leftovers(), 0);
+
+        // A failed rename leaves neither the temporary file nor a changed target
+        let blocked = dir.path().join("blocked");
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("inside"), "kept").unwrap();
+        assert!(write_atomic(&blocked, "data").is_err());
+        assert_eq!(leftovers(), 0);
+        assert_eq!(fs::read_to_string(blocked.join("inside")).unwrap(), "kept");
+    }
+
+    #[test]
+    fn test_single_file_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.bvault");
+        let master_key = MasterKey::new(vec![7; 32]);
+
+        let storage = Storage::single_file(path.clone());
+        storage.unlock(&master_key).unwrap();
+        storage.init_vault(test_config()).unwrap();
+        let on_disk = || {
+            let reopened = Storage::single_file(path.clone());
+            reopened.unlock(&master_key).unwrap();
+            reopened.list_entries().unwrap()
+        };
+
+        // Written once, when the batch ends
+        storage
+            .batch(|| {
+                storage.store_entry(&test_entry("a", b"1", &master_key), &master_key)?;
+                storage.store_entry(&test_entry("b", b"2", &master_key), &master_key)?;
+                assert!(on_disk().is_empty());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(on_disk(), vec!["a", "b"]);
+
+        // A failed batch keeps none of its changes
+        let failed: Result<()> = storage.batch(|| {
+            storage.store_entry(&test_entry("c", b"3", &master_key), &master_key)?;
+            storage.delete_entry("a")?;
+            Err(anyhow!("interrupted"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(storage.list_entries().unwrap(), vec!["a", "b"]);
+        assert_eq!(on_disk(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_single_file_session_names() {
+        let root = tempfile::tempdir().unwrap();
+        let a = root.path().join("a").join("stick.bvault");
+        let b = root.path().join("b").join("stick.bvault");
+        fs::create_dir_all(a.parent().unwrap()).unwrap();
+        fs::create_dir_all(b.parent().unwrap()).unwrap();
+
+        let first = Storage::single_file(a.clone());
+        let second = Storage::single_file(b);
+        assert_eq!(first.get_vault_name(), "stick");
+        assert_eq!(second.get_vault_name(), "stick");
+        assert_ne!(first.session_name, second.session_name);
+        assert_ne!(first.session_name, "stick");
+
+        // Another spelling of the same file shares its session, before and after
+        // the file is created
+        let dotted = root
+            .path()
+            .join("a")
+            .join("..")
+            .join("a")
+            .join("stick.bvault");
+        assert_eq!(
+            Storage::single_file(dotted.clone()).session_name,
+            first.session_name
+        );
+        fs::write(&a, "").unwrap();
+        assert_eq!(
+            Storage::single_file(dotted).session_name,
+            first.session_name
+        );
+    }
+
+    #[test]
+    fn test_missing_entry_errors() {
+        let storage = test_storage();
+        let master_key = MasterKey::new(vec![7; 32]);
+        let error = |key: &str| {
+            storage
+                .load_entry(key, &master_key)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(error("a//b"), "Entry 'a/b' not found");
+        storage.store_alias("old", "a//b").unwrap();
+        assert_eq!(error("old"), "Alias 'old' points to missing entry 'a/b'");
+    }
+
+    #[test]
+    fn test_load_entries() {
+        let storage = test_storage();
+        let master_key = MasterKey::new(vec![7; 32]);
+        let keys: Vec<String> = (0..50).map(|i| format!("site{:02}", i)).collect();
+        for key in &keys {
+            let entry = test_entry(key, key.as_bytes(), &master_key);
+            storage.store_entry(&entry, &master_key).unwrap();
+        }
+        // One entry written under another key cannot be decrypted
+        let other_key = MasterKey::new(vec![8; 32]);
+        let entry = test_entry("site07", b"other", &other_key);
+        storage.store_entry(&entry, &other_key).unwrap();
+
+        let loaded = storage.load_entries(&keys, &master_key);
+        assert_eq!(loaded.len(), keys.len());
+        for ((key, entry), expected) in loaded.iter().zip(&keys) {
+            assert_eq!(key, expected);
+            match entry {
+                Ok(entry) => assert_eq!(
+                    Crypto::decrypt(&entry.value, &master_key).unwrap(),
+                    key.as_bytes()
+                ),
+                Err(e) => {
+                    assert_eq!(key, "site07");
+                    assert!(e.to_string().contains("'site07'"));
                 }
-                fs::write(entry_path, value.as_str().unwrap_or(""))?;
             }
         }
+        assert_eq!(loaded.iter().filter(|(_, e)| e.is_err()).count(), 1);
+    }
 
-        Ok(())
+    #[test]
+    fn test_find_project_vault() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("services").join("api");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(Storage::find_project_vault(&nested), None);
+
+        fs::create_dir_all(root.path().join(PROJECT_DIR)).unwrap();
+        fs::write(root.path().join(PROJECT_DIR).join(".vault"), "{}").unwrap();
+        // A .bunker directory without a vault in it, like a bunker home, is passed over
+        fs::create_dir_all(nested.join(PROJECT_DIR).join("vaults")).unwrap();
+
+        let found = Storage::find_project_vault(&nested).unwrap();
+        assert_eq!(found, root.path().join(PROJECT_DIR));
+        let storage = Storage::project(found);
+        assert_eq!(
+            storage.get_vault_name(),
+            root.path().file_name().unwrap().to_string_lossy()
+        );
+        assert!(storage.vault_exists());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let storage = test_storage();
+        let master_key = MasterKey::new(vec![7; 32]);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), b"-----BEGIN CERTIFICATE-----").unwrap();
+        let (blob, size) = storage.store_attachment(file.path(), &master_key).unwrap();
+        let mut entry = test_entry("certs/web", b"pem", &master_key);
+        entry.metadata.attachments.push(Attachment {
+            name: "web.pem".to_string(),
+            blob: blob.clone(),
+            size,
+            added_at: Utc::now(),
+        });
+        storage.store_entry(&entry, &master_key).unwrap();
+        storage.store_alias("web", "certs/web").unwrap();
+        let old = test_entry("certs/old", b"old", &master_key);
+        storage.store_entry(&old, &master_key).unwrap();
+        storage.trash_entry("certs/old").unwrap();
+
+        let data = storage.export_vault("export password").unwrap();
+        assert!(Storage::export_has_attachments(&data));
+        let imported = Storage::open_export(&data, "export password").unwrap();
+
+        assert_eq!(imported.resolve_key("web").unwrap(), "certs/web");
+        assert_eq!(imported.list_trash().unwrap().len(), 1);
+        let mut extracted = Vec::new();
+        imported
+            .read_attachment(&blob, &mut extracted, &master_key)
+            .unwrap();
+        assert_eq!(extracted, b"-----BEGIN CERTIFICATE-----");
+    }
+
+    /// Ids that are not a hex SHA-256, starting with one that escapes the vault
+    fn bad_blob_ids(victim: &Path) -> Vec<String> {
+        let relative = victim.to_string_lossy().trim_start_matches('/').to_string();
+        vec![
+            format!("{}{}", "../".repeat(20), relative),
+            "é".repeat(32),
+            "AB".repeat(32),
+            "ab".repeat(31),
+        ]
+    }
+
+    #[test]
+    fn test_bad_blob_ids_are_refused() {
+        let root = tempfile::tempdir().unwrap();
+        let victim = root.path().join("victim.txt");
+        fs::write(&victim, "keep me").unwrap();
+        let storage = Storage::project(root.path().join(PROJECT_DIR));
+        storage.init_vault(test_config()).unwrap();
+        let master_key = MasterKey::new(vec![7; 32]);
+
+        for id in bad_blob_ids(&victim) {
+            assert!(storage.delete_attachment_if_unused(&id).is_err());
+            assert!(
+                storage
+                    .read_attachment(&id, &mut Vec::new(), &master_key)
+                    .is_err()
+            );
+            assert!(storage.backend.remove_blob(&id).is_err());
+
+            // An entry whose metadata was edited to point at the id cannot be loaded
+            let mut entry = test_entry("evil", b"x", &master_key);
+            entry.metadata.attachments.push(Attachment {
+                name: "a".to_string(),
+                blob: id.clone(),
+                size: 1,
+                added_at: Utc::now(),
+            });
+            storage.store_entry(&entry, &master_key).unwrap();
+            assert!(storage.load_entry("evil", &master_key).is_err());
+            assert!(storage.delete_entry("evil").is_err());
+        }
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep me");
+    }
+
+    #[test]
+    fn test_import_refuses_bad_blob_ids() {
+        let victim = Path::new("/tmp/victim.txt");
+        for id in bad_blob_ids(victim) {
+            let master_key = MasterKey::new(vec![7; 32]);
+
+            // A blob stored under the id
+            let payload = ExportPayload {
+                version: EXPORT_VERSION.to_string(),
+                vault_config: test_config(),
+                entries: BTreeMap::new(),
+                aliases: BTreeMap::new(),
+                trash: BTreeMap::new(),
+                attachments: BTreeMap::from([(id.clone(), BASE64.encode(b"data"))]),
+                exported_at: None,
+            };
+            let data = Storage::encode_export(&payload, "export password").unwrap();
+            assert!(Storage::open_export(&data, "export password").is_err());
+
+            // An entry pointing at the id
+            let storage = test_storage();
+            let mut entry = test_entry("evil", b"x", &master_key);
+            entry.metadata.attachments.push(Attachment {
+                name: "a".to_string(),
+                blob: id.clone(),
+                size: 1,
+                added_at: Utc::now(),
+            });
+            storage.store_entry(&entry, &master_key).unwrap();
+            let data = storage.export_vault("export password").unwrap();
+            assert!(Storage::open_export(&data, "export password").is_err());
+        }
     }
 }
@@ -0,0 +1,86 @@
+use anyhow::{Result, anyhow};
+
+/// Filter built from repeated `--tag` flags.
+///
+/// Every flag must match (AND). Within a flag, `a|b` or `a,b` matches either
+/// tag (OR) and `!a` matches entries without the tag (NOT).
+#[derive(Debug, Default, Clone)]
+pub struct TagFilter {
+    clauses: Vec<Vec<TagTerm>>,
+}
+
+#[derive(Debug, Clone)]
+struct TagTerm {
+    tag: String,
+    negated: bool,
+}
+
+impl TagFilter {
+    pub fn parse(exprs: &[String]) -> Result<Self> {
+        let mut clauses = Vec::new();
+        for expr in exprs {
+            let mut terms = Vec::new();
+            for term in expr.split(['|', ',']) {
+                let term = term.trim();
+                let (tag, negated) = match term.strip_prefix('!') {
+                    Some(tag) => (tag.trim(), true),
+                    None => (term, false),
+                };
+                if tag.is_empty() {
+                    return Err(anyhow!("Empty tag in filter '{}'", expr));
+                }
+                terms.push(TagTerm {
+                    tag: tag.to_string(),
+                    negated,
+                });
+            }
+            clauses.push(terms);
+        }
+        Ok(Self { clauses })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        self.clauses.iter().all(|terms| {
+            terms
+                .iter()
+                .any(|term| tags.contains(&term.tag) != term.negated)
+        })
+    }
+}
+
+/// Check that a tag can be used in filters
+pub fn validate(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(anyhow!("Tags cannot be empty"));
+    }
+    if tag.starts_with('!') || tag.contains(['|', ',']) || tag.contains(char::is_whitespace) {
+        return Err(anyhow!(
+            "Invalid tag '{}': tags cannot contain spaces, '|' or ',' or start with '!'",
+            tag
+        ));
+    }
+    Ok(tag.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_filter() {
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let filter = TagFilter::parse(&tags(&["prod", "oncall|db", "!legacy"])).unwrap();
+
+        assert!(filter.matches(&tags(&["prod", "db"])));
+        assert!(filter.matches(&tags(&["prod", "oncall", "web"])));
+        assert!(!filter.matches(&tags(&["prod", "db", "legacy"])));
+        assert!(!filter.matches(&tags(&["staging", "db"])));
+        assert!(TagFilter::default().matches(&[]));
+        assert!(TagFilter::parse(&tags(&["prod|"])).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use uuid::Uuid;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -16,6 +17,69 @@ pub struct Entry {
     pub accessed_at: Option<DateTime<Utc>>,
 }
 
+impl Entry {
+    /// Record a replaced value in history (newest first), keeping at most `limit`
+    pub fn push_history(&mut self, previous: EncryptedValue, limit: usize) {
+        let set_at = self.value_set_at();
+        self.record_history(previous, set_at, limit);
+    }
+
+    /// When the current value was set: when it replaced the newest value in
+    /// history, or else when the entry was created
+    pub fn value_set_at(&self) -> DateTime<Utc> {
+        self.metadata
+            .history
+            .first()
+            .map_or(self.created_at, |previous| previous.changed_at)
+    }
+
+    /// Record a replaced value that was set at `set_at`, for when the history
+    /// has changed since it was set
+    pub fn record_history(&mut self, value: EncryptedValue, set_at: DateTime<Utc>, limit: usize) {
+        // Derived entries have no stored value worth keeping
+        if limit > 0 && self.metadata.derivation.is_none() {
+            self.metadata.history.insert(
+                0,
+                PreviousValue {
+                    value,
+                    set_at: Some(set_at),
+                    changed_at: Utc::now(),
+                },
+            );
+        }
+        self.metadata.history.truncate(limit);
+    }
+
+    /// Take over the history and attachments of the entry this one overwrites.
+    ///
+    /// Both histories are kept, newest first. An attachment of this entry wins
+    /// over one of the same name on the overwritten entry.
+    pub fn inherit_from(&mut self, previous: Entry, limit: usize) {
+        let set_at = previous.value_set_at();
+        let mut history = previous.metadata.history;
+        history.append(&mut self.metadata.history);
+        history.sort_by_key(|h| std::cmp::Reverse(h.changed_at));
+        self.metadata.history = history;
+
+        for attachment in previous.metadata.attachments {
+            if !self
+                .metadata
+                .attachments
+                .iter()
+                .any(|a| a.name == attachment.name)
+            {
+                self.metadata.attachments.push(attachment);
+            }
+        }
+
+        if previous.metadata.derivation.is_none() {
+            self.record_history(previous.value, set_at, limit);
+        } else {
+            self.metadata.history.truncate(limit);
+        }
+    }
+}
+
 /// Encrypted value wrapper
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedValue {
@@ -33,8 +97,76 @@ pub struct EntryMetadata {
     pub url: Option<String>,
     pub username: Option<String>,
     pub custom_fields: HashMap<String, String>,
+    /// Custom fields encrypted like the main value and masked in `get` output
+    #[serde(default)]
+    pub secret_fields: HashMap<String, EncryptedValue>,
     pub expires_at: Option<DateTime<Utc>>,
     pub auto_type: Option<String>,
+    /// Set for derived entries, whose password is computed instead of stored
+    pub derivation: Option<DerivationParams>,
+    /// Generation policy used when the entry is rotated
+    pub policy: Option<GenerateOptions>,
+    /// Previous values, newest first
+    #[serde(default)]
+    pub history: Vec<PreviousValue>,
+    /// Structured fields of typed entries (see `schema`), keyed by field name
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldValue>,
+    /// Encrypted files attached to the entry
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file attached to an entry, stored as an encrypted blob in the vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    /// Content id of the blob under `attachments/`
+    pub blob: String,
+    pub size: u64,
+    pub added_at: DateTime<Utc>,
+}
+
+/// An entry moved to the vault's trash by `remove`, kept in its stored form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedEntry {
+    pub deleted_at: DateTime<Utc>,
+    pub entry: Entry,
+}
+
+/// Another name for an entry, stored as `<key>.link` next to the entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alias {
+    pub target: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Value of a structured field; sensitive fields are encrypted individually
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldValue {
+    Plain(String),
+    Secret(EncryptedValue),
+}
+
+/// A previous value of an entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousValue {
+    pub value: EncryptedValue,
+    /// When the value was set; not known for values recorded by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_at: Option<DateTime<Utc>>,
+    /// When it was replaced
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Inputs of a derived password (the password itself is never stored)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivationParams {
+    pub site: String,
+    pub login: String,
+    pub counter: u32,
+    pub policy: GenerateOptions,
 }
 
 /// Type of entry
@@ -74,6 +206,25 @@ impl std::fmt::Display for EntryType {
     }
 }
 
+impl std::str::FromStr for EntryType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "password" => Ok(EntryType::Password),
+            "note" => Ok(EntryType::Note),
+            "card" => Ok(EntryType::Card),
+            "identity" => Ok(EntryType::Identity),
+            "securefile" | "file" => Ok(EntryType::SecureFile),
+            "apikey" | "api-key" => Ok(EntryType::ApiKey),
+            "sshkey" | "ssh-key" | "ssh" => Ok(EntryType::SshKey),
+            "database" | "db" => Ok(EntryType::Database),
+            "" => Err("Entry type cannot be empty".to_string()),
+            _ => Ok(EntryType::Custom(s.to_string())),
+        }
+    }
+}
+
 /// Vault configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VaultConfig {
@@ -85,8 +236,28 @@ pub struct VaultConfig {
     pub git_remote: Option<String>,
     pub auto_sync: bool,
     pub auto_lock_minutes: Option<u64>,
+    /// Number of previous values kept per entry (0 disables history)
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// Days a removed entry stays in the trash before it is purged (0 keeps it forever)
+    #[serde(default = "default_trash_retention")]
+    pub trash_retention_days: u32,
+}
+
+fn default_history_limit() -> usize {
+    DEFAULT_HISTORY_LIMIT
+}
+
+fn default_trash_retention() -> u32 {
+    DEFAULT_TRASH_RETENTION_DAYS
 }
 
+/// Default number of previous values kept per entry
+pub const DEFAULT_HISTORY_LIMIT: usize = 10;
+
+/// Default number of days removed entries are kept in the trash
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
 /// Encryption settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptionConfig {
@@ -223,8 +394,9 @@ impl Default for Theme {
     }
 }
 
-/// Password generation options
-#[derive(Debug, Clone)]
+/// Password generation options (also used as a named generation policy)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GenerateOptions {
     pub length: usize,
     pub use_uppercase: bool,
@@ -233,6 +405,16 @@ pub struct GenerateOptions {
     pub use_symbols: bool,
     pub exclude_ambiguous: bool,
     pub custom_charset: Option<String>,
+    // Enabled classes always get at least one character
+    pub min_uppercase: usize,
+    pub min_lowercase: usize,
+    pub min_numbers: usize,
+    pub min_symbols: usize,
+    pub max_repeat: Option<usize>,
+    pub allowed_symbols: Option<String>,
+    pub forbidden_chars: Option<String>,
+    pub prefix: Option<String>,
+    pub first_char: Option<CharClass>,
 }
 
 impl Default for GenerateOptions {
@@ -245,6 +427,100 @@ impl Default for GenerateOptions {
             use_symbols: true,
             exclude_ambiguous: true,
             custom_charset: None,
+            min_uppercase: 0,
+            min_lowercase: 0,
+            min_numbers: 0,
+            min_symbols: 0,
+            max_repeat: None,
+            allowed_symbols: None,
+            forbidden_chars: None,
+            prefix: None,
+            first_char: None,
+        }
+    }
+}
+
+/// Character class used by generation policies. Profiles accept the same
+/// spellings as the command line, as both are parsed by `FromStr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+    Letter,
+}
+
+impl CharClass {
+    /// Check whether a character belongs to this class
+    pub fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Lowercase => c.is_lowercase(),
+            CharClass::Uppercase => c.is_uppercase(),
+            CharClass::Digit => c.is_ascii_digit(),
+            CharClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+            CharClass::Letter => c.is_alphabetic(),
+        }
+    }
+}
+
+impl std::fmt::Display for CharClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CharClass::Lowercase => write!(f, "lowercase"),
+            CharClass::Uppercase => write!(f, "uppercase"),
+            CharClass::Digit => write!(f, "digit"),
+            CharClass::Symbol => write!(f, "symbol"),
+            CharClass::Letter => write!(f, "letter"),
+        }
+    }
+}
+
+impl std::str::FromStr for CharClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lower" | "lowercase" => Ok(CharClass::Lowercase),
+            "upper" | "uppercase" => Ok(CharClass::Uppercase),
+            "digit" | "digits" | "number" | "numbers" => Ok(CharClass::Digit),
+            "symbol" | "symbols" => Ok(CharClass::Symbol),
+            "letter" | "letters" => Ok(CharClass::Letter),
+            _ => Err(format!(
+                "Unknown character class '{}'. Use lower, upper, digit, symbol or letter",
+                s
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CharClass {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Passphrase generation options
+#[derive(Debug, Clone)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    pub add_digit: bool,
+    pub wordlist: Option<PathBuf>,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            add_digit: false,
+            wordlist: None,
         }
     }
 }
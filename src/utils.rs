@@ -1,4 +1,5 @@
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDate, Utc};
 use clipboard::{ClipboardContext, ClipboardProvider};
 use colored::*;
 use dialoguer::{Confirm, Input, Password};
@@ -7,8 +8,9 @@ use std::thread;
 use std::time::Duration;
 
 use crate::crypto::Crypto;
+use crate::schema;
 use crate::storage::Storage;
-use crate::types::MasterKey;
+use crate::types::{Entry, MasterKey};
 
 /// Format error for display
 pub fn format_error(err: &anyhow::Error) -> String {
@@ -144,7 +146,12 @@ pub fn format_tree(entries: &[String], prefix: &str) -> String {
 
     // Build tree structure
     for entry in entries {
-        let parts: Vec<&str> = entry.split('/').collect();
+        // Only the key is a path; an alias target ("key → target") stays attached to it
+        let (key, link) = match entry.split_once(" → ") {
+            Some((key, target)) => (key, format!(" → {}", target)),
+            None => (entry.as_str(), String::new()),
+        };
+        let parts: Vec<&str> = key.split('/').collect();
         if parts.len() == 1 {
             path_tree
                 .entry(String::new())
@@ -152,7 +159,7 @@ pub fn format_tree(entries: &[String], prefix: &str) -> String {
                 .push(entry.clone());
         } else {
             let dir = parts[0].to_string();
-            let rest = parts[1..].join("/");
+            let rest = parts[1..].join("/") + &link;
             path_tree.entry(dir).or_insert_with(Vec::new).push(rest);
         }
     }
@@ -190,11 +197,10 @@ pub fn format_tree(entries: &[String], prefix: &str) -> String {
 }
 
 /// Get master key (from permanent storage) - Passwordless after setup
-pub fn get_master_key(vault_name: Option<String>) -> Result<MasterKey> {
-    let storage = Storage::new(vault_name)?;
-
+pub fn get_master_key(storage: &Storage) -> Result<MasterKey> {
     // Try to load from permanent storage first
     if let Ok(master_key) = storage.load_master_key_permanently() {
+        storage.unlock(&master_key)?;
         return Ok(master_key);
     }
 
@@ -207,6 +213,7 @@ pub fn get_master_key(vault_name: Option<String>) -> Result<MasterKey> {
     let config = storage.load_config()?;
     let salt = config.id.as_bytes();
     let master_key = Crypto::derive_key(&password, salt)?;
+    storage.unlock(&master_key)?;
 
     // Store master key permanently for future use
     storage.store_master_key_permanently(&master_key)?;
@@ -219,6 +226,63 @@ pub fn get_master_key(vault_name: Option<String>) -> Result<MasterKey> {
     Ok(master_key)
 }
 
+/// Get the secret value of an entry (computed for derived entries)
+pub fn entry_value(entry: &Entry, master_key: &MasterKey) -> Result<String> {
+    if let Some(params) = &entry.metadata.derivation {
+        return Crypto::derive_site_password(master_key, params);
+    }
+
+    let decrypted = Crypto::decrypt(&entry.value, master_key)?;
+    String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode value: {}", e))
+}
+
+/// Get a single field of an entry, or its main value when no field is given.
+///
+/// Fields are typed entry fields, then `username`, `url`, `notes` and custom fields.
+pub fn entry_field(entry: &Entry, field: Option<&str>, master_key: &MasterKey) -> Result<String> {
+    let Some(name) = field else {
+        return entry_value(entry, master_key);
+    };
+
+    let metadata = &entry.metadata;
+    if name == "value" {
+        return entry_value(entry, master_key);
+    }
+
+    // Typed fields first: a database's `username` is the one in its schema
+    if let Some(fields) = schema::fields(&metadata.entry_type)
+        && fields[0].name == name
+    {
+        return entry_value(entry, master_key);
+    }
+    if let Some(value) = metadata.fields.get(name) {
+        return schema::read_field(value, master_key);
+    }
+
+    let builtin = match name {
+        "username" => &metadata.username,
+        "url" => &metadata.url,
+        "notes" => &metadata.notes,
+        _ => &None,
+    };
+    if let Some(value) = builtin {
+        return Ok(value.clone());
+    }
+    if let Some(value) = metadata.custom_fields.get(name) {
+        return Ok(value.clone());
+    }
+    if let Some(encrypted) = metadata.secret_fields.get(name) {
+        let decrypted = Crypto::decrypt(encrypted, master_key)?;
+        return String::from_utf8(decrypted).map_err(|e| anyhow!("Failed to decode field: {}", e));
+    }
+    // "password" names the main value unless a field claims it
+    if name == "password" {
+        return entry_value(entry, master_key);
+    }
+
+    Err(anyhow!("Entry '{}' has no field '{}'", entry.key, name))
+}
+
 /// Generate a random session password
 fn generate_session_password() -> String {
     use rand::Rng;
@@ -254,6 +318,47 @@ pub fn parse_key_value(input: &str) -> Result<(String, String)> {
     Ok((parts[0].trim().to_string(), parts[1].trim().to_string()))
 }
 
+/// Parse an expiry given as a date, an RFC 3339 timestamp, a number of days (`90d`) or `never`
+pub fn parse_expiry(input: &str) -> Result<Option<DateTime<Utc>>> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("never") {
+        return Ok(None);
+    }
+
+    if let Some(days) = input.strip_suffix('d')
+        && let Ok(days) = days.parse::<i64>()
+    {
+        return Ok(Some(Utc::now() + chrono::Duration::days(days)));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        return Ok(Some(midnight.and_utc()));
+    }
+
+    DateTime::parse_from_rfc3339(input)
+        .map(|d| Some(d.with_timezone(&Utc)))
+        .map_err(|_| {
+            anyhow!(
+                "Invalid expiry '{}'. Expected YYYY-MM-DD, an RFC 3339 timestamp, Nd or 'never'",
+                input
+            )
+        })
+}
+
+/// Read a value from stdin, dropping the trailing newline
+pub fn read_stdin_value() -> Result<String> {
+    let mut value =
+        io::read_to_string(io::stdin()).map_err(|e| anyhow!("Failed to read from stdin: {}", e))?;
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    Ok(value)
+}
+
 /// Generate QR code
 pub fn generate_qr_code(data: &str) -> Result<String> {
     use qrcode::{QrCode, render::unicode};
@@ -280,3 +385,29 @@ pub fn clear_screen() {
         let _ = io::stdout().flush();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::test_entry;
+    use crate::types::{EntryType, FieldValue};
+
+    #[test]
+    fn test_typed_fields_come_first() {
+        let master_key = MasterKey::new(vec![7; 32]);
+        let mut entry = test_entry("db/prod", b"hunter2", &master_key);
+        entry.metadata.entry_type = EntryType::Database;
+        entry.metadata.username = Some("login-user".to_string());
+        entry.metadata.notes = Some("primary".to_string());
+        entry
+            .metadata
+            .fields
+            .insert("username".to_string(), FieldValue::Plain("app".to_string()));
+
+        let field = |name| entry_field(&entry, Some(name), &master_key).unwrap();
+        assert_eq!(field("username"), "app");
+        assert_eq!(field("password"), "hunter2");
+        assert_eq!(field("notes"), "primary");
+        assert!(entry_field(&entry, Some("missing"), &master_key).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Built-in wordlist (BIP39 English, 2048 words)
+const BUILTIN_WORDLIST: &str = include_str!("wordlists/bip39_english.txt");
+
+/// Minimum number of distinct words a wordlist must contain
+const MIN_WORDS: usize = 16;
+
+/// Wordlist used for passphrase generation
+#[derive(Debug, Clone)]
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    /// Load the built-in wordlist
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_WORDLIST).expect("built-in wordlist is valid")
+    }
+
+    /// Load a wordlist from a file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read wordlist {}: {}", path.display(), e))?;
+        Self::parse(&content)
+    }
+
+    /// Parse a wordlist, one word per line.
+    ///
+    /// Diceware-style lines (`11111\tabacus`) are accepted as well, in which case
+    /// the dice index is ignored. Blank lines, `#` comments and duplicates are skipped.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut seen = HashSet::new();
+        let mut words = Vec::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let first = parts.next().unwrap_or_default();
+            let word = match parts.next() {
+                Some(word) if first.chars().all(|c| c.is_ascii_digit()) => word,
+                Some(_) => return Err(anyhow!("Invalid wordlist line: '{}'", line)),
+                None => first,
+            };
+
+            if seen.insert(word.to_string()) {
+                words.push(word.to_string());
+            }
+        }
+
+        if words.len() < MIN_WORDS {
+            return Err(anyhow!(
+                "Wordlist must contain at least {} distinct words (found {})",
+                MIN_WORDS,
+                words.len()
+            ));
+        }
+
+        Ok(Self { words })
+    }
+
+    /// Get the words in this list
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Entropy contributed by each randomly chosen word, in bits
+    pub fn bits_per_word(&self) -> f64 {
+        (self.words.len() as f64).log2()
+    }
+}